@@ -0,0 +1,409 @@
+use std::{
+    error::Error,
+    fmt::{Debug, Display},
+    str::FromStr,
+};
+
+use chess_common::Player;
+use chess_core::{AcnMoveErr, Board, SelectedMove};
+use chess_parsers::{MoveAnnotation, ParsedGame, PieceMove};
+use iso_8859_1_encoder::Iso8859String;
+
+use crate::{Evaluator, Limits, Score, Searcher};
+
+/// Evaluations are capped at this many centipawns when working out how much
+/// a move lost, so that a move that only turns a crushing advantage into a
+/// winning one isn't counted as losing a lot.
+const EVALUATION_CAP: i32 = 1_000;
+
+/// How deeply to analyse each position, and how many centipawns a move has
+/// to lose against the best move to be called an inaccuracy, a mistake or a
+/// blunder.
+#[derive(Clone, Debug)]
+pub struct AnalysisSettings {
+    pub limits: Limits,
+    pub inaccuracy: i32,
+    pub mistake: i32,
+    pub blunder: i32,
+}
+
+impl Default for AnalysisSettings {
+    fn default() -> Self {
+        Self {
+            limits: Limits::depth(10),
+            inaccuracy: 50,
+            mistake: 100,
+            blunder: 300,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum MoveQuality {
+    Inaccuracy,
+    Mistake,
+    Blunder,
+}
+
+impl MoveQuality {
+    /// The Numeric Annotation Glyph for the quality.
+    pub fn nag(&self) -> u8 {
+        match self {
+            MoveQuality::Inaccuracy => 6,
+            MoveQuality::Mistake => 2,
+            MoveQuality::Blunder => 4,
+        }
+    }
+
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            MoveQuality::Inaccuracy => "?!",
+            MoveQuality::Mistake => "?",
+            MoveQuality::Blunder => "??",
+        }
+    }
+}
+
+impl Display for MoveQuality {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MoveQuality::Inaccuracy => write!(f, "Inaccuracy"),
+            MoveQuality::Mistake => write!(f, "Mistake"),
+            MoveQuality::Blunder => write!(f, "Blunder"),
+        }
+    }
+}
+
+/// What the analysis found about one move of a game.
+#[derive(Clone, Debug)]
+pub struct AnalysedMove {
+    pub move_: SelectedMove,
+    pub notation: PieceMove,
+    pub player: Player,
+    /// The evaluation of the position after the move from white's point of
+    /// view, or None if the game is over there.
+    pub evaluation: Option<Score>,
+    /// How many centipawns the move lost against the best move, from the
+    /// point of view of the player who made it.
+    pub loss: i32,
+    pub quality: Option<MoveQuality>,
+    /// The line the search preferred, starting with the move that should
+    /// have been played. It is empty if the move played was the best one.
+    pub best_line: Vec<PieceMove>,
+}
+
+/// The analysis of every move of a game.
+#[derive(Clone, Debug)]
+pub struct GameAnalysis {
+    pub moves: Vec<AnalysedMove>,
+}
+
+impl GameAnalysis {
+    /// The average number of centipawns the player lost per move, or None
+    /// if they made no moves.
+    pub fn average_centipawn_loss(&self, player: Player) -> Option<f64> {
+        let losses = self
+            .moves
+            .iter()
+            .filter(|move_| move_.player == player)
+            .map(|move_| move_.loss as f64)
+            .collect::<Vec<_>>();
+        if losses.is_empty() {
+            return None;
+        }
+        Some(losses.iter().sum::<f64>() / losses.len() as f64)
+    }
+
+    /// Counts the player's moves of the given quality.
+    pub fn count(&self, player: Player, quality: MoveQuality) -> usize {
+        self.moves
+            .iter()
+            .filter(|move_| move_.player == player && move_.quality == Some(quality))
+            .count()
+    }
+
+    /// Annotates `game`, which has to be the game that was analysed. Each
+    /// move gets an `[%eval]` comment, and each inaccuracy, mistake and
+    /// blunder also gets its NAG, a comment naming the best move and the
+    /// best line as a variation.
+    pub fn annotate(&self, game: &mut ParsedGame) {
+        game.annotations = self
+            .moves
+            .iter()
+            .map(|move_| {
+                let mut comment = move_.evaluation.map(eval_comment).unwrap_or_default();
+                let mut annotation = MoveAnnotation::default();
+                if let (Some(quality), Some(best)) = (move_.quality, move_.best_line.first()) {
+                    annotation.nags.push(quality.nag());
+                    if !comment.is_empty() {
+                        comment.push(' ');
+                    }
+                    comment.push_str(&format!("{quality}. {} was best.", best.to_string()));
+                    annotation.variations.push(move_.best_line.clone());
+                }
+                if !comment.is_empty() {
+                    annotation.comment = Some(comment);
+                }
+                annotation
+            })
+            .collect();
+
+        let annotator = (
+            Iso8859String::try_from("Annotator").expect("ASCII to be valid"),
+            Iso8859String::try_from("chess_ai").expect("ASCII to be valid"),
+        );
+        game.tag_pairs
+            .retain(|(name, _)| name.to_string() != "Annotator");
+        game.tag_pairs.push(annotator);
+    }
+}
+
+/// Formats an evaluation from white's point of view the way `[%eval]`
+/// comments expect, in pawns or as `#n` for a mate in n.
+fn eval_comment(evaluation: Score) -> String {
+    match evaluation {
+        Score::Centipawns(centipawns) => format!("[%eval {:.2}]", centipawns as f64 / 100.0),
+        Score::Mate(moves) => format!("[%eval #{moves}]"),
+    }
+}
+
+/// Evaluates every position of `game` with `searcher`, and judges each
+/// move by how much worse the position after it is than the position after
+/// the best move. The game starts from its FEN tag if it has one.
+pub fn analyse_game<E: Evaluator + Clone + Send>(
+    game: &ParsedGame,
+    searcher: &mut Searcher<E>,
+    settings: &AnalysisSettings,
+) -> Result<GameAnalysis, AnalysisErr> {
+    let (board, played) = replay_game(game)?;
+
+    searcher.new_game();
+    let mut position = board.clone();
+    let mut best = search(searcher, &mut position, settings);
+    let mut moves = Vec::with_capacity(played.len());
+    for (move_, notation) in played.into_iter().zip(game.moves.iter()) {
+        let player = position.player_to_move();
+        let before = position.clone();
+        position
+            .make_move(move_.clone())
+            .expect("the replayed moves to be legal");
+        let after = search(searcher, &mut position, settings);
+
+        let (best_score, best_pv) = best.expect("a position with a move to have a best move");
+        let is_best = best_pv.first() == Some(&move_);
+        let loss = if is_best {
+            0
+        } else {
+            let score_after = match &after {
+                Some((score, _)) => -capped(*score),
+                None if position.is_check() => EVALUATION_CAP,
+                None => 0,
+            };
+            (capped(best_score) - score_after).max(0)
+        };
+
+        let quality = if is_best {
+            None
+        } else if loss >= settings.blunder {
+            Some(MoveQuality::Blunder)
+        } else if loss >= settings.mistake {
+            Some(MoveQuality::Mistake)
+        } else if loss >= settings.inaccuracy {
+            Some(MoveQuality::Inaccuracy)
+        } else {
+            None
+        };
+
+        let best_line = if quality.is_some() {
+            san_line(&before, &best_pv)
+        } else {
+            Vec::new()
+        };
+
+        moves.push(AnalysedMove {
+            move_,
+            notation: notation.clone(),
+            player,
+            evaluation: after
+                .as_ref()
+                .map(|(score, _)| for_white(*score, position.player_to_move())),
+            loss,
+            quality,
+            best_line,
+        });
+        best = after;
+    }
+
+    Ok(GameAnalysis { moves })
+}
+
+/// Replays `game` from its FEN tag, or the starting position if it has
+/// none, returning the position it starts from and its moves. This is done
+/// before any searching, so that a bad move is reported before any time is
+/// spent on the game.
+pub(crate) fn replay_game(game: &ParsedGame) -> Result<(Board, Vec<SelectedMove>), AnalysisErr> {
+    let mut board = starting_board(game)?;
+    let mut played = Vec::with_capacity(game.moves.len());
+    for (ply, notation) in game.moves.iter().enumerate() {
+        let notation = notation.to_string();
+        match play_notation(&mut board, &notation) {
+            Some(move_) => played.push(move_),
+            None => return Err(AnalysisErr::IllegalMove { ply, notation }),
+        }
+    }
+    for _ in played.iter() {
+        board.undo().expect("the game's moves to be undoable");
+    }
+    Ok((board, played))
+}
+
+/// Sets up the position `game` starts from: its FEN tag, or the starting
+/// position if it has none.
+pub(crate) fn starting_board(game: &ParsedGame) -> Result<Board, AnalysisErr> {
+    let fen = game
+        .tag_pairs
+        .iter()
+        .find(|(name, _)| name.to_string() == "FEN")
+        .map(|(_, fen)| fen.to_string());
+    match fen {
+        None => Ok(Board::default()),
+        Some(fen) => Board::from_str(&fen).map_err(|_| AnalysisErr::InvalidFen(fen)),
+    }
+}
+
+/// Plays a move in algebraic notation, returning None if it's illegal. A
+/// legal move with the wrong check marker is still played.
+pub(crate) fn play_notation(board: &mut Board, notation: &str) -> Option<SelectedMove> {
+    match board.make_move_acn(notation) {
+        Ok(move_) => Some(move_),
+        Err(AcnMoveErr::CheckStateMismatch(move_)) => {
+            board
+                .make_move(move_.clone())
+                .expect("a move that was just undone to be legal");
+            Some(move_)
+        }
+        Err(_) => None,
+    }
+}
+
+/// Searches the position, returning the score and principal variation, or
+/// None if the game is over.
+fn search<E: Evaluator + Clone + Send>(
+    searcher: &mut Searcher<E>,
+    board: &mut Board,
+    settings: &AnalysisSettings,
+) -> Option<(Score, Vec<SelectedMove>)> {
+    board.possible_moves().next()?;
+    let result = searcher.search(board, settings.limits.clone());
+    Some((result.score, result.pv))
+}
+
+pub(crate) fn capped(score: Score) -> i32 {
+    match score {
+        Score::Centipawns(centipawns) => centipawns.clamp(-EVALUATION_CAP, EVALUATION_CAP),
+        Score::Mate(moves) if moves > 0 => EVALUATION_CAP,
+        Score::Mate(_) => -EVALUATION_CAP,
+    }
+}
+
+fn for_white(score: Score, player_to_move: Player) -> Score {
+    match (player_to_move, score) {
+        (Player::White, score) => score,
+        (Player::Black, Score::Centipawns(centipawns)) => Score::Centipawns(-centipawns),
+        (Player::Black, Score::Mate(moves)) => Score::Mate(-moves),
+    }
+}
+
+pub(crate) fn san_line(board: &Board, line: &[SelectedMove]) -> Vec<PieceMove> {
+    let mut position =
+        Board::from_str(&board.to_fen_string()).expect("the board's FEN to be valid");
+    for move_ in line {
+        if position.make_move(move_.clone()).is_err() {
+            break;
+        }
+    }
+    position.get_move_history_acn()
+}
+
+pub enum AnalysisErr {
+    InvalidFen(String),
+    /// The move at `ply`, counted from 0, can't be played.
+    IllegalMove {
+        ply: usize,
+        notation: String,
+    },
+}
+
+impl Debug for AnalysisErr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Just defer to the Display impl
+        write!(f, "{}", self)
+    }
+}
+
+impl Display for AnalysisErr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AnalysisErr::InvalidFen(fen) => write!(f, "The game's FEN {fen:?} is invalid"),
+            AnalysisErr::IllegalMove { ply, notation } => {
+                write!(f, "The move {notation} at ply {} is illegal", ply + 1)
+            }
+        }
+    }
+}
+
+impl Error for AnalysisErr {}
+
+#[cfg(test)]
+mod tests {
+    use chess_common::Player;
+    use chess_parsers::parse_pgn;
+    use iso_8859_1_encoder::Iso8859String;
+
+    use super::{analyse_game, AnalysisErr, AnalysisSettings, MoveQuality};
+    use crate::{Limits, Searcher};
+
+    fn settings() -> AnalysisSettings {
+        AnalysisSettings {
+            limits: Limits::depth(3),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn finds_blunders_and_writes_them_as_pgn() {
+        // Black's last move walks into mate instead of defending f7.
+        let pgn = b"1. e4 e5 2. Qh5 Nc6 3. Bc4 Nf6 4. Qxf7# 1-0";
+        let mut game = parse_pgn(pgn).unwrap().remove(0);
+        let mut searcher = Searcher::new();
+        let analysis = analyse_game(&game, &mut searcher, &settings()).unwrap();
+
+        let blunder = &analysis.moves[5];
+        assert_eq!(Some(MoveQuality::Blunder), blunder.quality);
+        assert_eq!(Player::Black, blunder.player);
+        assert!(!blunder.best_line.is_empty());
+        assert_eq!(1, analysis.count(Player::Black, MoveQuality::Blunder));
+        assert_eq!(None, analysis.moves[6].evaluation);
+        assert_eq!(0, analysis.moves[6].loss);
+        assert!(
+            analysis.average_centipawn_loss(Player::Black).unwrap()
+                > analysis.average_centipawn_loss(Player::White).unwrap()
+        );
+
+        analysis.annotate(&mut game);
+        let written: Iso8859String = (&game).into();
+        let written = written.to_string();
+        assert!(written.contains("[Annotator \"chess_ai\"]"), "{written}");
+        assert!(written.contains("Nf6 $4 {[%eval #1] Blunder."), "{written}");
+        assert!(written.contains("was best.} (3... "), "{written}");
+    }
+
+    #[test]
+    fn reports_illegal_moves() {
+        let game = parse_pgn(b"1. e4 e5 2. Ke3 *").unwrap().remove(0);
+        match analyse_game(&game, &mut Searcher::new(), &settings()) {
+            Err(AnalysisErr::IllegalMove { ply: 2, .. }) => {}
+            other => panic!("{:?}", other.map(|analysis| analysis.moves.len())),
+        }
+    }
+}
@@ -0,0 +1,151 @@
+use chess_common::{Piece, PieceKind, Player};
+use chess_core::Board;
+
+use crate::zobrist::{PIECE_KINDS, PLAYERS};
+
+/// Square indexes follow the board's bitboard layout: 0 is a1, 7 is h1
+/// and 63 is h8.
+const NOT_A_FILE: u64 = 0xFEFE_FEFE_FEFE_FEFE;
+const NOT_H_FILE: u64 = 0x7F7F_7F7F_7F7F_7F7F;
+const NOT_AB_FILE: u64 = 0xFCFC_FCFC_FCFC_FCFC;
+const NOT_GH_FILE: u64 = 0x3F3F_3F3F_3F3F_3F3F;
+
+/// The squares attacked by a knight on `square`.
+pub(crate) fn knight_attacks(square: usize) -> u64 {
+    let bit = 1_u64 << square;
+    ((bit << 17) & NOT_A_FILE)
+        | ((bit << 15) & NOT_H_FILE)
+        | ((bit << 10) & NOT_AB_FILE)
+        | ((bit << 6) & NOT_GH_FILE)
+        | ((bit >> 17) & NOT_H_FILE)
+        | ((bit >> 15) & NOT_A_FILE)
+        | ((bit >> 10) & NOT_GH_FILE)
+        | ((bit >> 6) & NOT_AB_FILE)
+}
+
+/// The squares attacked by a king on `square`.
+pub(crate) fn king_attacks(square: usize) -> u64 {
+    let bit = 1_u64 << square;
+    let sideways = ((bit << 1) & NOT_A_FILE) | ((bit >> 1) & NOT_H_FILE);
+    let row = bit | sideways;
+    sideways | (row << 8) | (row >> 8)
+}
+
+/// The squares attacked by the given player's pawns.
+pub(crate) fn pawn_attacks(player: Player, pawns: u64) -> u64 {
+    match player {
+        Player::White => ((pawns << 7) & NOT_H_FILE) | ((pawns << 9) & NOT_A_FILE),
+        Player::Black => ((pawns >> 9) & NOT_H_FILE) | ((pawns >> 7) & NOT_A_FILE),
+    }
+}
+
+/// The squares attacked by a bishop on `square`, stopping at (and
+/// including) the first occupied square in each direction.
+pub(crate) fn bishop_attacks(square: usize, occupied: u64) -> u64 {
+    slide(square, occupied, &[(1, 1), (1, -1), (-1, 1), (-1, -1)])
+}
+
+/// The squares attacked by a rook on `square`, stopping at (and
+/// including) the first occupied square in each direction.
+pub(crate) fn rook_attacks(square: usize, occupied: u64) -> u64 {
+    slide(square, occupied, &[(1, 0), (-1, 0), (0, 1), (0, -1)])
+}
+
+/// The squares attacked by a piece of the given kind on `square`. Pawns
+/// are left out, since their attacks depend on their colour; use
+/// [`pawn_attacks`] for them.
+pub(crate) fn piece_attacks(kind: PieceKind, square: usize, occupied: u64) -> u64 {
+    match kind {
+        PieceKind::Knight => knight_attacks(square),
+        PieceKind::Bishop => bishop_attacks(square, occupied),
+        PieceKind::Rook => rook_attacks(square, occupied),
+        PieceKind::Queen => bishop_attacks(square, occupied) | rook_attacks(square, occupied),
+        PieceKind::King => king_attacks(square),
+        PieceKind::Pawn => 0,
+    }
+}
+
+fn slide(square: usize, occupied: u64, directions: &[(i32, i32)]) -> u64 {
+    let file = (square % 8) as i32;
+    let rank = (square / 8) as i32;
+    let mut attacks = 0;
+    for (file_step, rank_step) in directions {
+        let mut target_file = file + file_step;
+        let mut target_rank = rank + rank_step;
+        while (0..8).contains(&target_file) && (0..8).contains(&target_rank) {
+            let bit = 1_u64 << (target_rank * 8 + target_file);
+            attacks |= bit;
+            if occupied & bit != 0 {
+                break;
+            }
+            target_file += file_step;
+            target_rank += rank_step;
+        }
+    }
+    attacks
+}
+
+/// Gets the bitboard of every square occupied by the given player.
+pub(crate) fn occupied_by(board: &Board, player: Player) -> u64 {
+    PIECE_KINDS.iter().fold(0, |occupied, kind| {
+        occupied | board.bitboard_for(&Piece::new(player, *kind))
+    })
+}
+
+/// Gets the bitboard of every occupied square.
+pub(crate) fn occupied(board: &Board) -> u64 {
+    occupied_by(board, Player::White) | occupied_by(board, Player::Black)
+}
+
+/// Gets every piece of either player that attacks `square`, treating only
+/// the squares in `occupied` as blocking sliding pieces. Pieces that are
+/// not in `occupied` are ignored, which lets callers remove pieces that
+/// have already been traded off.
+pub(crate) fn attackers_to(board: &Board, square: usize, occupied: u64) -> u64 {
+    let bishop_rays = bishop_attacks(square, occupied);
+    let rook_rays = rook_attacks(square, occupied);
+    let square_bit = 1_u64 << square;
+
+    let mut attackers = 0;
+    for player in PLAYERS {
+        let pieces = |kind| board.bitboard_for(&Piece::new(player, kind));
+        // A pawn of `player` attacks the square if a pawn of the other
+        // player standing on it would attack the pawn.
+        attackers |= pawn_attacks(player.other_player(), square_bit) & pieces(PieceKind::Pawn);
+        attackers |= knight_attacks(square) & pieces(PieceKind::Knight);
+        attackers |= king_attacks(square) & pieces(PieceKind::King);
+        attackers |= bishop_rays & (pieces(PieceKind::Bishop) | pieces(PieceKind::Queen));
+        attackers |= rook_rays & (pieces(PieceKind::Rook) | pieces(PieceKind::Queen));
+    }
+
+    attackers & occupied
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{bishop_attacks, king_attacks, knight_attacks, pawn_attacks, rook_attacks};
+    use chess_common::Player;
+
+    const A1: usize = 0;
+    const D4: usize = 27;
+    const H8: usize = 63;
+
+    #[test]
+    fn leaper_attacks_respect_board_edges() {
+        assert_eq!(2, knight_attacks(A1).count_ones());
+        assert_eq!(8, knight_attacks(D4).count_ones());
+        assert_eq!(3, king_attacks(H8).count_ones());
+        assert_eq!(8, king_attacks(D4).count_ones());
+        // a2 and h2 pawns each attack a single square.
+        assert_eq!(1 << 17, pawn_attacks(Player::White, 1 << 8));
+        assert_eq!(1 << 22, pawn_attacks(Player::White, 1 << 15));
+    }
+
+    #[test]
+    fn sliders_stop_at_blockers() {
+        assert_eq!(14, rook_attacks(A1, 0).count_ones());
+        // A blocker on a3 leaves a2, a3 and the whole first rank.
+        assert_eq!(9, rook_attacks(A1, 1 << 16).count_ones());
+        assert_eq!(13, bishop_attacks(D4, 0).count_ones());
+    }
+}
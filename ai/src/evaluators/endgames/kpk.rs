@@ -0,0 +1,248 @@
+use std::sync::OnceLock;
+
+use chess_common::{PieceKind, Player};
+use chess_core::Board;
+
+use super::{distance, king_square, pieces, KNOWN_WIN};
+use crate::attacks::{king_attacks, pawn_attacks};
+
+/// The pawn is always on one of the files a to d, mirroring the board if
+/// it isn't, and on one of the ranks 2 to 7.
+const PAWN_SQUARES: usize = 24;
+const POSITIONS: usize = 2 * 64 * 64 * PAWN_SQUARES;
+
+/// Results are combined as bit flags while the bitbase is being built, so
+/// that a set of successors can be checked for any win or any draw.
+const INVALID: u8 = 0;
+const UNKNOWN: u8 = 1;
+const DRAW: u8 = 2;
+const WIN: u8 = 4;
+
+/// Scores king and pawn against king exactly, from the strong side's point
+/// of view: a known win plus a bonus for the pawn's progress, or 0 if the
+/// weak side can draw.
+pub(super) fn evaluate(board: &Board, strong: Player) -> Option<i32> {
+    let weak = strong.other_player();
+    let pawn = pieces(board, strong, PieceKind::Pawn).trailing_zeros() as usize;
+    let to_move = if board.player_to_move() == strong {
+        Player::White
+    } else {
+        Player::Black
+    };
+
+    let orient = |square: usize| match strong {
+        Player::White => square,
+        Player::Black => square ^ 56,
+    };
+    let (strong_king, weak_king, pawn) = (
+        orient(king_square(board, strong)),
+        orient(king_square(board, weak)),
+        orient(pawn),
+    );
+
+    if !is_win(to_move, strong_king, weak_king, pawn) {
+        return Some(0);
+    }
+    Some(KNOWN_WIN + 100 + 20 * (pawn / 8) as i32)
+}
+
+/// Looks up whether white wins with a king on `white_king` and a pawn on
+/// `pawn` against black's king on `black_king`, with `to_move` to move.
+fn is_win(to_move: Player, white_king: usize, black_king: usize, pawn: usize) -> bool {
+    let bitbase = BITBASE.get_or_init(Bitbase::generate);
+    let (white_king, black_king, pawn) = if pawn % 8 > 3 {
+        (white_king ^ 7, black_king ^ 7, pawn ^ 7)
+    } else {
+        (white_king, black_king, pawn)
+    };
+    bitbase.is_win(index(to_move, white_king, black_king, pawn))
+}
+
+static BITBASE: OnceLock<Bitbase> = OnceLock::new();
+
+/// Whether white wins each position of king and pawn against king, one
+/// bit per position. It is built by retrograde analysis the first time it
+/// is needed.
+struct Bitbase {
+    wins: Vec<u64>,
+}
+
+impl Bitbase {
+    fn generate() -> Self {
+        let mut results = (0..POSITIONS).map(initial_result).collect::<Vec<_>>();
+
+        // Keep classifying positions from their successors until nothing
+        // changes. Whatever is still unknown then can't be won.
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for index in 0..POSITIONS {
+                if results[index] == UNKNOWN {
+                    results[index] = classify(&results, index);
+                    changed |= results[index] != UNKNOWN;
+                }
+            }
+        }
+
+        let mut wins = vec![0; POSITIONS.div_ceil(64)];
+        for (index, result) in results.into_iter().enumerate() {
+            if result == WIN {
+                wins[index / 64] |= 1 << (index % 64);
+            }
+        }
+        Self { wins }
+    }
+
+    fn is_win(&self, index: usize) -> bool {
+        self.wins[index / 64] & (1 << (index % 64)) != 0
+    }
+}
+
+fn index(to_move: Player, white_king: usize, black_king: usize, pawn: usize) -> usize {
+    let pawn_index = pawn % 8 + 4 * (pawn / 8 - 1);
+    let side = match to_move {
+        Player::White => 0,
+        Player::Black => 1,
+    };
+    side + 2 * (white_king + 64 * (black_king + 64 * pawn_index))
+}
+
+/// The inverse of [`index`].
+fn position(index: usize) -> (Player, usize, usize, usize) {
+    let to_move = if index.is_multiple_of(2) {
+        Player::White
+    } else {
+        Player::Black
+    };
+    let index = index / 2;
+    let pawn_index = index / (64 * 64);
+    (
+        to_move,
+        index % 64,
+        index / 64 % 64,
+        pawn_index % 4 + 8 * (pawn_index / 4 + 1),
+    )
+}
+
+/// Settles the positions that can be decided without looking at any
+/// moves, and rules out the impossible ones.
+fn initial_result(index: usize) -> u8 {
+    let (to_move, white_king, black_king, pawn) = position(index);
+    let pawn_bit = 1_u64 << pawn;
+    let black_king_bit = 1_u64 << black_king;
+
+    if distance(white_king, black_king) <= 1
+        || white_king == pawn
+        || black_king == pawn
+        // Black can't be in check with white to move.
+        || (to_move == Player::White && pawn_attacks(Player::White, pawn_bit) & black_king_bit != 0)
+    {
+        return INVALID;
+    }
+
+    let promotion = pawn + 8;
+    if to_move == Player::White
+        && pawn / 8 == 6
+        && white_king != promotion
+        && (distance(black_king, promotion) > 1 || distance(white_king, promotion) == 1)
+    {
+        // The pawn promotes and the new queen can't be taken.
+        return WIN;
+    }
+
+    let black_moves = king_attacks(black_king) & !king_attacks(white_king);
+    if to_move == Player::Black
+        && (black_moves & !pawn_attacks(Player::White, pawn_bit) == 0
+            || black_moves & pawn_bit != 0)
+    {
+        // Stalemate, or the pawn is taken.
+        return DRAW;
+    }
+
+    UNKNOWN
+}
+
+/// Classifies a position from the results of the positions it can move
+/// to. White wins if any move wins, and black draws if any move draws.
+fn classify(results: &[u8], index: usize) -> u8 {
+    let (to_move, white_king, black_king, pawn) = position(index);
+    let mut successors = INVALID;
+
+    match to_move {
+        Player::White => {
+            let mut moves = king_attacks(white_king);
+            while moves != 0 {
+                let square = moves.trailing_zeros() as usize;
+                moves &= moves - 1;
+                successors |= results[self::index(Player::Black, square, black_king, pawn)];
+            }
+
+            // A pawn on the seventh rank has been dealt with already.
+            if pawn / 8 < 6 {
+                let push = pawn + 8;
+                successors |= results[self::index(Player::Black, white_king, black_king, push)];
+                if pawn / 8 == 1 && push != white_king && push != black_king {
+                    successors |=
+                        results[self::index(Player::Black, white_king, black_king, push + 8)];
+                }
+            }
+
+            if successors & WIN != 0 {
+                WIN
+            } else if successors & UNKNOWN != 0 {
+                UNKNOWN
+            } else {
+                DRAW
+            }
+        }
+        Player::Black => {
+            let mut moves = king_attacks(black_king);
+            while moves != 0 {
+                let square = moves.trailing_zeros() as usize;
+                moves &= moves - 1;
+                successors |= results[self::index(Player::White, white_king, square, pawn)];
+            }
+
+            if successors & DRAW != 0 {
+                DRAW
+            } else if successors & UNKNOWN != 0 {
+                UNKNOWN
+            } else {
+                WIN
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use chess_common::Player;
+    use chess_core::Board;
+
+    use super::evaluate;
+
+    fn is_win(fen: &str, strong: Player) -> bool {
+        evaluate(&Board::from_str(fen).unwrap(), strong).unwrap() > 0
+    }
+
+    #[test]
+    fn knows_who_has_the_opposition() {
+        assert!(!is_win("8/4k3/8/4K3/4P3/8/8/8 w - - 0 1", Player::White));
+        assert!(is_win("8/4k3/8/4K3/4P3/8/8/8 b - - 0 1", Player::White));
+        // The same positions with the colours swapped.
+        assert!(!is_win("8/8/8/4p3/4k3/8/4K3/8 b - - 0 1", Player::Black));
+        assert!(is_win("8/8/8/4p3/4k3/8/4K3/8 w - - 0 1", Player::Black));
+    }
+
+    #[test]
+    fn knows_rook_pawns_and_runaway_pawns() {
+        // The black king reaches the corner in front of the rook pawn.
+        assert!(!is_win("k7/8/K7/P7/8/8/8/8 w - - 0 1", Player::White));
+        assert!(!is_win("7k/8/7K/7P/8/8/8/8 w - - 0 1", Player::White));
+        // The black king is too far away to catch the pawn.
+        assert!(is_win("8/8/8/8/P7/8/7k/K7 w - - 0 1", Player::White));
+        assert!(!is_win("8/8/8/3k4/P7/8/8/K7 b - - 0 1", Player::White));
+    }
+}
@@ -0,0 +1,88 @@
+use chess_common::{PieceKind, Player};
+use chess_core::Board;
+
+use super::{
+    distance, is_light, king_square, kpk, material, pieces, Endgames, MaterialSignature, KNOWN_WIN,
+    SIGNATURE_KINDS,
+};
+
+pub(super) fn add_evaluations(endgames: &mut Endgames) {
+    let signature = |text: &str| {
+        text.parse::<MaterialSignature>()
+            .expect("a valid signature")
+    };
+    endgames.add_evaluation(signature("KPK"), kpk::evaluate);
+    endgames.add_evaluation(signature("KBNK"), evaluate_kbnk);
+    endgames.add_evaluation(signature("KNNK"), |_, _| Some(0));
+    endgames.add_general_evaluation(evaluate_kxk);
+}
+
+/// Against a bare king, any queen, rook, pair of bishops on different
+/// colours or bishop and knight mates. The weak king is driven to the edge
+/// and the strong king brought up to help.
+fn evaluate_kxk(board: &Board, strong: Player) -> Option<i32> {
+    let weak = strong.other_player();
+    let signature = MaterialSignature::of(board);
+    if !signature.is_bare_king(weak) {
+        return None;
+    }
+
+    let count = |kind| signature.count(strong, kind);
+    let bishops = pieces(board, strong, PieceKind::Bishop);
+    let has_bishop_pair = bishops & LIGHT_SQUARES != 0 && bishops & !LIGHT_SQUARES != 0;
+    let can_mate = count(PieceKind::Queen) > 0
+        || count(PieceKind::Rook) > 0
+        || has_bishop_pair
+        || (count(PieceKind::Bishop) > 0 && count(PieceKind::Knight) > 0);
+    if !can_mate {
+        return None;
+    }
+
+    let weak_king = king_square(board, weak);
+    let strong_king = king_square(board, strong);
+    Some(
+        KNOWN_WIN
+            + material(&signature, strong, &SIGNATURE_KINDS)
+            + push_to_edge(weak_king)
+            + push_close(strong_king, weak_king),
+    )
+}
+
+/// Bishop and knight can only mate in a corner the bishop controls, so the
+/// weak king is driven towards the nearer of those two corners.
+fn evaluate_kbnk(board: &Board, strong: Player) -> Option<i32> {
+    let weak = strong.other_player();
+    let bishop = pieces(board, strong, PieceKind::Bishop).trailing_zeros() as usize;
+    // a1 and h8 are dark, a8 and h1 are light.
+    let corners = if is_light(bishop) { [56, 7] } else { [0, 63] };
+
+    let weak_king = king_square(board, weak);
+    let strong_king = king_square(board, strong);
+    let corner_distance = corners
+        .iter()
+        .map(|corner| distance(weak_king, *corner))
+        .min()
+        .expect("two corners");
+
+    Some(
+        KNOWN_WIN
+            + material(&MaterialSignature::of(board), strong, &SIGNATURE_KINDS)
+            + push_to_edge(weak_king)
+            + 40 * (7 - corner_distance)
+            + push_close(strong_king, weak_king),
+    )
+}
+
+const LIGHT_SQUARES: u64 = 0x55AA_55AA_55AA_55AA;
+
+/// Grows from 0 in the centre to 120 in the corners.
+fn push_to_edge(square: usize) -> i32 {
+    let from_centre = |line: usize| line.abs_diff(3).min(line.abs_diff(4)) as i32;
+    20 * (from_centre(square % 8) + from_centre(square / 8))
+}
+
+/// Grows as the two squares get closer, up to 100 when they are next to
+/// each other.
+fn push_close(from: usize, to: usize) -> i32 {
+    20 * (6 - distance(from, to))
+}
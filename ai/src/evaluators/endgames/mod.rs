@@ -0,0 +1,381 @@
+mod kpk;
+mod mates;
+mod scaling;
+
+use std::{
+    collections::HashMap,
+    fmt::{Debug, Display},
+    str::FromStr,
+};
+
+use chess_common::{Piece, PieceKind, Player};
+use chess_core::Board;
+
+/// The score given to endgames that are known to be won, on top of the
+/// winning side's material. It keeps any won endgame ahead of every
+/// position that isn't, while staying well clear of mate scores.
+pub(crate) const KNOWN_WIN: i32 = 10_000;
+
+/// The scale factor that leaves the evaluation as it is. A scale factor
+/// of 0 makes the position a dead draw.
+pub const SCALE_NORMAL: i32 = 64;
+
+/// The pieces other than kings, in the order they are written in
+/// signatures after the king.
+const SIGNATURE_KINDS: [PieceKind; 5] = [
+    PieceKind::Queen,
+    PieceKind::Rook,
+    PieceKind::Bishop,
+    PieceKind::Knight,
+    PieceKind::Pawn,
+];
+
+/// Rough piece values, indexed by [`PieceKind`], used to weigh material
+/// within the endgame functions.
+const PIECE_VALUES: [i32; 5] = [100, 300, 300, 500, 900];
+
+/// Scores a position from the strong side's point of view, or returns None
+/// to leave it to the usual evaluation.
+pub type EvaluationFn = fn(&Board, Player) -> Option<i32>;
+
+/// Gets how much of the usual evaluation to keep when the strong side is
+/// ahead, out of [`SCALE_NORMAL`], or returns None if it knows nothing
+/// about the position.
+pub type ScaleFn = fn(&Board, Player) -> Option<i32>;
+
+/// The pieces each side has, which identifies the endgame being played.
+///
+/// Signatures are written with the strong side's pieces first, each side
+/// starting with its king, such as `KBNK` or `KRPKR`.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct MaterialSignature {
+    /// The number of pieces of each kind each player has, indexed by
+    /// [`Player::as_index`] and then by [`PieceKind`], leaving out kings.
+    counts: [[u8; 5]; 2],
+}
+
+impl MaterialSignature {
+    pub fn of(board: &Board) -> Self {
+        let mut counts = [[0; 5]; 2];
+        for player in [Player::White, Player::Black] {
+            for kind in SIGNATURE_KINDS {
+                counts[player.as_index()][kind as usize] =
+                    board.bitboard_for(&Piece::new(player, kind)).count_ones() as u8;
+            }
+        }
+        Self { counts }
+    }
+
+    pub fn count(&self, player: Player, kind: PieceKind) -> u32 {
+        match kind {
+            PieceKind::King => 1,
+            _ => self.counts[player.as_index()][kind as usize] as u32,
+        }
+    }
+
+    /// Gets whether the player has nothing left but their king.
+    pub fn is_bare_king(&self, player: Player) -> bool {
+        self.counts[player.as_index()] == [0; 5]
+    }
+
+    /// Gets the same material with the players swapped.
+    pub fn flipped(&self) -> Self {
+        Self {
+            counts: [self.counts[1], self.counts[0]],
+        }
+    }
+}
+
+impl FromStr for MaterialSignature {
+    type Err = ();
+
+    /// Reads a signature such as `KBNK`, with white's pieces first.
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let text = text.strip_prefix('K').ok_or(())?;
+        let (white, black) = text.split_once('K').ok_or(())?;
+
+        let mut counts = [[0; 5]; 2];
+        for (player, pieces) in [(Player::White, white), (Player::Black, black)] {
+            for piece in pieces.chars() {
+                match PieceKind::try_from(piece) {
+                    Ok(PieceKind::King) | Err(_) => return Err(()),
+                    Ok(kind) => counts[player.as_index()][kind as usize] += 1,
+                }
+            }
+        }
+        Ok(Self { counts })
+    }
+}
+
+impl Display for MaterialSignature {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for player in [Player::White, Player::Black] {
+            write!(f, "K")?;
+            for kind in SIGNATURE_KINDS {
+                for _ in 0..self.count(player, kind) {
+                    write!(f, "{}", kind.as_char())?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Specialised knowledge of endgames, used by
+/// [`TaperedEvaluator`](super::TaperedEvaluator) in place of or on top of
+/// its usual evaluation.
+///
+/// Evaluation functions replace the evaluation entirely, such as by
+/// driving the losing king towards a mating corner. Scale functions keep
+/// the evaluation but pull it towards a draw, for endgames that look
+/// better on material than they are.
+///
+/// Most functions are registered for an exact [`MaterialSignature`] with
+/// the strong side written first, and apply with either colour as the
+/// strong side. General functions are tried in the order they were added
+/// whenever there is no function for the material or it returns None, and
+/// decide for themselves whether they apply.
+#[derive(Clone)]
+pub struct Endgames {
+    evaluations: HashMap<MaterialSignature, EvaluationFn>,
+    general_evaluations: Vec<EvaluationFn>,
+    scales: HashMap<MaterialSignature, ScaleFn>,
+    general_scales: Vec<ScaleFn>,
+}
+
+impl Default for Endgames {
+    /// Gets the endgame knowledge built into the engine.
+    fn default() -> Self {
+        let mut endgames = Self::empty();
+        mates::add_evaluations(&mut endgames);
+        scaling::add_scales(&mut endgames);
+        endgames
+    }
+}
+
+impl Endgames {
+    /// Creates endgame knowledge that knows nothing.
+    pub fn empty() -> Self {
+        Self {
+            evaluations: HashMap::new(),
+            general_evaluations: Vec::new(),
+            scales: HashMap::new(),
+            general_scales: Vec::new(),
+        }
+    }
+
+    /// Evaluates positions with the given material using `evaluation`,
+    /// replacing any function already registered for it.
+    pub fn add_evaluation(&mut self, signature: MaterialSignature, evaluation: EvaluationFn) {
+        self.evaluations.insert(signature, evaluation);
+    }
+
+    /// Tries `evaluation` with each player as the strong side on material
+    /// that has no evaluation function of its own.
+    pub fn add_general_evaluation(&mut self, evaluation: EvaluationFn) {
+        self.general_evaluations.push(evaluation);
+    }
+
+    /// Scales the evaluation of positions with the given material using
+    /// `scale`, replacing any function already registered for it.
+    pub fn add_scale(&mut self, signature: MaterialSignature, scale: ScaleFn) {
+        self.scales.insert(signature, scale);
+    }
+
+    /// Tries `scale` on material that has no scale function of its own.
+    pub fn add_general_scale(&mut self, scale: ScaleFn) {
+        self.general_scales.push(scale);
+    }
+
+    /// Scores the position from white's point of view, if anything is known
+    /// about its material.
+    pub(crate) fn evaluate(&self, board: &Board, signature: &MaterialSignature) -> Option<i32> {
+        let from_white = |score: i32, strong: Player| match strong {
+            Player::White => score,
+            Player::Black => -score,
+        };
+
+        if let Some((evaluation, strong)) = lookup(&self.evaluations, signature) {
+            if let Some(score) = evaluation(board, strong) {
+                return Some(from_white(score, strong));
+            }
+        }
+
+        self.general_evaluations.iter().find_map(|evaluation| {
+            [Player::White, Player::Black]
+                .into_iter()
+                .find_map(|strong| evaluation(board, strong).map(|score| from_white(score, strong)))
+        })
+    }
+
+    /// Gets the scale factor for a position that `strong` is ahead in.
+    pub(crate) fn scale(
+        &self,
+        board: &Board,
+        signature: &MaterialSignature,
+        strong: Player,
+    ) -> i32 {
+        let specific = match self.scales.get(&oriented(signature, strong)) {
+            Some(scale) => scale(board, strong),
+            None => None,
+        };
+        specific
+            .or_else(|| {
+                self.general_scales
+                    .iter()
+                    .find_map(|scale| scale(board, strong))
+            })
+            .unwrap_or(SCALE_NORMAL)
+    }
+}
+
+impl Debug for Endgames {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut evaluations = self
+            .evaluations
+            .keys()
+            .map(|key| key.to_string())
+            .collect::<Vec<_>>();
+        let mut scales = self
+            .scales
+            .keys()
+            .map(|key| key.to_string())
+            .collect::<Vec<_>>();
+        evaluations.sort();
+        scales.sort();
+        f.debug_struct("Endgames")
+            .field("evaluations", &evaluations)
+            .field("general_evaluations", &self.general_evaluations.len())
+            .field("scales", &scales)
+            .field("general_scales", &self.general_scales.len())
+            .finish()
+    }
+}
+
+/// Finds the function registered for the material, and which player it
+/// treats as the strong side.
+fn lookup<F: Copy>(
+    functions: &HashMap<MaterialSignature, F>,
+    signature: &MaterialSignature,
+) -> Option<(F, Player)> {
+    functions
+        .get(signature)
+        .map(|function| (*function, Player::White))
+        .or_else(|| {
+            functions
+                .get(&signature.flipped())
+                .map(|function| (*function, Player::Black))
+        })
+}
+
+/// Gets the signature with `strong`'s pieces first.
+fn oriented(signature: &MaterialSignature, strong: Player) -> MaterialSignature {
+    match strong {
+        Player::White => *signature,
+        Player::Black => signature.flipped(),
+    }
+}
+
+fn pieces(board: &Board, player: Player, kind: PieceKind) -> u64 {
+    board.bitboard_for(&Piece::new(player, kind))
+}
+
+fn king_square(board: &Board, player: Player) -> usize {
+    pieces(board, player, PieceKind::King).trailing_zeros() as usize
+}
+
+/// Gets the rough value of the player's pieces of the given kinds.
+fn material(signature: &MaterialSignature, player: Player, kinds: &[PieceKind]) -> i32 {
+    kinds
+        .iter()
+        .map(|kind| PIECE_VALUES[*kind as usize] * signature.count(player, *kind) as i32)
+        .sum()
+}
+
+/// The number of king moves between two squares.
+fn distance(from: usize, to: usize) -> i32 {
+    let files = (from % 8).abs_diff(to % 8);
+    let ranks = (from / 8).abs_diff(to / 8);
+    files.max(ranks) as i32
+}
+
+/// Gets whether the square is a light square. a1 is dark.
+fn is_light(square: usize) -> bool {
+    (square % 8 + square / 8) % 2 == 1
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use chess_common::{PieceKind, Player};
+    use chess_core::Board;
+
+    use super::{Endgames, MaterialSignature};
+    use crate::{Evaluator, TaperedEvaluator};
+
+    fn evaluate(fen: &str) -> i32 {
+        TaperedEvaluator::default().evaluate(&Board::from_str(fen).unwrap())
+    }
+
+    #[test]
+    fn reads_material_signatures() {
+        let signature = "KRPKB".parse::<MaterialSignature>().unwrap();
+        assert_eq!(1, signature.count(Player::White, PieceKind::Rook));
+        assert_eq!(1, signature.count(Player::Black, PieceKind::Bishop));
+        assert!(!signature.is_bare_king(Player::Black));
+        assert_eq!("KBKRP", signature.flipped().to_string());
+
+        let board = Board::from_str("8/8/8/4k3/8/8/3PB3/2N1K3 w - - 0 1").unwrap();
+        assert_eq!("KBNPK", MaterialSignature::of(&board).to_string());
+        assert!("KBN".parse::<MaterialSignature>().is_err());
+        assert!("KXK".parse::<MaterialSignature>().is_err());
+    }
+
+    #[test]
+    fn drives_the_bare_king_to_the_edge() {
+        let centre = evaluate("8/8/8/3k4/8/3K4/8/Q7 w - - 0 1");
+        let edge = evaluate("3k4/8/3K4/8/8/8/8/Q7 w - - 0 1");
+        assert!(centre > 5_000);
+        assert!(edge > centre);
+
+        // The same position with colours swapped, from black's side.
+        assert_eq!(edge, evaluate("q7/8/8/8/8/3k4/8/3K4 b - - 0 1"));
+    }
+
+    #[test]
+    fn drives_the_bare_king_to_the_bishops_corner() {
+        // The bishop on c1 controls the dark corners, a1 and h8.
+        let right_corner = evaluate("8/8/8/8/8/3K4/8/k1B1N3 b - - 0 1");
+        let wrong_corner = evaluate("8/8/8/8/8/3K4/8/2B1N2k b - - 0 1");
+        assert!(right_corner < wrong_corner);
+        assert!(wrong_corner < -5_000);
+    }
+
+    #[test]
+    fn scores_insufficient_material_as_a_draw() {
+        assert_eq!(0, evaluate("8/8/8/4k3/8/8/8/4KN2 w - - 0 1"));
+        assert_eq!(0, evaluate("8/8/8/4k3/8/8/8/3BK3 w - - 0 1"));
+        assert_eq!(0, evaluate("8/8/8/4k3/8/8/8/1N2KN2 w - - 0 1"));
+    }
+
+    #[test]
+    fn evaluator_can_do_without_endgame_knowledge() {
+        let board = Board::from_str("8/8/8/4k3/8/8/8/4KN2 w - - 0 1").unwrap();
+        let mut evaluator = TaperedEvaluator::default();
+        evaluator.set_endgames(Endgames::empty());
+        assert!(evaluator.evaluate(&board) > 0);
+    }
+
+    #[test]
+    fn accepts_new_endgames() {
+        // Rook endgames without pawns are drawn.
+        let mut endgames = Endgames::default();
+        endgames.add_evaluation("KRKR".parse().unwrap(), |_, _| Some(0));
+        let mut evaluator = TaperedEvaluator::default();
+        evaluator.set_endgames(endgames);
+
+        let board = Board::from_str("r7/8/8/4k3/8/8/8/4K2R b - - 0 1").unwrap();
+        assert_eq!(0, evaluator.evaluate(&board));
+    }
+}
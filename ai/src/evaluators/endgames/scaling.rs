@@ -0,0 +1,156 @@
+use chess_common::{PieceKind, Player};
+use chess_core::Board;
+
+use super::{
+    distance, is_light, king_square, material, pieces, Endgames, MaterialSignature, PIECE_VALUES,
+    SCALE_NORMAL,
+};
+use crate::evaluators::pawns::FILE_A;
+
+/// How much of the evaluation is kept with bishops on opposite colours and
+/// nothing else but pawns, since the weak side can usually blockade on the
+/// squares the strong side's bishop doesn't control.
+const OPPOSITE_BISHOPS_SCALE: i32 = 24;
+
+/// Rough values of the minor pieces and the rook, used to judge whether
+/// the strong side has enough to win without pawns.
+const MINOR_VALUE: i32 = PIECE_VALUES[PieceKind::Knight as usize];
+const ROOK_VALUE: i32 = PIECE_VALUES[PieceKind::Rook as usize];
+
+/// The pieces other than pawns and the king.
+const PIECES: [PieceKind; 4] = [
+    PieceKind::Queen,
+    PieceKind::Rook,
+    PieceKind::Bishop,
+    PieceKind::Knight,
+];
+
+pub(super) fn add_scales(endgames: &mut Endgames) {
+    endgames.add_general_scale(scale_rook_pawn_fortress);
+    endgames.add_general_scale(scale_opposite_bishops);
+    endgames.add_general_scale(scale_without_pawns);
+}
+
+/// Rook pawns can't be promoted if the weak king gets in front of them,
+/// unless the strong side has a bishop that controls the promotion square.
+fn scale_rook_pawn_fortress(board: &Board, strong: Player) -> Option<i32> {
+    let weak = strong.other_player();
+    let signature = MaterialSignature::of(board);
+    let pawns = pieces(board, strong, PieceKind::Pawn);
+    let bishops = signature.count(strong, PieceKind::Bishop);
+    let only_pawns_and_bishop = [PieceKind::Knight, PieceKind::Rook, PieceKind::Queen]
+        .into_iter()
+        .all(|kind| signature.count(strong, kind) == 0)
+        && bishops <= 1;
+    if pawns == 0 || !only_pawns_and_bishop || !signature.is_bare_king(weak) {
+        return None;
+    }
+
+    let file = if pawns & !FILE_A == 0 {
+        0
+    } else if pawns & !(FILE_A << 7) == 0 {
+        7
+    } else {
+        return None;
+    };
+    let promotion = match strong {
+        Player::White => 56 + file,
+        Player::Black => file,
+    };
+
+    let bishop = pieces(board, strong, PieceKind::Bishop);
+    let bishop_controls_promotion =
+        bishop != 0 && is_light(bishop.trailing_zeros() as usize) == is_light(promotion);
+    if !bishop_controls_promotion && distance(king_square(board, weak), promotion) <= 1 {
+        return Some(0);
+    }
+    None
+}
+
+fn scale_opposite_bishops(board: &Board, _strong: Player) -> Option<i32> {
+    let signature = MaterialSignature::of(board);
+    let only_bishop = |player| {
+        signature.count(player, PieceKind::Bishop) == 1
+            && [PieceKind::Knight, PieceKind::Rook, PieceKind::Queen]
+                .into_iter()
+                .all(|kind| signature.count(player, kind) == 0)
+    };
+    if !only_bishop(Player::White) || !only_bishop(Player::Black) {
+        return None;
+    }
+
+    let square_of = |player| pieces(board, player, PieceKind::Bishop).trailing_zeros() as usize;
+    if is_light(square_of(Player::White)) == is_light(square_of(Player::Black)) {
+        return None;
+    }
+    Some(OPPOSITE_BISHOPS_SCALE)
+}
+
+/// Without pawns, the strong side needs more than a minor piece's
+/// advantage to win, and at least a rook to win at all.
+fn scale_without_pawns(board: &Board, strong: Player) -> Option<i32> {
+    let weak = strong.other_player();
+    let signature = MaterialSignature::of(board);
+    if signature.count(strong, PieceKind::Pawn) > 0 {
+        return None;
+    }
+
+    let strong_material = material(&signature, strong, &PIECES);
+    let weak_material = material(&signature, weak, &PIECES);
+    if strong_material - weak_material > MINOR_VALUE {
+        return None;
+    }
+
+    Some(if strong_material < ROOK_VALUE {
+        0
+    } else if weak_material <= MINOR_VALUE {
+        4
+    } else {
+        SCALE_NORMAL / 4
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use chess_common::Player;
+    use chess_core::Board;
+
+    use super::{Endgames, MaterialSignature, OPPOSITE_BISHOPS_SCALE};
+    use crate::evaluators::endgames::SCALE_NORMAL;
+
+    fn scale(fen: &str, strong: Player) -> i32 {
+        let board = Board::from_str(fen).unwrap();
+        Endgames::default().scale(&board, &MaterialSignature::of(&board), strong)
+    }
+
+    #[test]
+    fn knows_the_wrong_rook_pawn() {
+        // The bishop on c1 can't cover a8, so the king there can't be shifted.
+        assert_eq!(0, scale("k7/8/8/8/P7/8/8/2B1K3 w - - 0 1", Player::White));
+        assert_eq!(
+            SCALE_NORMAL,
+            scale("k7/8/8/8/P7/8/8/4KB2 w - - 0 1", Player::White)
+        );
+        assert_eq!(0, scale("8/8/8/8/p7/p1k5/8/K7 w - - 0 1", Player::Black));
+        assert_eq!(
+            SCALE_NORMAL,
+            scale("8/8/8/8/8/pp6/8/1k5K w - - 0 1", Player::Black)
+        );
+    }
+
+    #[test]
+    fn scales_drawish_material() {
+        assert_eq!(
+            OPPOSITE_BISHOPS_SCALE,
+            scale("4k3/5p2/3b4/8/8/2P5/1PB5/4K3 w - - 0 1", Player::White)
+        );
+        assert_eq!(
+            SCALE_NORMAL,
+            scale("4k3/5p2/4b3/8/8/2P5/1PB5/4K3 w - - 0 1", Player::White)
+        );
+        assert_eq!(4, scale("4k3/8/8/3b4/8/8/8/R3K3 w - - 0 1", Player::White));
+        assert_eq!(0, scale("4k3/8/8/8/8/8/8/N3K3 b - - 0 1", Player::White));
+    }
+}
@@ -0,0 +1,391 @@
+use std::{
+    error::Error,
+    fmt::{Debug, Display},
+    fs,
+    path::Path,
+    str::FromStr,
+};
+
+use super::piece_square_tables::{ENDGAME_TABLES, MIDDLEGAME_TABLES};
+
+/// The names used for each piece kind in parameter names, indexed by
+/// `PieceKind as usize`.
+const PIECE_NAMES: [&str; 6] = ["pawn", "knight", "bishop", "rook", "queen", "king"];
+
+/// Every tunable number used by [`TaperedEvaluator`](super::TaperedEvaluator).
+///
+/// Parameters can be saved to and loaded from a plain text file, so they
+/// can be tuned without recompiling. The file lists each parameter's name
+/// followed by its values, separated by whitespace. Lines starting with
+/// `#` are comments, and parameters that are left out keep their default
+/// values. For example:
+///
+/// ```text
+/// # Pawn, knight, bishop, rook, queen, king
+/// material_middlegame 82 337 365 477 1025 0
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct EvalParams {
+    /// The value of each piece kind in the middlegame, indexed by
+    /// `PieceKind as usize`.
+    pub material_middlegame: [i32; 6],
+    /// The value of each piece kind in the endgame.
+    pub material_endgame: [i32; 6],
+    /// Bonuses for each piece kind standing on each square in the
+    /// middlegame, indexed by `[piece kind][square]`. Squares are listed
+    /// the way a board is drawn from white's side, starting with a8 and
+    /// ending with h1, and are mirrored for black.
+    pub pst_middlegame: [[i32; 64]; 6],
+    /// Bonuses for each piece kind standing on each square in the endgame.
+    pub pst_endgame: [[i32; 64]; 6],
+    /// How much each piece kind counts towards the game phase. The phase
+    /// of the starting position is the middlegame, and the phase drops as
+    /// pieces come off the board until only kings and pawns are left,
+    /// which is the endgame.
+    pub phase_weights: [i32; 6],
+    /// Bonuses for passed pawns in the middlegame, indexed by how many
+    /// ranks the pawn has advanced from its owner's first rank.
+    pub passed_pawn_middlegame: [i32; 8],
+    /// Bonuses for passed pawns in the endgame.
+    pub passed_pawn_endgame: [i32; 8],
+    /// Bonuses for pawns that are defended by or stand beside another of
+    /// their own pawns in the middlegame, indexed like passed pawns.
+    pub connected_pawn_middlegame: [i32; 8],
+    /// Bonuses for connected pawns in the endgame.
+    pub connected_pawn_endgame: [i32; 8],
+    /// The middlegame and endgame scores for each pawn with another pawn
+    /// of the same player in front of it.
+    pub doubled_pawn: [i32; 2],
+    /// The middlegame and endgame scores for each pawn with no pawns of
+    /// the same player on neighbouring files.
+    pub isolated_pawn: [i32; 2],
+    /// The middlegame and endgame scores for each pawn that is behind
+    /// every pawn on the neighbouring files and can't safely advance.
+    pub backward_pawn: [i32; 2],
+    /// The middlegame and endgame scores for each group of pawns on
+    /// neighbouring files after the first.
+    pub pawn_island: [i32; 2],
+    /// The middlegame and endgame scores for each pawn in front of its own
+    /// king, on the king's file or a neighbouring one.
+    pub pawn_shield: [i32; 2],
+    /// The middlegame and endgame scores for each file on or next to the
+    /// king's file that has none of the king's own pawns.
+    pub king_open_file: [i32; 2],
+    /// The middlegame penalty for each piece attacking the squares around
+    /// the enemy king, indexed by the attacker's `PieceKind as usize`.
+    pub king_attacker_weights: [i32; 6],
+    /// The middlegame score for each square a piece can move to that isn't
+    /// occupied by its own pieces or attacked by enemy pawns, indexed by
+    /// `PieceKind as usize`.
+    pub mobility_middlegame: [i32; 6],
+    /// The endgame score for each square a piece can move to.
+    pub mobility_endgame: [i32; 6],
+    /// The middlegame and endgame scores for having both bishops.
+    pub bishop_pair: [i32; 2],
+    /// The middlegame and endgame scores for a rook on a file without pawns.
+    pub rook_open_file: [i32; 2],
+    /// The middlegame and endgame scores for a rook on a file with only
+    /// enemy pawns.
+    pub rook_half_open_file: [i32; 2],
+    /// The middlegame and endgame scores for a rook on its seventh rank.
+    pub rook_seventh_rank: [i32; 2],
+    /// The middlegame and endgame scores for a knight in the enemy half of
+    /// the board that is defended by a pawn and can't be chased away by
+    /// enemy pawns.
+    pub knight_outpost: [i32; 2],
+}
+
+impl Default for EvalParams {
+    fn default() -> Self {
+        Self {
+            material_middlegame: [82, 337, 365, 477, 1025, 0],
+            material_endgame: [94, 281, 297, 512, 936, 0],
+            pst_middlegame: MIDDLEGAME_TABLES,
+            pst_endgame: ENDGAME_TABLES,
+            phase_weights: [0, 1, 1, 2, 4, 0],
+            passed_pawn_middlegame: [0, 0, 5, 10, 20, 35, 60, 0],
+            passed_pawn_endgame: [0, 10, 15, 25, 45, 75, 120, 0],
+            connected_pawn_middlegame: [0, 5, 8, 12, 20, 30, 50, 0],
+            connected_pawn_endgame: [0, 3, 5, 8, 15, 25, 40, 0],
+            doubled_pawn: [-10, -25],
+            isolated_pawn: [-12, -15],
+            backward_pawn: [-8, -10],
+            pawn_island: [-5, -10],
+            pawn_shield: [12, 0],
+            king_open_file: [-18, 0],
+            king_attacker_weights: [0, 12, 12, 20, 40, 0],
+            mobility_middlegame: [0, 4, 5, 2, 1, 0],
+            mobility_endgame: [0, 4, 5, 4, 2, 0],
+            bishop_pair: [30, 50],
+            rook_open_file: [25, 10],
+            rook_half_open_file: [10, 5],
+            rook_seventh_rank: [15, 25],
+            knight_outpost: [20, 10],
+        }
+    }
+}
+
+impl EvalParams {
+    /// Loads parameters from a file in the format described on
+    /// [`EvalParams`].
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, EvalParamsErr> {
+        let text = fs::read_to_string(path).map_err(EvalParamsErr::Io)?;
+        text.parse()
+    }
+
+    /// Saves every parameter to a file that [`EvalParams::load`] can read.
+    pub fn save(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        fs::write(path, self.to_string())
+    }
+
+    /// Gets every parameter by name, in the order they are saved in.
+    pub fn parameters_mut(&mut self) -> Vec<(String, &mut [i32])> {
+        let mut parameters: Vec<(String, &mut [i32])> = vec![
+            (
+                "material_middlegame".to_string(),
+                &mut self.material_middlegame,
+            ),
+            ("material_endgame".to_string(), &mut self.material_endgame),
+            ("phase_weights".to_string(), &mut self.phase_weights),
+            (
+                "passed_pawn_middlegame".to_string(),
+                &mut self.passed_pawn_middlegame,
+            ),
+            (
+                "passed_pawn_endgame".to_string(),
+                &mut self.passed_pawn_endgame,
+            ),
+            (
+                "connected_pawn_middlegame".to_string(),
+                &mut self.connected_pawn_middlegame,
+            ),
+            (
+                "connected_pawn_endgame".to_string(),
+                &mut self.connected_pawn_endgame,
+            ),
+            ("doubled_pawn".to_string(), &mut self.doubled_pawn),
+            ("isolated_pawn".to_string(), &mut self.isolated_pawn),
+            ("backward_pawn".to_string(), &mut self.backward_pawn),
+            ("pawn_island".to_string(), &mut self.pawn_island),
+            ("pawn_shield".to_string(), &mut self.pawn_shield),
+            ("king_open_file".to_string(), &mut self.king_open_file),
+            (
+                "king_attacker_weights".to_string(),
+                &mut self.king_attacker_weights,
+            ),
+            (
+                "mobility_middlegame".to_string(),
+                &mut self.mobility_middlegame,
+            ),
+            ("mobility_endgame".to_string(), &mut self.mobility_endgame),
+            ("bishop_pair".to_string(), &mut self.bishop_pair),
+            ("rook_open_file".to_string(), &mut self.rook_open_file),
+            (
+                "rook_half_open_file".to_string(),
+                &mut self.rook_half_open_file,
+            ),
+            ("rook_seventh_rank".to_string(), &mut self.rook_seventh_rank),
+            ("knight_outpost".to_string(), &mut self.knight_outpost),
+        ];
+
+        for (name, table) in PIECE_NAMES.iter().zip(self.pst_middlegame.iter_mut()) {
+            parameters.push((format!("pst_middlegame_{name}"), table));
+        }
+        for (name, table) in PIECE_NAMES.iter().zip(self.pst_endgame.iter_mut()) {
+            parameters.push((format!("pst_endgame_{name}"), table));
+        }
+
+        parameters
+    }
+
+    /// The phase of a position that still has all of its starting pieces.
+    pub(crate) fn max_phase(&self) -> i32 {
+        let starting_counts = [16, 4, 4, 4, 2, 2];
+        let phase = self
+            .phase_weights
+            .iter()
+            .zip(starting_counts)
+            .map(|(weight, count)| weight * count)
+            .sum::<i32>();
+        phase.max(1)
+    }
+}
+
+impl Display for EvalParams {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut params = self.clone();
+        for (name, values) in params.parameters_mut() {
+            write!(f, "{name}")?;
+            if values.len() == 64 {
+                // Lay tables out like a board so they stay readable.
+                for rank in values.chunks(8) {
+                    writeln!(f)?;
+                    for value in rank {
+                        write!(f, " {value:4}")?;
+                    }
+                }
+            } else {
+                for value in values.iter() {
+                    write!(f, " {value}")?;
+                }
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for EvalParams {
+    type Err = EvalParamsErr;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut params = Self::default();
+        let mut parameters = params.parameters_mut();
+
+        // The parameter currently being read and how many of its values
+        // have been read so far.
+        let mut current: Option<(usize, usize)> = None;
+        for (line_index, line) in text.lines().enumerate() {
+            let line_number = line_index + 1;
+            let line = line.trim();
+            if line.starts_with('#') {
+                continue;
+            }
+
+            for token in line.split_whitespace() {
+                if let Ok(value) = token.parse::<i32>() {
+                    let (index, read) = current
+                        .as_mut()
+                        .ok_or(EvalParamsErr::UnexpectedValue { line: line_number })?;
+                    let (name, values) = &mut parameters[*index];
+                    if *read >= values.len() {
+                        return Err(EvalParamsErr::WrongValueCount {
+                            name: name.clone(),
+                            expected: values.len(),
+                        });
+                    }
+                    values[*read] = value;
+                    *read += 1;
+                    continue;
+                }
+
+                check_value_count(&parameters, current)?;
+                let index = parameters
+                    .iter()
+                    .position(|(name, _)| name == token)
+                    .ok_or_else(|| EvalParamsErr::UnknownParameter {
+                        name: token.to_string(),
+                        line: line_number,
+                    })?;
+                current = Some((index, 0));
+            }
+        }
+        check_value_count(&parameters, current)?;
+
+        drop(parameters);
+        Ok(params)
+    }
+}
+
+fn check_value_count(
+    parameters: &[(String, &mut [i32])],
+    current: Option<(usize, usize)>,
+) -> Result<(), EvalParamsErr> {
+    match current {
+        Some((index, read)) if read != parameters[index].1.len() => {
+            Err(EvalParamsErr::WrongValueCount {
+                name: parameters[index].0.clone(),
+                expected: parameters[index].1.len(),
+            })
+        }
+        _ => Ok(()),
+    }
+}
+
+pub enum EvalParamsErr {
+    Io(std::io::Error),
+    UnknownParameter {
+        name: String,
+        line: usize,
+    },
+    /// A value appeared before the name of any parameter.
+    UnexpectedValue {
+        line: usize,
+    },
+    WrongValueCount {
+        name: String,
+        expected: usize,
+    },
+}
+
+impl Debug for EvalParamsErr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Just defer to the Display impl
+        write!(f, "{}", self)
+    }
+}
+
+impl Display for EvalParamsErr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EvalParamsErr::Io(err) => write!(f, "Couldn't read the parameters: {err}"),
+            EvalParamsErr::UnknownParameter { name, line } => {
+                write!(f, "Unknown parameter {name:?} on line {line}")
+            }
+            EvalParamsErr::UnexpectedValue { line } => {
+                write!(
+                    f,
+                    "Expected a parameter name before the value on line {line}"
+                )
+            }
+            EvalParamsErr::WrongValueCount { name, expected } => {
+                write!(f, "Expected {expected} values for {name}")
+            }
+        }
+    }
+}
+
+impl Error for EvalParamsErr {}
+
+#[cfg(test)]
+mod tests {
+    use super::{EvalParams, EvalParamsErr};
+
+    #[test]
+    fn round_trips_through_text() {
+        let mut params = EvalParams::default();
+        params.material_middlegame[1] = 350;
+        params.pst_endgame[5][63] = -7;
+
+        let parsed = params.to_string().parse::<EvalParams>().unwrap();
+        assert_eq!(params, parsed);
+    }
+
+    #[test]
+    fn missing_parameters_keep_defaults() {
+        let text = "# Only override the queen\nmaterial_middlegame 82 337 365 477 1000 0\n";
+        let params = text.parse::<EvalParams>().unwrap();
+
+        assert_eq!(1000, params.material_middlegame[4]);
+        assert_eq!(
+            EvalParams::default().material_endgame,
+            params.material_endgame
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_files() {
+        assert!(matches!(
+            "material_middlegame 1 2 3".parse::<EvalParams>(),
+            Err(EvalParamsErr::WrongValueCount { .. })
+        ));
+        assert!(matches!(
+            "queen_value 900".parse::<EvalParams>(),
+            Err(EvalParamsErr::UnknownParameter { line: 1, .. })
+        ));
+        assert!(matches!(
+            "900".parse::<EvalParams>(),
+            Err(EvalParamsErr::UnexpectedValue { line: 1 })
+        ));
+    }
+}
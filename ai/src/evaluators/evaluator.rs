@@ -0,0 +1,20 @@
+use chess_core::Board;
+
+/// Statically scores positions for the searcher.
+pub trait Evaluator {
+    /// Scores the position in centipawns from the perspective of the side
+    /// to move, so a positive score means the side to move is better.
+    ///
+    /// Checkmate and stalemate are handled by the searcher, so this is
+    /// only called on positions that have legal moves or are about to be
+    /// searched further.
+    fn evaluate(&mut self, board: &Board) -> i32;
+
+    /// Called by the searcher right after it makes a move on `board`, so
+    /// that evaluators can update their state incrementally.
+    fn on_make_move(&mut self, _board: &Board) {}
+
+    /// Called by the searcher right after it undoes the move that the last
+    /// call to [`Evaluator::on_make_move`] was for.
+    fn on_undo_move(&mut self) {}
+}
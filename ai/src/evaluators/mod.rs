@@ -1,3 +1,17 @@
+mod endgames;
+mod eval_params;
+mod evaluator;
+mod nnue;
+mod pawns;
+mod piece_square_tables;
 mod simple;
+mod tapered;
+mod terms;
 
-pub use simple::SimpleEvaluator;
\ No newline at end of file
+pub use endgames::{Endgames, EvaluationFn, MaterialSignature, ScaleFn, SCALE_NORMAL};
+pub use eval_params::{EvalParams, EvalParamsErr};
+pub use evaluator::Evaluator;
+pub use nnue::{Network, NetworkErr, NnueEvaluator};
+pub use simple::SimpleEvaluator;
+pub use tapered::TaperedEvaluator;
+pub use terms::EvalTerms;
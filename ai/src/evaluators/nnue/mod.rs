@@ -0,0 +1,297 @@
+mod network;
+
+use std::{path::Path, sync::Arc};
+
+use chess_common::{Piece, PieceKind, Player};
+use chess_core::Board;
+
+use network::{input_index, CENTIPAWN_SCALE, CLIPPED_MAX, OUTPUT_SCALE};
+pub use network::{Network, NetworkErr};
+
+use super::Evaluator;
+use crate::{
+    search_algorithms::MATE_THRESHOLD,
+    zobrist::{PIECE_KINDS, PLAYERS},
+};
+
+/// The largest evaluation in either direction, so that no network, however
+/// badly scaled, can make a position look like a forced mate.
+const MAX_EVALUATION: i64 = MATE_THRESHOLD as i64 - 1;
+
+/// The sums of the active inputs of both perspectives in one position.
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct Accumulator {
+    /// The position the accumulator is for, as bitboards indexed by
+    /// `[player][piece kind]`.
+    pieces: [[u64; 6]; 2],
+    /// The accumulated values, indexed by perspective.
+    values: [Vec<i16>; 2],
+}
+
+/// Evaluates positions with a [`Network`].
+///
+/// Accumulators are updated incrementally as the searcher makes and undoes
+/// moves. Each update only adds and removes the inputs of the pieces that
+/// changed, except when a king moves, since that changes every input of
+/// its perspective. In debug builds, every evaluation checks that the
+/// incrementally updated accumulator matches one computed from scratch.
+#[derive(Clone, Debug)]
+pub struct NnueEvaluator {
+    network: Arc<Network>,
+    /// One accumulator for each position from the root to the node being
+    /// searched.
+    stack: Vec<Accumulator>,
+}
+
+impl NnueEvaluator {
+    /// Creates an evaluator using the network, which can be shared with
+    /// other evaluators without copying its weights.
+    pub fn new(network: Arc<Network>) -> Self {
+        Self {
+            network,
+            stack: Vec::new(),
+        }
+    }
+
+    /// Creates an evaluator using the network in the given file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, NetworkErr> {
+        Ok(Self::new(Arc::new(Network::load(path)?)))
+    }
+
+    pub fn network(&self) -> &Arc<Network> {
+        &self.network
+    }
+
+    /// Computes an accumulator from scratch.
+    fn refresh(&self, pieces: [[u64; 6]; 2]) -> Accumulator {
+        let values = PLAYERS.map(|perspective| self.refresh_perspective(&pieces, perspective));
+        Accumulator { pieces, values }
+    }
+
+    fn refresh_perspective(&self, pieces: &[[u64; 6]; 2], perspective: Player) -> Vec<i16> {
+        let mut values = self.network.feature_biases.clone();
+        let king_square = king_square(pieces, perspective);
+        for player in PLAYERS {
+            for kind in non_king_kinds() {
+                let mut bitboard = pieces[player.as_index()][kind as usize];
+                while bitboard != 0 {
+                    let square = bitboard.trailing_zeros() as usize;
+                    bitboard &= bitboard - 1;
+                    let input = input_index(perspective, king_square, player, kind, square);
+                    add_weights(&mut values, self.network.weights_for(input));
+                }
+            }
+        }
+        values
+    }
+
+    /// Computes the accumulator for `pieces` by adding and removing the
+    /// inputs that differ from `base`. This is exact no matter how far
+    /// apart the two positions are, so a stale `base` only costs time.
+    fn update(&self, base: &Accumulator, pieces: [[u64; 6]; 2]) -> Accumulator {
+        let mut values = base.values.clone();
+        for perspective in PLAYERS {
+            let king = king_square(&pieces, perspective);
+            if king != king_square(&base.pieces, perspective) {
+                values[perspective.as_index()] = self.refresh_perspective(&pieces, perspective);
+                continue;
+            }
+
+            let perspective_values = &mut values[perspective.as_index()];
+            for player in PLAYERS {
+                for kind in non_king_kinds() {
+                    let before = base.pieces[player.as_index()][kind as usize];
+                    let after = pieces[player.as_index()][kind as usize];
+
+                    let mut removed = before & !after;
+                    while removed != 0 {
+                        let square = removed.trailing_zeros() as usize;
+                        removed &= removed - 1;
+                        let input = input_index(perspective, king, player, kind, square);
+                        subtract_weights(perspective_values, self.network.weights_for(input));
+                    }
+
+                    let mut added = after & !before;
+                    while added != 0 {
+                        let square = added.trailing_zeros() as usize;
+                        added &= added - 1;
+                        let input = input_index(perspective, king, player, kind, square);
+                        add_weights(perspective_values, self.network.weights_for(input));
+                    }
+                }
+            }
+        }
+
+        Accumulator { pieces, values }
+    }
+
+    fn accumulator_for(&self, pieces: [[u64; 6]; 2]) -> Accumulator {
+        match self.stack.last() {
+            None => self.refresh(pieces),
+            Some(top) => self.update(top, pieces),
+        }
+    }
+}
+
+impl Evaluator for NnueEvaluator {
+    fn evaluate(&mut self, board: &Board) -> i32 {
+        let pieces = pieces_of(board);
+        // The top of the stack is for a different position when evaluating
+        // outside of a search, so bring it up to date first.
+        if self.stack.last().is_none_or(|top| top.pieces != pieces) {
+            let accumulator = self.accumulator_for(pieces);
+            self.stack.pop();
+            self.stack.push(accumulator);
+        }
+
+        let accumulator = self
+            .stack
+            .last()
+            .expect("an accumulator to be on the stack");
+        debug_assert_eq!(
+            self.refresh(pieces),
+            *accumulator,
+            "the incrementally updated accumulator differs from a full refresh"
+        );
+
+        let player = board.player_to_move();
+        let hidden_size = self.network.hidden_size;
+        let (own_weights, other_weights) = self.network.output_weights.split_at(hidden_size);
+        let output = self.network.output_bias as i64
+            + clipped_dot(&accumulator.values[player.as_index()], own_weights)
+            + clipped_dot(
+                &accumulator.values[player.other_player().as_index()],
+                other_weights,
+            );
+
+        let centipawns = output * CENTIPAWN_SCALE as i64 / (CLIPPED_MAX * OUTPUT_SCALE) as i64;
+        centipawns.clamp(-MAX_EVALUATION, MAX_EVALUATION) as i32
+    }
+
+    fn on_make_move(&mut self, board: &Board) {
+        let accumulator = self.accumulator_for(pieces_of(board));
+        self.stack.push(accumulator);
+    }
+
+    fn on_undo_move(&mut self) {
+        self.stack.pop();
+    }
+}
+
+fn pieces_of(board: &Board) -> [[u64; 6]; 2] {
+    PLAYERS.map(|player| PIECE_KINDS.map(|kind| board.bitboard_for(&Piece::new(player, kind))))
+}
+
+fn non_king_kinds() -> impl Iterator<Item = PieceKind> {
+    PIECE_KINDS
+        .into_iter()
+        .filter(|kind| *kind != PieceKind::King)
+}
+
+/// Positions without a king only come up in tests, so they use a1.
+fn king_square(pieces: &[[u64; 6]; 2], player: Player) -> usize {
+    let king = pieces[player.as_index()][PieceKind::King as usize];
+    if king == 0 {
+        0
+    } else {
+        king.trailing_zeros() as usize
+    }
+}
+
+// Accumulators wrap on overflow, which keeps incremental updates exactly
+// in line with full refreshes even for badly scaled networks.
+fn add_weights(values: &mut [i16], weights: &[i16]) {
+    for (value, weight) in values.iter_mut().zip(weights) {
+        *value = value.wrapping_add(*weight);
+    }
+}
+
+fn subtract_weights(values: &mut [i16], weights: &[i16]) {
+    for (value, weight) in values.iter_mut().zip(weights) {
+        *value = value.wrapping_sub(*weight);
+    }
+}
+
+/// Sums in i64, since the hidden size comes from the network file and a
+/// large enough layer of large weights overflows an i32.
+fn clipped_dot(values: &[i16], weights: &[i16]) -> i64 {
+    values
+        .iter()
+        .zip(weights)
+        .map(|(value, weight)| (*value as i64).clamp(0, CLIPPED_MAX as i64) * *weight as i64)
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{str::FromStr, sync::Arc};
+
+    use chess_core::Board;
+
+    use super::{network::INPUTS, Network, NnueEvaluator, MAX_EVALUATION};
+    use crate::{Evaluator, Limits, Searcher};
+
+    fn evaluator() -> NnueEvaluator {
+        NnueEvaluator::new(Arc::new(Network::random(8, 1)))
+    }
+
+    #[test]
+    fn incremental_updates_match_full_refresh() {
+        let mut board = Board::from_str("r3k2r/1P6/8/3pP3/8/8/8/R3K2R w KQkq d6 0 1").unwrap();
+        let mut incremental = evaluator();
+        incremental.evaluate(&board);
+
+        // En-passant, castling, a promotion and a king move.
+        for move_ in ["exd6", "O-O", "b8=Q", "Kg7"] {
+            board.make_move_acn(move_).unwrap();
+            incremental.on_make_move(&board);
+            assert_eq!(evaluator().evaluate(&board), incremental.evaluate(&board));
+        }
+
+        for _ in 0..4 {
+            board.undo().unwrap();
+            incremental.on_undo_move();
+            assert_eq!(evaluator().evaluate(&board), incremental.evaluate(&board));
+        }
+    }
+
+    #[test]
+    fn clamps_evaluations_of_extreme_networks() {
+        let hidden_size = 512;
+        let network = |output_weight| Network {
+            hidden_size,
+            feature_weights: vec![0; INPUTS * hidden_size],
+            feature_biases: vec![i16::MAX; hidden_size],
+            output_weights: vec![output_weight; 2 * hidden_size],
+            output_bias: 0,
+        };
+        let board = Board::default();
+
+        let mut highest = NnueEvaluator::new(Arc::new(network(i16::MAX)));
+        assert_eq!(MAX_EVALUATION as i32, highest.evaluate(&board));
+        let mut lowest = NnueEvaluator::new(Arc::new(network(i16::MIN)));
+        assert_eq!(-MAX_EVALUATION as i32, lowest.evaluate(&board));
+    }
+
+    #[test]
+    fn loads_network_files() {
+        let network = Network::random(4, 2);
+        let path = std::env::temp_dir().join(format!("chess_ai_nnue_{}.bin", std::process::id()));
+        network.save(&path).unwrap();
+        let loaded = NnueEvaluator::load(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(network, **loaded.unwrap().network());
+    }
+
+    #[test]
+    fn searches_with_network() {
+        let mut board = Board::default();
+        let fen_before = board.to_fen_string();
+        let mut searcher = Searcher::with_evaluator(evaluator());
+        let result = searcher.search(&mut board, Limits::depth(2));
+
+        assert!(result.best_move.is_some());
+        assert_eq!(fen_before, board.to_fen_string());
+    }
+}
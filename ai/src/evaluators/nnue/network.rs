@@ -0,0 +1,255 @@
+use std::{
+    error::Error,
+    fmt::{Debug, Display},
+    fs,
+    path::Path,
+};
+
+use chess_common::{PieceKind, Player};
+
+use crate::random::Rng;
+
+/// The bytes every network file starts with.
+const MAGIC: &[u8; 4] = b"CNUE";
+const VERSION: u32 = 1;
+
+/// The number of inputs for each perspective: every square the
+/// perspective's king can stand on, times every non-king piece of either
+/// player, times every square that piece can stand on.
+pub(crate) const INPUTS: usize = 64 * 10 * 64;
+
+/// Accumulator values are clamped to `0..=CLIPPED_MAX` before the output
+/// layer, which is also the scale of the quantised feature weights.
+pub(crate) const CLIPPED_MAX: i32 = 255;
+/// The scale of the quantised output weights.
+pub(crate) const OUTPUT_SCALE: i32 = 64;
+/// Converts the network's output into centipawns.
+pub(crate) const CENTIPAWN_SCALE: i32 = 400;
+
+/// A quantised HalfKP network.
+///
+/// Each perspective has its own set of inputs, one for every combination of
+/// its king's square with a non-king piece on a square. The inputs that are
+/// active in a position are summed into that perspective's accumulator,
+/// which is then clipped and fed, side to move first, into a single output
+/// neuron.
+///
+/// Network files are little-endian and laid out as follows:
+///
+/// | Field           | Type  | Count                 |
+/// |-----------------|-------|-----------------------|
+/// | magic `CNUE`    | bytes | 4                     |
+/// | version (1)     | `u32` | 1                     |
+/// | hidden size `H` | `u32` | 1                     |
+/// | feature weights | `i16` | 40960 × `H`           |
+/// | feature biases  | `i16` | `H`                   |
+/// | output weights  | `i16` | 2 × `H`               |
+/// | output bias     | `i32` | 1                     |
+///
+/// Feature weights are grouped by input, so the `H` weights of input `i`
+/// start at `i × H`. Input indexes are
+/// `king × 640 + (relative player × 5 + piece kind) × 64 + square`, where
+/// the relative player is 0 for the perspective's own pieces and 1 for the
+/// opponent's, the piece kind runs from pawn (0) to queen (4), and squares
+/// run from a1 (0) to h8 (63). Black's perspective mirrors every square
+/// vertically, so both perspectives see their own pieces start on the
+/// first ranks. The first `H` output weights apply to the side to move's
+/// accumulator, and the rest to the other side's.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Network {
+    pub(crate) hidden_size: usize,
+    pub(crate) feature_weights: Vec<i16>,
+    pub(crate) feature_biases: Vec<i16>,
+    pub(crate) output_weights: Vec<i16>,
+    pub(crate) output_bias: i32,
+}
+
+impl Network {
+    /// Loads a network from a file in the format described on [`Network`].
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, NetworkErr> {
+        let bytes = fs::read(path).map_err(NetworkErr::Io)?;
+        Self::from_bytes(&bytes)
+    }
+
+    /// Saves the network to a file that [`Network::load`] can read.
+    pub fn save(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        fs::write(path, self.to_bytes())
+    }
+
+    /// Creates a network with small random weights. Its evaluations are
+    /// meaningless, but it is useful for testing.
+    pub fn random(hidden_size: usize, seed: u64) -> Self {
+        let mut rng = Rng::new(seed);
+        let mut weights = |count: usize, limit: i64| {
+            (0..count)
+                .map(|_| rng.range(-limit, limit) as i16)
+                .collect::<Vec<_>>()
+        };
+
+        Self {
+            hidden_size,
+            feature_weights: weights(INPUTS * hidden_size, 16),
+            feature_biases: weights(hidden_size, 64),
+            output_weights: weights(2 * hidden_size, 64),
+            output_bias: 0,
+        }
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, NetworkErr> {
+        let mut reader = Reader { bytes, position: 0 };
+        if reader.take(4)? != MAGIC {
+            return Err(NetworkErr::NotANetwork);
+        }
+
+        let version = reader.u32()?;
+        if version != VERSION {
+            return Err(NetworkErr::UnsupportedVersion(version));
+        }
+
+        let hidden_size = reader.u32()? as usize;
+        if hidden_size == 0 {
+            return Err(NetworkErr::NotANetwork);
+        }
+        let feature_weights = reader.i16s(INPUTS * hidden_size)?;
+        let feature_biases = reader.i16s(hidden_size)?;
+        let output_weights = reader.i16s(2 * hidden_size)?;
+        let output_bias = reader.u32()? as i32;
+
+        if reader.position != bytes.len() {
+            return Err(NetworkErr::TrailingBytes);
+        }
+
+        Ok(Self {
+            hidden_size,
+            feature_weights,
+            feature_biases,
+            output_weights,
+            output_bias,
+        })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(16 + 2 * self.feature_weights.len());
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&VERSION.to_le_bytes());
+        bytes.extend_from_slice(&(self.hidden_size as u32).to_le_bytes());
+        for weights in [
+            &self.feature_weights,
+            &self.feature_biases,
+            &self.output_weights,
+        ] {
+            for weight in weights.iter() {
+                bytes.extend_from_slice(&weight.to_le_bytes());
+            }
+        }
+        bytes.extend_from_slice(&self.output_bias.to_le_bytes());
+        bytes
+    }
+
+    /// The weights of the given input, one for each accumulator value.
+    pub(crate) fn weights_for(&self, input: usize) -> &[i16] {
+        &self.feature_weights[input * self.hidden_size..(input + 1) * self.hidden_size]
+    }
+}
+
+/// Gets the index of the input for a piece as seen from `perspective`,
+/// whose king is on `king_square`.
+pub(crate) fn input_index(
+    perspective: Player,
+    king_square: usize,
+    player: Player,
+    kind: PieceKind,
+    square: usize,
+) -> usize {
+    let orient = |square: usize| match perspective {
+        Player::White => square,
+        Player::Black => square ^ 56,
+    };
+    let relative_player = if player == perspective { 0 } else { 1 };
+    orient(king_square) * 640 + (relative_player * 5 + kind as usize) * 64 + orient(square)
+}
+
+struct Reader<'bytes> {
+    bytes: &'bytes [u8],
+    position: usize,
+}
+
+impl<'bytes> Reader<'bytes> {
+    fn take(&mut self, count: usize) -> Result<&'bytes [u8], NetworkErr> {
+        let end = self.position + count;
+        if end > self.bytes.len() {
+            return Err(NetworkErr::Truncated);
+        }
+        let taken = &self.bytes[self.position..end];
+        self.position = end;
+        Ok(taken)
+    }
+
+    fn u32(&mut self) -> Result<u32, NetworkErr> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn i16s(&mut self, count: usize) -> Result<Vec<i16>, NetworkErr> {
+        let bytes = self.take(count * 2)?;
+        Ok(bytes
+            .chunks_exact(2)
+            .map(|pair| i16::from_le_bytes([pair[0], pair[1]]))
+            .collect())
+    }
+}
+
+pub enum NetworkErr {
+    Io(std::io::Error),
+    /// The data doesn't start with the expected header.
+    NotANetwork,
+    UnsupportedVersion(u32),
+    /// The data ended before the whole network was read.
+    Truncated,
+    /// There is more data after the end of the network.
+    TrailingBytes,
+}
+
+impl Debug for NetworkErr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Just defer to the Display impl
+        write!(f, "{}", self)
+    }
+}
+
+impl Display for NetworkErr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NetworkErr::Io(err) => write!(f, "Couldn't read the network: {err}"),
+            NetworkErr::NotANetwork => write!(f, "The data isn't a network file"),
+            NetworkErr::UnsupportedVersion(version) => {
+                write!(f, "Network version {version} isn't supported")
+            }
+            NetworkErr::Truncated => write!(f, "The network file is truncated"),
+            NetworkErr::TrailingBytes => write!(f, "Unexpected data after the network"),
+        }
+    }
+}
+
+impl Error for NetworkErr {}
+
+#[cfg(test)]
+mod tests {
+    use super::{Network, NetworkErr};
+
+    #[test]
+    fn round_trips_through_bytes() {
+        let network = Network::random(4, 7);
+        let bytes = network.to_bytes();
+        assert_eq!(network, Network::from_bytes(&bytes).unwrap());
+
+        assert!(matches!(
+            Network::from_bytes(&bytes[..bytes.len() - 1]),
+            Err(NetworkErr::Truncated)
+        ));
+        assert!(matches!(
+            Network::from_bytes(b"nope"),
+            Err(NetworkErr::NotANetwork)
+        ));
+    }
+}
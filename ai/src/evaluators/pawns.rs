@@ -0,0 +1,255 @@
+use chess_common::{Piece, PieceKind, Player};
+use chess_core::Board;
+
+use super::EvalParams;
+use crate::{attacks::pawn_attacks, zobrist};
+
+/// The number of pawn structures the pawn table remembers.
+const PAWN_TABLE_SIZE: usize = 1 << 14;
+
+pub(crate) const FILE_A: u64 = 0x0101_0101_0101_0101;
+const RANK_1: u64 = 0xFF;
+
+#[derive(Clone, Copy, Debug, Default)]
+struct PawnEntry {
+    key: u64,
+    middlegame: i32,
+    endgame: i32,
+}
+
+/// Caches pawn structure scores by a hash of the pawns alone. Pawns move
+/// rarely compared to other pieces, so most positions reached in a search
+/// share their pawn structure with many others.
+///
+/// Positions without pawns hash to 0 and score 0, which is what empty
+/// entries already hold, so they need no special handling.
+#[derive(Clone, Debug)]
+pub(crate) struct PawnTable {
+    entries: Vec<PawnEntry>,
+}
+
+impl PawnTable {
+    pub(crate) fn new() -> Self {
+        Self {
+            entries: vec![PawnEntry::default(); PAWN_TABLE_SIZE],
+        }
+    }
+
+    /// Gets the middlegame and endgame pawn structure scores from white's
+    /// point of view, computing them if they aren't cached already.
+    pub(crate) fn probe(&mut self, board: &Board, params: &EvalParams) -> (i32, i32) {
+        let key = zobrist::pawn_hash(board);
+        let entry = &mut self.entries[key as usize % PAWN_TABLE_SIZE];
+        if entry.key != key {
+            let white = board.bitboard_for(&Piece::new(Player::White, PieceKind::Pawn));
+            let black = board.bitboard_for(&Piece::new(Player::Black, PieceKind::Pawn));
+            let (middlegame, endgame) = evaluate_pawns(white, black, params);
+            *entry = PawnEntry {
+                key,
+                middlegame,
+                endgame,
+            };
+        }
+        (entry.middlegame, entry.endgame)
+    }
+}
+
+/// Scores the pawn structure from white's point of view, returning the
+/// middlegame and endgame scores.
+pub(crate) fn evaluate_pawns(white: u64, black: u64, params: &EvalParams) -> (i32, i32) {
+    let (white_middlegame, white_endgame) = evaluate_side(Player::White, white, black, params);
+    let (black_middlegame, black_endgame) = evaluate_side(Player::Black, black, white, params);
+    (
+        white_middlegame - black_middlegame,
+        white_endgame - black_endgame,
+    )
+}
+
+fn evaluate_side(player: Player, own: u64, enemy: u64, params: &EvalParams) -> (i32, i32) {
+    let mut middlegame = 0;
+    let mut endgame = 0;
+    let mut add = |[term_middlegame, term_endgame]: [i32; 2]| {
+        middlegame += term_middlegame;
+        endgame += term_endgame;
+    };
+
+    let mut pawns = own;
+    while pawns != 0 {
+        let square = pawns.trailing_zeros() as usize;
+        pawns &= pawns - 1;
+
+        let file = square % 8;
+        let rank = square / 8;
+        let relative_rank = match player {
+            Player::White => rank,
+            Player::Black => 7 - rank,
+        };
+        let bit = 1_u64 << square;
+        let file_mask = FILE_A << file;
+        let adjacent = adjacent_files(file);
+        let ahead = ranks_ahead(player, rank);
+
+        // Pawns further back on the same file are counted as doubled, so
+        // that each extra pawn on a file is penalized once.
+        let doubled = own & file_mask & ahead != 0;
+        if doubled {
+            add(params.doubled_pawn);
+        }
+
+        let isolated = own & adjacent == 0;
+        if isolated {
+            add(params.isolated_pawn);
+        }
+
+        if !doubled && enemy & (file_mask | adjacent) & ahead == 0 {
+            add([
+                params.passed_pawn_middlegame[relative_rank],
+                params.passed_pawn_endgame[relative_rank],
+            ]);
+        }
+
+        // A pawn of the other player on this square would attack the
+        // squares that defend it.
+        let supported = pawn_attacks(player.other_player(), bit) & own != 0;
+        let phalanx = own & adjacent & (RANK_1 << (rank * 8)) != 0;
+        if supported || phalanx {
+            add([
+                params.connected_pawn_middlegame[relative_rank],
+                params.connected_pawn_endgame[relative_rank],
+            ]);
+        }
+
+        // A backward pawn has fallen behind its neighbours, so none of them
+        // can ever defend it, and it can't safely advance either.
+        let stop_square = match player {
+            Player::White => bit << 8,
+            Player::Black => bit >> 8,
+        };
+        if !isolated
+            && own & adjacent & !ahead == 0
+            && pawn_attacks(player, stop_square) & enemy != 0
+        {
+            add(params.backward_pawn);
+        }
+    }
+
+    // Each group of pawns on neighbouring files is an island. Every island
+    // after the first is another group that has to be defended separately.
+    let files = (0..8).fold(0_u8, |files, file| {
+        if own & (FILE_A << file) != 0 {
+            files | (1 << file)
+        } else {
+            files
+        }
+    });
+    let islands = (files & !(files << 1)).count_ones() as i32;
+    for _ in 1..islands {
+        add(params.pawn_island);
+    }
+
+    (middlegame, endgame)
+}
+
+pub(crate) fn adjacent_files(file: usize) -> u64 {
+    let mut files = 0;
+    if file > 0 {
+        files |= FILE_A << (file - 1);
+    }
+    if file < 7 {
+        files |= FILE_A << (file + 1);
+    }
+    files
+}
+
+/// Every square on the ranks in front of `rank` from the player's side.
+pub(crate) fn ranks_ahead(player: Player, rank: usize) -> u64 {
+    match player {
+        Player::White if rank == 7 => 0,
+        Player::White => !0 << ((rank + 1) * 8),
+        Player::Black => (1 << (rank * 8)) - 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use chess_common::{Piece, PieceKind, Player};
+    use chess_core::Board;
+
+    use super::{evaluate_pawns, PawnTable};
+    use crate::EvalParams;
+
+    /// Parameters that only score the single term set by `set_term`.
+    fn only(set_term: impl FnOnce(&mut EvalParams)) -> EvalParams {
+        let mut params = EvalParams {
+            doubled_pawn: [0; 2],
+            isolated_pawn: [0; 2],
+            backward_pawn: [0; 2],
+            pawn_island: [0; 2],
+            passed_pawn_middlegame: [0; 8],
+            passed_pawn_endgame: [0; 8],
+            connected_pawn_middlegame: [0; 8],
+            connected_pawn_endgame: [0; 8],
+            ..Default::default()
+        };
+        set_term(&mut params);
+        params
+    }
+
+    fn score(fen: &str, params: &EvalParams) -> (i32, i32) {
+        let board = Board::from_str(fen).unwrap();
+        evaluate_pawns(
+            board.bitboard_for(&Piece::new(Player::White, PieceKind::Pawn)),
+            board.bitboard_for(&Piece::new(Player::Black, PieceKind::Pawn)),
+            params,
+        )
+    }
+
+    #[test]
+    fn finds_weak_pawns() {
+        // Every pawn is isolated, making three islands, and e2 and e3 are doubled.
+        let fen = "4k3/8/8/8/8/4P3/P1P1P3/4K3 w - - 0 1";
+        assert_eq!(
+            (-40, -40),
+            score(fen, &only(|p| p.isolated_pawn = [-10, -10]))
+        );
+        assert_eq!((-1, -2), score(fen, &only(|p| p.doubled_pawn = [-1, -2])));
+        assert_eq!((-10, -20), score(fen, &only(|p| p.pawn_island = [-5, -10])));
+
+        // b2 can't be defended by c3 and black's c4 pawn stops it advancing.
+        let fen = "4k3/8/8/8/2p5/2P5/1P6/4K3 w - - 0 1";
+        assert_eq!((-7, -9), score(fen, &only(|p| p.backward_pawn = [-7, -9])));
+    }
+
+    #[test]
+    fn rewards_passed_and_connected_pawns() {
+        // The pawn on e6 is passed, while black's a7 pawn is blocked by a6.
+        let params = only(|p| {
+            p.passed_pawn_middlegame = [0, 1, 2, 3, 4, 5, 6, 0];
+            p.passed_pawn_endgame = [0, 10, 20, 30, 40, 50, 60, 0];
+        });
+        assert_eq!((5, 50), score("4k3/p7/P3P3/8/8/8/8/4K3 w - - 0 1", &params));
+
+        // c3 defends d4, and e4 stands beside it, but nothing supports c3.
+        let params = only(|p| p.connected_pawn_middlegame = [0, 1, 2, 3, 4, 5, 6, 0]);
+        assert_eq!(
+            (3 + 3, 0),
+            score("4k3/8/8/8/3PP3/2P5/8/4K3 w - - 0 1", &params)
+        );
+    }
+
+    #[test]
+    fn caches_by_pawn_structure() {
+        let params = EvalParams::default();
+        let mut table = PawnTable::new();
+        let board = Board::from_str("4k3/pp6/8/8/8/8/PP3P2/4K3 w - - 0 1").unwrap();
+        let score = table.probe(&board, &params);
+
+        // Moving the kings doesn't change the pawn structure.
+        let moved = Board::from_str("3k4/pp6/8/8/8/8/PP3P2/3K4 w - - 0 1").unwrap();
+        assert_eq!(score, table.probe(&moved, &params));
+        // The lone f-pawn is an isolated extra island.
+        assert!(score.1 < 0);
+    }
+}
@@ -0,0 +1,189 @@
+use std::sync::Arc;
+
+use chess_common::{Piece, Player};
+use chess_core::Board;
+
+use super::{
+    pawns::PawnTable, terms::evaluate_terms, Endgames, EvalParams, EvalTerms, Evaluator,
+    MaterialSignature, SCALE_NORMAL,
+};
+use crate::zobrist::{PIECE_KINDS, PLAYERS};
+
+/// Evaluates positions with separate middlegame and endgame scores, which
+/// are blended according to how much material is left on the board. This
+/// lets pieces prefer different squares as the game goes on, such as the
+/// king hiding in the middlegame but centralizing in the endgame.
+#[derive(Clone, Debug)]
+pub struct TaperedEvaluator {
+    params: EvalParams,
+    terms: EvalTerms,
+    pawn_table: PawnTable,
+    /// Shared between the evaluators of every search thread.
+    endgames: Arc<Endgames>,
+}
+
+impl Default for TaperedEvaluator {
+    fn default() -> Self {
+        Self::new(EvalParams::default())
+    }
+}
+
+impl TaperedEvaluator {
+    pub fn new(params: EvalParams) -> Self {
+        Self {
+            params,
+            terms: EvalTerms::default(),
+            pawn_table: PawnTable::new(),
+            endgames: Arc::new(Endgames::default()),
+        }
+    }
+
+    pub fn params(&self) -> &EvalParams {
+        &self.params
+    }
+
+    pub fn terms(&self) -> &EvalTerms {
+        &self.terms
+    }
+
+    /// Chooses which evaluation terms are used, besides material and
+    /// piece-square tables which are always used.
+    pub fn set_terms(&mut self, terms: EvalTerms) {
+        self.terms = terms;
+    }
+
+    pub fn endgames(&self) -> &Endgames {
+        &self.endgames
+    }
+
+    /// Replaces the endgame knowledge, which is only used while the
+    /// endgames term is enabled.
+    pub fn set_endgames(&mut self, endgames: Endgames) {
+        self.endgames = Arc::new(endgames);
+    }
+
+    /// Scores the position from white's point of view.
+    pub fn evaluate_for_white(&mut self, board: &Board) -> i32 {
+        if !self.terms.endgames {
+            return self.evaluate_usually(board);
+        }
+
+        let signature = MaterialSignature::of(board);
+        if let Some(score) = self.endgames.evaluate(board, &signature) {
+            return score;
+        }
+        let score = self.evaluate_usually(board);
+        let strong = if score >= 0 {
+            Player::White
+        } else {
+            Player::Black
+        };
+        score * self.endgames.scale(board, &signature, strong) / SCALE_NORMAL
+    }
+
+    /// Scores the position from white's point of view without any
+    /// endgame knowledge.
+    fn evaluate_usually(&mut self, board: &Board) -> i32 {
+        let params = &self.params;
+        let (mut middlegame, mut endgame) = evaluate_terms(board, params, &self.terms);
+        if self.terms.pawn_structure {
+            let (pawns_middlegame, pawns_endgame) = self.pawn_table.probe(board, params);
+            middlegame += pawns_middlegame;
+            endgame += pawns_endgame;
+        }
+        let mut phase = 0;
+
+        for player in PLAYERS {
+            let sign = match player {
+                Player::White => 1,
+                Player::Black => -1,
+            };
+
+            for kind in PIECE_KINDS {
+                let kind_index = kind as usize;
+                let mut pieces = board.bitboard_for(&Piece::new(player, kind));
+                phase += params.phase_weights[kind_index] * pieces.count_ones() as i32;
+
+                while pieces != 0 {
+                    let square = pieces.trailing_zeros() as usize;
+                    pieces &= pieces - 1;
+
+                    let table_index = table_index(player, square);
+                    middlegame += sign
+                        * (params.material_middlegame[kind_index]
+                            + params.pst_middlegame[kind_index][table_index]);
+                    endgame += sign
+                        * (params.material_endgame[kind_index]
+                            + params.pst_endgame[kind_index][table_index]);
+                }
+            }
+        }
+
+        // Promotions can push the phase past that of the starting position.
+        let max_phase = params.max_phase();
+        let phase = phase.min(max_phase);
+        (middlegame * phase + endgame * (max_phase - phase)) / max_phase
+    }
+}
+
+impl Evaluator for TaperedEvaluator {
+    fn evaluate(&mut self, board: &Board) -> i32 {
+        let score = self.evaluate_for_white(board);
+        match board.player_to_move() {
+            Player::White => score,
+            Player::Black => -score,
+        }
+    }
+}
+
+/// Maps a square to its index in the piece-square tables, which are laid
+/// out from white's point of view starting at a8. Black's pieces use the
+/// vertically mirrored square.
+fn table_index(player: Player, square: usize) -> usize {
+    match player {
+        Player::White => square ^ 56,
+        Player::Black => square,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use chess_core::Board;
+
+    use super::TaperedEvaluator;
+    use crate::{EvalParams, EvalTerms, Evaluator};
+
+    #[test]
+    fn mirrored_positions_score_equally() {
+        let mut evaluator = TaperedEvaluator::default();
+        let white = Board::from_str("4k3/8/8/8/3N4/8/1P6/4K3 w - - 0 1").unwrap();
+        let black = Board::from_str("4k3/1p6/8/3n4/8/8/8/4K3 b - - 0 1").unwrap();
+
+        assert_eq!(evaluator.evaluate(&white), evaluator.evaluate(&black));
+        assert_eq!(0, evaluator.evaluate(&Board::default()));
+    }
+
+    #[test]
+    fn interpolates_between_phases() {
+        let mut params = EvalParams {
+            pst_middlegame: [[0; 64]; 6],
+            pst_endgame: [[0; 64]; 6],
+            ..Default::default()
+        };
+        params.material_middlegame[0] = 100;
+        params.material_endgame[0] = 200;
+        let mut evaluator = TaperedEvaluator::new(params);
+        evaluator.set_terms(EvalTerms::none());
+
+        // With only kings and pawns left, the endgame values apply.
+        let board = Board::from_str("4k3/8/8/8/8/8/P7/4K3 w - - 0 1").unwrap();
+        assert_eq!(200, evaluator.evaluate(&board));
+
+        // An extra pawn in the starting position counts at middlegame value.
+        let board =
+            Board::from_str("rnbqkbnr/ppppppp1/8/8/8/8/PPPPPPPP/RNBQKBNR b KQkq - 0 1").unwrap();
+        assert_eq!(-100, evaluator.evaluate(&board));
+    }
+}
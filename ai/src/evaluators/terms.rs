@@ -0,0 +1,319 @@
+use chess_common::{Piece, PieceKind, Player};
+use chess_core::Board;
+
+use super::{
+    pawns::{adjacent_files, ranks_ahead, FILE_A},
+    EvalParams,
+};
+use crate::attacks::{king_attacks, occupied, occupied_by, pawn_attacks, piece_attacks};
+
+/// Switches for the evaluation terms used by
+/// [`TaperedEvaluator`](super::TaperedEvaluator) on top of material and
+/// piece-square tables. Every term is enabled by default. Turning one off
+/// makes it possible to measure how much it is worth in engine matches.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct EvalTerms {
+    /// Passed, doubled, isolated, backward and connected pawns, and pawn
+    /// islands.
+    pub pawn_structure: bool,
+    /// Pawns sheltering their own king.
+    pub pawn_shield: bool,
+    /// Files near the king without any of its own pawns.
+    pub king_open_files: bool,
+    /// Enemy pieces attacking the squares around the king.
+    pub king_attackers: bool,
+    /// How many squares each piece can move to.
+    pub mobility: bool,
+    pub bishop_pair: bool,
+    /// Rooks on open and half-open files.
+    pub rook_files: bool,
+    pub rook_seventh_rank: bool,
+    pub knight_outposts: bool,
+    /// Specialised evaluation and scaling of known endgames, from the
+    /// evaluator's [`Endgames`](super::Endgames).
+    pub endgames: bool,
+}
+
+impl Default for EvalTerms {
+    fn default() -> Self {
+        Self {
+            pawn_structure: true,
+            pawn_shield: true,
+            king_open_files: true,
+            king_attackers: true,
+            mobility: true,
+            bishop_pair: true,
+            rook_files: true,
+            rook_seventh_rank: true,
+            knight_outposts: true,
+            endgames: true,
+        }
+    }
+}
+
+impl EvalTerms {
+    /// Disables every term, leaving only material and piece-square tables.
+    pub fn none() -> Self {
+        Self {
+            pawn_structure: false,
+            pawn_shield: false,
+            king_open_files: false,
+            king_attackers: false,
+            mobility: false,
+            bishop_pair: false,
+            rook_files: false,
+            rook_seventh_rank: false,
+            knight_outposts: false,
+            endgames: false,
+        }
+    }
+}
+
+/// Scores king safety, mobility and piece activity from white's point of
+/// view, returning the middlegame and endgame scores.
+pub(crate) fn evaluate_terms(board: &Board, params: &EvalParams, terms: &EvalTerms) -> (i32, i32) {
+    let (white_middlegame, white_endgame) = evaluate_side(board, Player::White, params, terms);
+    let (black_middlegame, black_endgame) = evaluate_side(board, Player::Black, params, terms);
+    (
+        white_middlegame - black_middlegame,
+        white_endgame - black_endgame,
+    )
+}
+
+fn evaluate_side(
+    board: &Board,
+    player: Player,
+    params: &EvalParams,
+    terms: &EvalTerms,
+) -> (i32, i32) {
+    let mut middlegame = 0;
+    let mut endgame = 0;
+    let mut add = |[term_middlegame, term_endgame]: [i32; 2], count: i32| {
+        middlegame += term_middlegame * count;
+        endgame += term_endgame * count;
+    };
+
+    let enemy = player.other_player();
+    let pieces = |player, kind| board.bitboard_for(&Piece::new(player, kind));
+    let own_pawns = pieces(player, PieceKind::Pawn);
+    let enemy_pawns = pieces(enemy, PieceKind::Pawn);
+    let occupied = occupied(board);
+    let own_pieces = occupied_by(board, player);
+
+    let king = pieces(player, PieceKind::King);
+    if king != 0 {
+        let king_square = king.trailing_zeros() as usize;
+        let king_file = king_square % 8;
+        let king_rank = king_square / 8;
+        let nearby_files = adjacent_files(king_file) | (FILE_A << king_file);
+
+        if terms.pawn_shield {
+            // The two ranks in front of the king.
+            let shield_ranks = ranks_ahead(player, king_rank)
+                & !ranks_ahead(player, forward(player, king_rank, 2));
+            let shield = own_pawns & nearby_files & shield_ranks;
+            add(params.pawn_shield, shield.count_ones() as i32);
+        }
+
+        if terms.king_open_files {
+            let open_files = (0..8)
+                .filter(|file| {
+                    let file_mask = FILE_A << file;
+                    nearby_files & file_mask != 0 && own_pawns & file_mask == 0
+                })
+                .count();
+            add(params.king_open_file, open_files as i32);
+        }
+
+        if terms.king_attackers {
+            let zone = king_attacks(king_square) | king;
+            for kind in [
+                PieceKind::Knight,
+                PieceKind::Bishop,
+                PieceKind::Rook,
+                PieceKind::Queen,
+            ] {
+                let mut attackers = pieces(enemy, kind);
+                while attackers != 0 {
+                    let square = attackers.trailing_zeros() as usize;
+                    attackers &= attackers - 1;
+                    if piece_attacks(kind, square, occupied) & zone != 0 {
+                        add([-params.king_attacker_weights[kind as usize], 0], 1);
+                    }
+                }
+            }
+        }
+    }
+
+    if terms.mobility {
+        // Squares attacked by enemy pawns are too dangerous to count.
+        let safe = !own_pieces & !pawn_attacks(enemy, enemy_pawns);
+        for kind in [
+            PieceKind::Knight,
+            PieceKind::Bishop,
+            PieceKind::Rook,
+            PieceKind::Queen,
+        ] {
+            let mut movers = pieces(player, kind);
+            while movers != 0 {
+                let square = movers.trailing_zeros() as usize;
+                movers &= movers - 1;
+                let moves = (piece_attacks(kind, square, occupied) & safe).count_ones() as i32;
+                add(
+                    [
+                        params.mobility_middlegame[kind as usize],
+                        params.mobility_endgame[kind as usize],
+                    ],
+                    moves,
+                );
+            }
+        }
+    }
+
+    if terms.bishop_pair && pieces(player, PieceKind::Bishop).count_ones() >= 2 {
+        add(params.bishop_pair, 1);
+    }
+
+    let mut rooks = pieces(player, PieceKind::Rook);
+    while rooks != 0 {
+        let square = rooks.trailing_zeros() as usize;
+        rooks &= rooks - 1;
+
+        let file_mask = FILE_A << (square % 8);
+        if terms.rook_files && own_pawns & file_mask == 0 {
+            if enemy_pawns & file_mask == 0 {
+                add(params.rook_open_file, 1);
+            } else {
+                add(params.rook_half_open_file, 1);
+            }
+        }
+
+        if terms.rook_seventh_rank && relative_rank(player, square / 8) == 6 {
+            add(params.rook_seventh_rank, 1);
+        }
+    }
+
+    if terms.knight_outposts {
+        let mut knights = pieces(player, PieceKind::Knight);
+        while knights != 0 {
+            let square = knights.trailing_zeros() as usize;
+            knights &= knights - 1;
+
+            let bit = 1_u64 << square;
+            let rank = square / 8;
+            let in_enemy_half = (3..=5).contains(&relative_rank(player, rank));
+            // A pawn of the other player on this square would attack the
+            // squares that defend it.
+            let defended = pawn_attacks(enemy, bit) & own_pawns != 0;
+            let chasers = enemy_pawns & adjacent_files(square % 8) & ranks_ahead(player, rank);
+            if in_enemy_half && defended && chasers == 0 {
+                add(params.knight_outpost, 1);
+            }
+        }
+    }
+
+    (middlegame, endgame)
+}
+
+/// How many ranks `rank` is from the player's first rank.
+fn relative_rank(player: Player, rank: usize) -> usize {
+    match player {
+        Player::White => rank,
+        Player::Black => 7 - rank,
+    }
+}
+
+/// The rank `steps` ranks in front of `rank` from the player's side,
+/// stopping at the edge of the board.
+fn forward(player: Player, rank: usize, steps: usize) -> usize {
+    match player {
+        Player::White => (rank + steps).min(7),
+        Player::Black => rank.saturating_sub(steps),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use chess_core::Board;
+
+    use super::{evaluate_terms, EvalTerms};
+    use crate::EvalParams;
+
+    /// Terms with only the term set by `set_term` enabled.
+    fn only(set_term: impl FnOnce(&mut EvalTerms)) -> EvalTerms {
+        let mut terms = EvalTerms::none();
+        set_term(&mut terms);
+        terms
+    }
+
+    fn score(fen: &str, terms: &EvalTerms) -> (i32, i32) {
+        let board = Board::from_str(fen).unwrap();
+        evaluate_terms(&board, &EvalParams::default(), terms)
+    }
+
+    #[test]
+    fn scores_king_safety() {
+        let params = EvalParams::default();
+        // White's king is sheltered by three pawns, black's by none.
+        let fen = "6k1/8/8/8/8/8/5PPP/6K1 w - - 0 1";
+        assert_eq!(
+            (params.pawn_shield[0] * 3, 0),
+            score(fen, &only(|terms| terms.pawn_shield = true))
+        );
+        assert_eq!(
+            (-params.king_open_file[0] * 3, 0),
+            score(fen, &only(|terms| terms.king_open_files = true))
+        );
+
+        // The queen on h5 attacks f7, next to black's king.
+        let fen = "4k3/8/8/7Q/8/8/8/4K3 w - - 0 1";
+        assert_eq!(
+            (params.king_attacker_weights[4], 0),
+            score(fen, &only(|terms| terms.king_attackers = true))
+        );
+    }
+
+    #[test]
+    fn scores_piece_activity() {
+        let params = EvalParams::default();
+        // Only white has both bishops.
+        let fen = "4k3/8/8/8/8/8/8/2B1KB2 w - - 0 1";
+        assert_eq!(
+            (params.bishop_pair[0], params.bishop_pair[1]),
+            score(fen, &only(|terms| terms.bishop_pair = true))
+        );
+
+        // White's rook is on an open file and its seventh rank.
+        let fen = "4k3/R7/8/8/8/8/8/4K3 w - - 0 1";
+        assert_eq!(
+            (params.rook_open_file[0], params.rook_open_file[1]),
+            score(fen, &only(|terms| terms.rook_files = true))
+        );
+        assert_eq!(
+            (params.rook_seventh_rank[0], params.rook_seventh_rank[1]),
+            score(fen, &only(|terms| terms.rook_seventh_rank = true))
+        );
+
+        // The knight on d5 is defended by e4 and no black pawn can chase it.
+        let fen = "4k3/pp6/8/3N4/4P3/8/8/4K3 w - - 0 1";
+        assert_eq!(
+            (params.knight_outpost[0], params.knight_outpost[1]),
+            score(fen, &only(|terms| terms.knight_outposts = true))
+        );
+    }
+
+    #[test]
+    fn counts_safe_mobility() {
+        let params = EvalParams::default();
+        // The knight on a1 can reach b3 and c2, but b3 is attacked by a
+        // black pawn.
+        let fen = "4k3/8/8/8/p7/8/8/N3K3 w - - 0 1";
+        assert_eq!(
+            (params.mobility_middlegame[1], params.mobility_endgame[1]),
+            score(fen, &only(|terms| terms.mobility = true))
+        );
+        assert_eq!((0, 0), score(fen, &only(|_| {})));
+    }
+}
@@ -2,10 +2,36 @@ use chess_core::Board;
 use evaluators::SimpleEvaluator;
 use search_algorithms::IterativeDeepeningMovesIterator;
 
+mod annotation;
+mod attacks;
 mod search_algorithms;
 mod evaluators;
+mod problems;
+mod puzzles;
+mod random;
+mod test_suite;
+mod tuning;
+mod zobrist;
 
+pub use annotation::{
+    analyse_game, AnalysedMove, AnalysisErr, AnalysisSettings, GameAnalysis, MoveQuality,
+};
+pub use evaluators::{
+    Endgames, EvalParams, EvalParamsErr, EvalTerms, EvaluationFn, Evaluator, MaterialSignature,
+    Network, NetworkErr, NnueEvaluator, ScaleFn, TaperedEvaluator, SCALE_NORMAL,
+};
 pub(crate) use search_algorithms::SearchAlgorithm;
+pub use problems::{
+    parse_problems, solve, solve_mate, Dual, MateSolution, Problem, ProblemErr, ProblemSolution,
+    SolutionNode, Stipulation, StipulationKind,
+};
+pub use search_algorithms::{
+    Limits, PonderHandle, PvLine, Score, SearchFeatures, SearchInfo, SearchInfoKind, SearchResult,
+    Searcher, Skill, StopHandle, TranspositionTable, MAX_SKILL_LEVEL,
+};
+pub use puzzles::{find_puzzles, Puzzle, PuzzleKind, PuzzleSettings, PuzzleTheme};
+pub use test_suite::{run_test_position, TestOutcome, TestPosition, TestSuiteErr};
+pub use tuning::{position_from_epd, positions_from_game, Tuner, TuningPosition};
 
 pub fn iterative_deepening_basic(position: &mut Board, search_depth: usize) -> SimpleEvaluator<IterativeDeepeningMovesIterator> {
     SimpleEvaluator::new(
@@ -0,0 +1,274 @@
+use std::collections::HashMap;
+
+use chess_core::{Board, SelectedMove};
+
+use super::{find_duals, legal_moves, make, notation, position_of, undo, Dual, SolutionNode};
+use crate::zobrist;
+
+/// The solution of a direct mate problem, where the side to move forces
+/// mate against any defence.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MateSolution {
+    /// The number of moves the mate had to be forced in.
+    pub moves: usize,
+    /// Every first move that forces mate in time, each followed by every
+    /// defence and, after each defence, every continuation that mates as
+    /// quickly as possible.
+    pub keys: Vec<SolutionNode>,
+}
+
+impl MateSolution {
+    /// Gets whether the problem has a solution at all.
+    pub fn is_solved(&self) -> bool {
+        !self.keys.is_empty()
+    }
+
+    /// Gets whether the problem has more than one key. The solver can't
+    /// tell which key the composer intended, so every key is a cook of
+    /// the others.
+    pub fn is_cooked(&self) -> bool {
+        self.keys.len() > 1
+    }
+
+    /// Gets whether the problem has exactly one key and no duals.
+    pub fn is_sound(&self) -> bool {
+        self.keys.len() == 1 && self.duals().is_empty()
+    }
+
+    /// Finds every dual in the solution trees.
+    pub fn duals(&self) -> Vec<Dual> {
+        let mut duals = Vec::new();
+        for key in self.keys.iter() {
+            find_duals(key, &mut vec![key.notation.clone()], &mut duals);
+        }
+        duals
+    }
+}
+
+/// Finds every way for the side to move to force mate in at most `moves`
+/// moves. Only the rules of chess are used, not the evaluation, so the
+/// solution is a proof.
+pub fn solve_mate(board: &Board, moves: usize) -> MateSolution {
+    let mut board = position_of(board);
+    let mut solver = MateSolver::default();
+    let keys = if moves == 0 {
+        Vec::new()
+    } else {
+        legal_moves(&board)
+            .into_iter()
+            .filter(|move_| solver.move_forces_mate(&mut board, move_, moves))
+            .collect::<Vec<_>>()
+    };
+
+    let keys = keys
+        .iter()
+        .map(|key| solver.attacker_tree(&mut board, key, moves))
+        .collect();
+    MateSolution { moves, keys }
+}
+
+#[derive(Default)]
+struct MateSolver {
+    /// Whether the side to move can force mate, keyed by position and the
+    /// number of moves.
+    cache: HashMap<(u64, usize), bool>,
+}
+
+impl MateSolver {
+    /// Gets whether the side to move can force mate in at most `moves`.
+    fn forces_mate(&mut self, board: &mut Board, moves: usize) -> bool {
+        let key = (zobrist::hash(board), moves);
+        if let Some(result) = self.cache.get(&key) {
+            return *result;
+        }
+
+        let result = legal_moves(board)
+            .iter()
+            .any(|move_| self.move_forces_mate(board, move_, moves));
+        self.cache.insert(key, result);
+        result
+    }
+
+    fn move_forces_mate(&mut self, board: &mut Board, move_: &SelectedMove, moves: usize) -> bool {
+        make(board, move_);
+        let result = if !board.is_check() {
+            // Only a check can mate, so quiet moves need time to spare.
+            moves > 1 && self.every_defence_fails(board, moves - 1)
+        } else {
+            board.is_check_mate() || (moves > 1 && self.every_defence_fails(board, moves - 1))
+        };
+        undo(board);
+        result
+    }
+
+    /// Gets whether the attacker can force mate in at most `moves` after
+    /// every defence. Stalemate is a successful defence.
+    fn every_defence_fails(&mut self, board: &mut Board, moves: usize) -> bool {
+        let defences = legal_moves(board);
+        if defences.is_empty() {
+            return false;
+        }
+
+        defences.iter().all(|defence| {
+            make(board, defence);
+            let result = self.forces_mate(board, moves);
+            undo(board);
+            result
+        })
+    }
+
+    /// Builds the tree after an attacking move that forces mate in at most
+    /// `moves`.
+    fn attacker_tree(
+        &mut self,
+        board: &mut Board,
+        move_: &SelectedMove,
+        moves: usize,
+    ) -> SolutionNode {
+        let notation = notation(board, move_);
+        make(board, move_);
+        let children = if board.is_check_mate() {
+            Vec::new()
+        } else {
+            legal_moves(board)
+                .iter()
+                .map(|defence| self.defence_tree(board, defence, moves - 1))
+                .collect()
+        };
+        undo(board);
+
+        SolutionNode {
+            move_: move_.clone(),
+            notation,
+            children,
+        }
+    }
+
+    /// Builds the tree after a defence, following only the continuations
+    /// that mate as quickly as possible.
+    fn defence_tree(
+        &mut self,
+        board: &mut Board,
+        move_: &SelectedMove,
+        moves: usize,
+    ) -> SolutionNode {
+        let notation = notation(board, move_);
+        make(board, move_);
+        let fastest = (1..=moves)
+            .find(|moves| self.forces_mate(board, *moves))
+            .expect("every defence to allow mate");
+        let continuations = legal_moves(board)
+            .into_iter()
+            .filter(|continuation| self.move_forces_mate(board, continuation, fastest))
+            .collect::<Vec<_>>();
+        let children = continuations
+            .iter()
+            .map(|continuation| self.attacker_tree(board, continuation, fastest))
+            .collect();
+        undo(board);
+
+        SolutionNode {
+            move_: move_.clone(),
+            notation,
+            children,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+    use std::time::{Duration, Instant};
+
+    use chess_core::Board;
+
+    use super::solve_mate;
+
+    #[test]
+    fn solves_mate_in_one() {
+        let board = Board::from_str("6k1/5ppp/8/8/8/8/5PPP/R5K1 w - - 0 1").unwrap();
+        let solution = solve_mate(&board, 1);
+
+        assert!(solution.is_sound());
+        assert_eq!("Ra8#", solution.keys[0].notation);
+        assert!(solution.keys[0].children.is_empty());
+    }
+
+    #[test]
+    fn reports_cooks() {
+        let board = Board::from_str("6k1/5ppp/8/8/8/8/5PPP/R3R1K1 w - - 0 1").unwrap();
+        let solution = solve_mate(&board, 1);
+
+        assert!(solution.is_cooked());
+        let mut keys = solution
+            .keys
+            .iter()
+            .map(|key| key.notation.as_str())
+            .collect::<Vec<_>>();
+        keys.sort();
+        assert_eq!(vec!["Ra8#", "Re8#"], keys);
+    }
+
+    #[test]
+    fn solves_mate_in_two_with_full_tree() {
+        let board = Board::from_str("kbK5/pp6/1P6/8/8/8/8/R7 w - - 0 1").unwrap();
+        let solution = solve_mate(&board, 2);
+
+        assert_eq!(1, solution.keys.len());
+        let key = &solution.keys[0];
+        assert_eq!("Ra6", key.notation);
+        for defence in key.children.iter() {
+            assert!(!defence.children.is_empty(), "{key}");
+        }
+    }
+
+    #[test]
+    fn solves_mate_in_three() {
+        // The discovered check Ra6+ wins the rook on a8 for the bishop's
+        // diagonal, and both defences still lose to mate.
+        let board = Board::from_str("r5rk/5p1p/5R2/4B3/8/8/7P/7K w - - 0 1").unwrap();
+        let solution = solve_mate(&board, 3);
+
+        assert!(solution.is_sound());
+        let key = &solution.keys[0];
+        assert_eq!("Ra6+", key.notation);
+        assert_eq!(2, key.children.len());
+        for defence in key.children.iter() {
+            assert!(!defence.children.is_empty(), "{key}");
+        }
+        assert!(!solve_mate(&board, 2).is_solved());
+    }
+
+    #[test]
+    #[ignore = "a benchmark whose wall time depends on the machine"]
+    fn solves_mate_in_three_within_a_second() {
+        let board = Board::from_str("r5rk/5p1p/5R2/4B3/8/8/7P/7K w - - 0 1").unwrap();
+        let start = Instant::now();
+        let solution = solve_mate(&board, 3);
+
+        assert!(solution.is_solved());
+        assert!(start.elapsed() < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn reports_duals() {
+        let board = Board::from_str("k7/8/8/3K4/8/8/8/6Q1 w - - 0 1").unwrap();
+        let solution = solve_mate(&board, 3);
+
+        assert!(solution.is_cooked());
+        let duals = solution.duals();
+        assert!(!duals.is_empty());
+        assert!(duals.iter().all(|dual| dual.continuations.len() > 1));
+        assert!(!solution.is_sound());
+    }
+
+    #[test]
+    fn stalemate_is_not_mate() {
+        // Qb6 would stalemate rather than mate.
+        let board = Board::from_str("k7/8/2K5/8/8/8/8/1Q6 w - - 0 1").unwrap();
+        let solution = solve_mate(&board, 1);
+
+        assert!(solution.keys.iter().all(|key| key.notation.ends_with('#')));
+        assert!(solution.keys.iter().all(|key| key.notation != "Qb6"));
+    }
+}
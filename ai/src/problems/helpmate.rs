@@ -0,0 +1,75 @@
+use std::collections::HashMap;
+
+use chess_core::Board;
+
+use super::{gives_mate, legal_moves, make, notation, position_of, undo, SolutionNode};
+use crate::zobrist;
+
+/// Finds every sequence of `moves` moves by each side, starting with the
+/// side to move, that ends with its opponent mating it. The solutions are
+/// returned as trees of cooperating moves whose leaves are the mates.
+pub(super) fn solve_helpmate(board: &Board, moves: usize) -> Vec<SolutionNode> {
+    let mut board = position_of(board);
+    HelpmateSolver::default().tree(&mut board, 2 * moves)
+}
+
+#[derive(Default)]
+struct HelpmateSolver {
+    /// Whether the side to move can be helped into mate, keyed by position
+    /// and the number of plies left.
+    cache: HashMap<(u64, usize), bool>,
+}
+
+impl HelpmateSolver {
+    /// Gets whether some sequence of exactly `plies` moves ends in mate.
+    fn reaches_mate(&mut self, board: &mut Board, plies: usize) -> bool {
+        if plies == 1 {
+            return legal_moves(board)
+                .iter()
+                .any(|move_| gives_mate(board, move_));
+        }
+
+        let key = (zobrist::hash(board), plies);
+        if let Some(result) = self.cache.get(&key) {
+            return *result;
+        }
+
+        let result = legal_moves(board).iter().any(|move_| {
+            make(board, move_);
+            let result = self.reaches_mate(board, plies - 1);
+            undo(board);
+            result
+        });
+        self.cache.insert(key, result);
+        result
+    }
+
+    fn tree(&mut self, board: &mut Board, plies: usize) -> Vec<SolutionNode> {
+        let mut nodes = Vec::new();
+        for move_ in legal_moves(board) {
+            if plies == 1 {
+                if gives_mate(board, &move_) {
+                    nodes.push(SolutionNode {
+                        notation: notation(board, &move_),
+                        move_,
+                        children: Vec::new(),
+                    });
+                }
+                continue;
+            }
+
+            let notation = notation(board, &move_);
+            make(board, &move_);
+            if self.reaches_mate(board, plies - 1) {
+                let children = self.tree(board, plies - 1);
+                nodes.push(SolutionNode {
+                    move_,
+                    notation,
+                    children,
+                });
+            }
+            undo(board);
+        }
+        nodes
+    }
+}
@@ -0,0 +1,241 @@
+mod direct_mate;
+mod helpmate;
+mod selfmate;
+mod stipulation;
+
+use std::str::FromStr;
+
+use chess_core::{Board, SelectedMove};
+
+pub use direct_mate::{solve_mate, MateSolution};
+pub use stipulation::{parse_problems, Problem, ProblemErr, Stipulation, StipulationKind};
+
+/// A move in a solution tree, followed by every move that answers it.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SolutionNode {
+    pub move_: SelectedMove,
+    /// The move in standard algebraic notation.
+    pub notation: String,
+    pub children: Vec<SolutionNode>,
+}
+
+impl SolutionNode {
+    fn write_tree(&self, f: &mut std::fmt::Formatter<'_>, indent: usize) -> std::fmt::Result {
+        writeln!(f, "{:indent$}{}", "", self.notation)?;
+        for child in self.children.iter() {
+            child.write_tree(f, indent + 2)?;
+        }
+        Ok(())
+    }
+
+    fn collect_lines(&self, line: &mut Vec<String>, lines: &mut Vec<Vec<String>>) {
+        line.push(self.notation.clone());
+        if self.children.is_empty() {
+            lines.push(line.clone());
+        }
+        for child in self.children.iter() {
+            child.collect_lines(line, lines);
+        }
+        line.pop();
+    }
+}
+
+impl std::fmt::Display for SolutionNode {
+    /// Writes the tree with one move per line, indenting each answer under
+    /// the move it answers.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.write_tree(f, 0)
+    }
+}
+
+/// A position in a solution where more than one continuation mates as
+/// quickly as possible.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Dual {
+    /// The moves leading to the position, ending with the defence.
+    pub line: Vec<String>,
+    pub continuations: Vec<String>,
+}
+
+/// The solution of a [`Problem`] of any stipulation.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ProblemSolution {
+    pub stipulation: Stipulation,
+    /// For helpmates, every solution as a tree of cooperating moves whose
+    /// leaves are the mates. For the other stipulations, every key followed
+    /// by every defence and the fastest continuations after each.
+    pub trees: Vec<SolutionNode>,
+}
+
+impl ProblemSolution {
+    pub fn is_solved(&self) -> bool {
+        !self.trees.is_empty()
+    }
+
+    /// Gets the number of solutions. Each line of a helpmate is a separate
+    /// solution, while the other stipulations have one solution per key.
+    pub fn solution_count(&self) -> usize {
+        match self.stipulation.kind {
+            StipulationKind::Helpmate => self.lines().len(),
+            _ => self.trees.len(),
+        }
+    }
+
+    /// Gets whether the problem has more than one solution, which means it
+    /// has been cooked.
+    pub fn is_cooked(&self) -> bool {
+        self.solution_count() > 1
+    }
+
+    /// Finds every dual in the solution trees. Helpmates have no defences,
+    /// so they never have duals.
+    pub fn duals(&self) -> Vec<Dual> {
+        let mut duals = Vec::new();
+        if self.stipulation.kind != StipulationKind::Helpmate {
+            for key in self.trees.iter() {
+                find_duals(key, &mut vec![key.notation.clone()], &mut duals);
+            }
+        }
+        duals
+    }
+
+    /// Gets every line from the first move to a mate.
+    pub fn lines(&self) -> Vec<Vec<String>> {
+        let mut lines = Vec::new();
+        for tree in self.trees.iter() {
+            tree.collect_lines(&mut Vec::new(), &mut lines);
+        }
+        lines
+    }
+}
+
+/// Finds every solution to a problem, using only the rules of chess.
+pub fn solve(problem: &Problem) -> ProblemSolution {
+    let Stipulation { kind, moves } = problem.stipulation;
+    let trees = match kind {
+        StipulationKind::DirectMate => solve_mate(&problem.board, moves).keys,
+        StipulationKind::Helpmate => helpmate::solve_helpmate(&problem.board, moves),
+        StipulationKind::Selfmate => selfmate::solve_selfmate(&problem.board, moves, false),
+        StipulationKind::Reflexmate => selfmate::solve_selfmate(&problem.board, moves, true),
+    };
+
+    ProblemSolution {
+        stipulation: problem.stipulation,
+        trees,
+    }
+}
+
+/// `attacker_move` has just been added to `line`.
+fn find_duals(attacker_move: &SolutionNode, line: &mut Vec<String>, duals: &mut Vec<Dual>) {
+    for defence in attacker_move.children.iter() {
+        line.push(defence.notation.clone());
+        if defence.children.len() > 1 {
+            duals.push(Dual {
+                line: line.clone(),
+                continuations: defence
+                    .children
+                    .iter()
+                    .map(|continuation| continuation.notation.clone())
+                    .collect(),
+            });
+        }
+        for continuation in defence.children.iter() {
+            line.push(continuation.notation.clone());
+            find_duals(continuation, line, duals);
+            line.pop();
+        }
+        line.pop();
+    }
+}
+
+/// Starts a board from the position alone, since solvers have no use for
+/// the moves that led to it.
+fn position_of(board: &Board) -> Board {
+    Board::from_str(&board.to_fen_string()).expect("the board's FEN to be valid")
+}
+
+/// Gets a move in standard algebraic notation, including the check or
+/// mate suffix.
+fn notation(board: &Board, move_: &SelectedMove) -> String {
+    let mut position = position_of(board);
+    position
+        .make_move(move_.clone())
+        .expect("solution moves to be legal");
+    position.get_move_history_acn()[0].to_string()
+}
+
+fn legal_moves(board: &Board) -> Vec<SelectedMove> {
+    board.possible_moves().collect()
+}
+
+fn gives_mate(board: &mut Board, move_: &SelectedMove) -> bool {
+    make(board, move_);
+    let mate = board.is_check_mate();
+    undo(board);
+    mate
+}
+
+fn make(board: &mut Board, move_: &SelectedMove) {
+    board
+        .make_move_unchecked(move_.clone())
+        .expect("legal moves to be playable");
+}
+
+fn undo(board: &mut Board) {
+    board.undo().expect("a move to be on the undo stack");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{solve, Problem};
+
+    #[test]
+    fn solves_helpmates() {
+        let problem = "k7/8/1K6/8/8/8/8/7R b - - 0 1 h#1"
+            .parse::<Problem>()
+            .unwrap();
+        let solution = solve(&problem);
+
+        assert_eq!(vec![vec!["Kb8", "Rh8#"]], solution.lines());
+        assert!(!solution.is_cooked());
+    }
+
+    #[test]
+    fn solves_selfmates() {
+        let problem = "K7/2Q3r1/k7/1r6/8/p7/4Q1p1/8 w - - 0 1 s#1"
+            .parse::<Problem>()
+            .unwrap();
+        let solution = solve(&problem);
+
+        assert_eq!(vec![vec!["Qa7+", "Rxa7#"]], solution.lines());
+        assert!(solution.duals().is_empty());
+    }
+
+    #[test]
+    fn reflexmates_oblige_either_side_to_mate() {
+        // Black would rather not mate after Kh2, but has to in a reflexmate.
+        let fen = "8/8/8/4r3/6p1/8/5k2/7K w - - 0 1";
+        let selfmate = solve(&format!("{fen} s#1").parse::<Problem>().unwrap());
+        let reflexmate = solve(&format!("{fen} r#1").parse::<Problem>().unwrap());
+
+        assert!(!selfmate.is_solved());
+        assert_eq!(1, reflexmate.solution_count());
+        assert_eq!("Kh2", reflexmate.trees[0].notation);
+        assert!(reflexmate
+            .lines()
+            .iter()
+            .all(|line| line.last().unwrap().ends_with('#')));
+    }
+
+    #[test]
+    fn reports_cooked_helpmates() {
+        // With two moves each, there are many ways to reach a back-rank mate.
+        let problem = "k7/8/1K6/8/8/8/8/7R b - - 0 1 h#2"
+            .parse::<Problem>()
+            .unwrap();
+        let solution = solve(&problem);
+
+        assert!(solution.is_cooked());
+        assert!(solution.lines().iter().all(|line| line.len() == 4));
+    }
+}
@@ -0,0 +1,165 @@
+use std::collections::HashMap;
+
+use chess_core::{Board, SelectedMove};
+
+use super::{gives_mate, legal_moves, make, notation, position_of, undo, SolutionNode};
+use crate::zobrist;
+
+/// Finds every key that forces the opponent to mate the side to move in at
+/// most `moves` moves. In a reflexmate, either side has to mate whenever
+/// it can.
+///
+/// Each key is followed by every defence and, after each defence that
+/// isn't itself the mate, every continuation that forces mate as quickly
+/// as possible.
+pub(super) fn solve_selfmate(board: &Board, moves: usize, reflex: bool) -> Vec<SolutionNode> {
+    let mut board = position_of(board);
+    let mut solver = SelfmateSolver {
+        reflex,
+        cache: HashMap::new(),
+    };
+    let keys = solver
+        .attacker_moves(&mut board)
+        .into_iter()
+        .filter(|key| solver.move_forces_mate(&mut board, key, moves))
+        .collect::<Vec<_>>();
+
+    keys.iter()
+        .map(|key| solver.attacker_tree(&mut board, key, moves))
+        .collect()
+}
+
+struct SelfmateSolver {
+    reflex: bool,
+    /// Whether the side to move can force its own mate, keyed by position
+    /// and the number of moves.
+    cache: HashMap<(u64, usize), bool>,
+}
+
+impl SelfmateSolver {
+    /// Gets the moves the attacker may play. In a reflexmate an attacker
+    /// who can mate has to, which ends the problem unsolved, so it has no
+    /// useful moves at all.
+    fn attacker_moves(&self, board: &mut Board) -> Vec<SelectedMove> {
+        let moves = legal_moves(board);
+        if self.reflex && moves.iter().any(|move_| gives_mate(board, move_)) {
+            return Vec::new();
+        }
+        moves
+    }
+
+    /// Gets the moves the defender may play. In a reflexmate a defender who
+    /// can mate has to.
+    fn defender_moves(&self, board: &mut Board) -> Vec<SelectedMove> {
+        let moves = legal_moves(board);
+        if self.reflex {
+            let mates = moves
+                .iter()
+                .filter(|move_| gives_mate(board, move_))
+                .cloned()
+                .collect::<Vec<_>>();
+            if !mates.is_empty() {
+                return mates;
+            }
+        }
+        moves
+    }
+
+    /// Gets whether the side to move can force its own mate in at most
+    /// `moves`.
+    fn forces_mate(&mut self, board: &mut Board, moves: usize) -> bool {
+        let key = (zobrist::hash(board), moves);
+        if let Some(result) = self.cache.get(&key) {
+            return *result;
+        }
+
+        let result = self
+            .attacker_moves(board)
+            .iter()
+            .any(|move_| self.move_forces_mate(board, move_, moves));
+        self.cache.insert(key, result);
+        result
+    }
+
+    fn move_forces_mate(&mut self, board: &mut Board, move_: &SelectedMove, moves: usize) -> bool {
+        make(board, move_);
+        let result = self.every_defence_mates(board, moves);
+        undo(board);
+        result
+    }
+
+    /// Gets whether every defence either mates the attacker or still lets
+    /// the attacker force mate with the moves left after the one just
+    /// played. A defender with no moves at all has been mated or
+    /// stalemated, which fails the problem.
+    fn every_defence_mates(&mut self, board: &mut Board, moves: usize) -> bool {
+        let defences = self.defender_moves(board);
+        if defences.is_empty() {
+            return false;
+        }
+
+        defences.iter().all(|defence| {
+            make(board, defence);
+            let result = board.is_check_mate() || (moves > 1 && self.forces_mate(board, moves - 1));
+            undo(board);
+            result
+        })
+    }
+
+    fn attacker_tree(
+        &mut self,
+        board: &mut Board,
+        move_: &SelectedMove,
+        moves: usize,
+    ) -> SolutionNode {
+        let notation = notation(board, move_);
+        make(board, move_);
+        let children = self
+            .defender_moves(board)
+            .iter()
+            .map(|defence| self.defence_tree(board, defence, moves))
+            .collect();
+        undo(board);
+
+        SolutionNode {
+            move_: move_.clone(),
+            notation,
+            children,
+        }
+    }
+
+    /// Builds the tree after a defence, following only the continuations
+    /// that force mate as quickly as possible.
+    fn defence_tree(
+        &mut self,
+        board: &mut Board,
+        move_: &SelectedMove,
+        moves: usize,
+    ) -> SolutionNode {
+        let notation = notation(board, move_);
+        make(board, move_);
+        let children = if board.is_check_mate() {
+            Vec::new()
+        } else {
+            let fastest = (1..moves)
+                .find(|moves| self.forces_mate(board, *moves))
+                .expect("every defence to allow mate");
+            let continuations = self
+                .attacker_moves(board)
+                .into_iter()
+                .filter(|continuation| self.move_forces_mate(board, continuation, fastest))
+                .collect::<Vec<_>>();
+            continuations
+                .iter()
+                .map(|continuation| self.attacker_tree(board, continuation, fastest))
+                .collect()
+        };
+        undo(board);
+
+        SolutionNode {
+            move_: move_.clone(),
+            notation,
+            children,
+        }
+    }
+}
@@ -0,0 +1,183 @@
+use std::{
+    error::Error,
+    fmt::{Debug, Display},
+    str::FromStr,
+};
+
+use chess_core::Board;
+use chess_parsers::FenErr;
+
+/// What the side to move has to achieve.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum StipulationKind {
+    /// The side to move forces mate against any defence.
+    DirectMate,
+    /// Both sides cooperate so that the side to move is mated. The side to
+    /// move starts, so its opponent makes the mating move.
+    Helpmate,
+    /// The side to move forces its opponent to mate it, against any
+    /// defence.
+    Selfmate,
+    /// A selfmate where either side has to mate whenever it can.
+    Reflexmate,
+}
+
+/// The goal of a problem and the number of moves it has to be reached in,
+/// written the usual way, such as `#2`, `h#3`, `s#2` or `r#4`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Stipulation {
+    pub kind: StipulationKind,
+    pub moves: usize,
+}
+
+impl FromStr for Stipulation {
+    type Err = ();
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let (kind, moves) = if let Some(moves) = text.strip_prefix("h#") {
+            (StipulationKind::Helpmate, moves)
+        } else if let Some(moves) = text.strip_prefix("s#") {
+            (StipulationKind::Selfmate, moves)
+        } else if let Some(moves) = text.strip_prefix("r#") {
+            (StipulationKind::Reflexmate, moves)
+        } else if let Some(moves) = text.strip_prefix('#') {
+            (StipulationKind::DirectMate, moves)
+        } else {
+            return Err(());
+        };
+
+        match moves.parse::<usize>() {
+            Ok(moves) if moves > 0 => Ok(Self { kind, moves }),
+            _ => Err(()),
+        }
+    }
+}
+
+impl Display for Stipulation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let prefix = match self.kind {
+            StipulationKind::DirectMate => "",
+            StipulationKind::Helpmate => "h",
+            StipulationKind::Selfmate => "s",
+            StipulationKind::Reflexmate => "r",
+        };
+        write!(f, "{prefix}#{}", self.moves)
+    }
+}
+
+/// A position and what has to be achieved from it.
+///
+/// Problems are written as a FEN followed by the stipulation, such as
+/// `k7/8/1K6/8/8/8/8/7R b - - 0 1 h#1`. [`parse_problems`] reads a file
+/// with one problem per line.
+#[derive(Clone)]
+pub struct Problem {
+    pub board: Board,
+    pub stipulation: Stipulation,
+}
+
+impl Problem {
+    fn parse(text: &str, line: usize) -> Result<Self, ProblemErr> {
+        let text = text.trim();
+        let (fen, stipulation) = text
+            .rsplit_once(char::is_whitespace)
+            .ok_or(ProblemErr::MissingStipulation { line })?;
+        let stipulation = stipulation
+            .parse()
+            .map_err(|_| ProblemErr::InvalidStipulation {
+                stipulation: stipulation.to_string(),
+                line,
+            })?;
+        let board = Board::from_str(fen.trim()).map_err(|err| ProblemErr::Fen { err, line })?;
+
+        Ok(Self { board, stipulation })
+    }
+}
+
+impl FromStr for Problem {
+    type Err = ProblemErr;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        Self::parse(text, 1)
+    }
+}
+
+/// Reads every problem in `text`, which has one problem per line. Blank
+/// lines and lines starting with `#` are skipped.
+pub fn parse_problems(text: &str) -> Result<Vec<Problem>, ProblemErr> {
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+        .map(|(index, line)| Problem::parse(line, index + 1))
+        .collect()
+}
+
+pub enum ProblemErr {
+    /// The line has a FEN but nothing after it.
+    MissingStipulation {
+        line: usize,
+    },
+    InvalidStipulation {
+        stipulation: String,
+        line: usize,
+    },
+    Fen {
+        err: FenErr,
+        line: usize,
+    },
+}
+
+impl Debug for ProblemErr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Just defer to the Display impl
+        write!(f, "{}", self)
+    }
+}
+
+impl Display for ProblemErr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProblemErr::MissingStipulation { line } => {
+                write!(f, "Expected a stipulation after the FEN on line {line}")
+            }
+            ProblemErr::InvalidStipulation { stipulation, line } => {
+                write!(f, "Unknown stipulation {stipulation:?} on line {line}")
+            }
+            ProblemErr::Fen { err, line } => write!(f, "{err} on line {line}"),
+        }
+    }
+}
+
+impl Error for ProblemErr {}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_problems, ProblemErr, Stipulation, StipulationKind};
+
+    #[test]
+    fn round_trips_stipulations() {
+        for text in ["#2", "h#3", "s#1", "r#12"] {
+            assert_eq!(text, text.parse::<Stipulation>().unwrap().to_string());
+        }
+        assert_eq!(
+            StipulationKind::Helpmate,
+            "h#3".parse::<Stipulation>().unwrap().kind
+        );
+        assert!("x#2".parse::<Stipulation>().is_err());
+        assert!("h#0".parse::<Stipulation>().is_err());
+    }
+
+    #[test]
+    fn parses_problem_files() {
+        let text = "# A helpmate\nk7/8/1K6/8/8/8/8/7R b - - 0 1 h#1\n\n6k1/5ppp/8/8/8/8/5PPP/R5K1 w - - 0 1 #1\n";
+        let problems = parse_problems(text).unwrap();
+
+        assert_eq!(2, problems.len());
+        assert_eq!(StipulationKind::Helpmate, problems[0].stipulation.kind);
+        assert_eq!(1, problems[1].stipulation.moves);
+        assert!(matches!(
+            parse_problems("\n6k1/5ppp/8/8/8/8/5PPP/R5K1 w - - 0 1 z#1"),
+            Err(ProblemErr::InvalidStipulation { line: 2, .. })
+        ));
+    }
+}
@@ -0,0 +1,412 @@
+use std::fmt::Display;
+
+use chess_common::{Location, Piece, PieceKind, Player};
+use chess_core::{Board, SelectedMove};
+use chess_parsers::{ParsedGame, PieceMove};
+
+use crate::{
+    annotation::{capped, replay_game, san_line},
+    attacks::{attackers_to, king_attacks, occupied, occupied_by, pawn_attacks, piece_attacks},
+    search_algorithms::{square_index, ORDERING_VALUES},
+    AnalysisErr, Evaluator, Limits, PvLine, Score, Searcher,
+};
+
+/// How deeply to search each position, and the centipawn thresholds a
+/// position has to cross to become a puzzle. A move wins if it scores at
+/// least `winning` for the side that plays it, and an alternative is no
+/// good if it scores at most `equal` for a winning puzzle, or at most
+/// `-winning` for a saving one.
+#[derive(Clone, Debug)]
+pub struct PuzzleSettings {
+    pub limits: Limits,
+    pub winning: i32,
+    pub equal: i32,
+    /// How many moves the solver plays at most, unless the solution ends in
+    /// mate.
+    pub max_moves: usize,
+    /// How many plies at the start of each game to skip, since the opening
+    /// rarely has puzzles worth the time it takes to search for them.
+    pub skipped_plies: usize,
+}
+
+impl Default for PuzzleSettings {
+    fn default() -> Self {
+        Self {
+            limits: Limits::depth(10),
+            winning: 300,
+            equal: 100,
+            max_moves: 3,
+            skipped_plies: 10,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PuzzleKind {
+    /// Only one move wins decisively.
+    Winning,
+    /// Only one move avoids losing.
+    Saving,
+}
+
+impl Display for PuzzleKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PuzzleKind::Winning => write!(f, "winning"),
+            PuzzleKind::Saving => write!(f, "saving"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PuzzleTheme {
+    /// A solution move attacks two or more pieces worth more than the piece
+    /// that moved, or the king and another piece.
+    Fork,
+    /// A solution move pins a piece to its king or to a more valuable
+    /// piece behind it.
+    Pin,
+    /// The solution mates in this many moves.
+    MateIn(usize),
+    /// The solution mates a king that is shut in on its first rank by its
+    /// own pieces.
+    BackRankMate,
+}
+
+impl Display for PuzzleTheme {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PuzzleTheme::Fork => write!(f, "fork"),
+            PuzzleTheme::Pin => write!(f, "pin"),
+            PuzzleTheme::MateIn(moves) => write!(f, "mateIn{moves}"),
+            PuzzleTheme::BackRankMate => write!(f, "backRankMate"),
+        }
+    }
+}
+
+/// A position from a game in which the side to move has exactly one good
+/// move, along with the line that proves it.
+#[derive(Clone, Debug)]
+pub struct Puzzle {
+    /// The position the puzzle starts from, with the solver to move.
+    pub fen: String,
+    /// The ply of the game the puzzle starts at, counted from 0.
+    pub ply: usize,
+    pub kind: PuzzleKind,
+    /// The score of the solution from the solver's point of view.
+    pub score: Score,
+    /// The solver's moves and the opponent's replies, starting and ending
+    /// with a solver's move.
+    pub solution: Vec<SelectedMove>,
+    pub notation: Vec<PieceMove>,
+    pub themes: Vec<PuzzleTheme>,
+}
+
+/// Searches every position of `game` after the skipped opening plies for
+/// puzzles. A position is a puzzle if exactly one move wins decisively, and
+/// the side to move wasn't already winning before the last move, or if
+/// exactly one move saves a position that would otherwise be lost. The
+/// solution is extended with the opponent's best replies for as long as
+/// each of the solver's moves stays the only good one.
+///
+/// The searcher's multi-PV setting is restored before returning.
+pub fn find_puzzles<E: Evaluator + Clone + Send>(
+    game: &ParsedGame,
+    searcher: &mut Searcher<E>,
+    settings: &PuzzleSettings,
+) -> Result<Vec<Puzzle>, AnalysisErr> {
+    let (mut board, played) = replay_game(game)?;
+
+    let multi_pv = searcher.multi_pv();
+    searcher.set_multi_pv(2);
+    searcher.new_game();
+
+    let mut puzzles = Vec::new();
+    // The score of the previous position from the point of view of the side
+    // that was to move there.
+    let mut previous: Option<i32> = None;
+    let mut next_ply = settings.skipped_plies;
+    for (ply, move_) in played.into_iter().enumerate() {
+        if ply >= next_ply {
+            let lines = search(searcher, &mut board, settings);
+            let already_winning = previous.is_some_and(|score| -score >= settings.winning);
+            if let Some(kind) = puzzle_kind(&lines, settings) {
+                if kind == PuzzleKind::Saving || !already_winning {
+                    let puzzle = build_puzzle(searcher, &board, ply, kind, &lines[0], settings);
+                    next_ply = ply + puzzle.solution.len();
+                    puzzles.push(puzzle);
+                }
+            }
+            previous = lines.first().map(|line| capped(line.score));
+        }
+        board
+            .make_move(move_)
+            .expect("the replayed moves to be legal");
+    }
+
+    searcher.set_multi_pv(multi_pv);
+    Ok(puzzles)
+}
+
+/// Searches the position for its two best lines, returning no lines if the
+/// game is over there.
+fn search<E: Evaluator + Clone + Send>(
+    searcher: &mut Searcher<E>,
+    board: &mut Board,
+    settings: &PuzzleSettings,
+) -> Vec<PvLine> {
+    if board.possible_moves().next().is_none() {
+        return Vec::new();
+    }
+    searcher.search(board, settings.limits.clone()).lines
+}
+
+/// Works out whether the best of `lines` is the only good move. A position
+/// with a single legal move is never a puzzle.
+fn puzzle_kind(lines: &[PvLine], settings: &PuzzleSettings) -> Option<PuzzleKind> {
+    let (best, second) = match lines {
+        [best, second, ..] => (best.score, second.score),
+        _ => return None,
+    };
+    if is_only_move(PuzzleKind::Winning, best, second, settings) {
+        Some(PuzzleKind::Winning)
+    } else if is_only_move(PuzzleKind::Saving, best, second, settings) {
+        Some(PuzzleKind::Saving)
+    } else {
+        None
+    }
+}
+
+fn is_only_move(kind: PuzzleKind, best: Score, second: Score, settings: &PuzzleSettings) -> bool {
+    match (kind, best, second) {
+        // Any mate in one solves a puzzle, so a second one doesn't matter.
+        (PuzzleKind::Winning, Score::Mate(1), _) => true,
+        // A slower mate is as good as a faster one for the solver.
+        (PuzzleKind::Winning, Score::Mate(mate), Score::Mate(moves)) if mate > 0 => moves < 0,
+        (PuzzleKind::Winning, best, second) => {
+            capped(best) >= settings.winning && capped(second) <= settings.equal
+        }
+        (PuzzleKind::Saving, best, second) => {
+            capped(best) > -settings.equal && capped(second) <= -settings.winning
+        }
+    }
+}
+
+/// Plays out the solution starting with `best`, adding the opponent's
+/// best reply and the solver's next move for as long as that move is the
+/// only good one.
+fn build_puzzle<E: Evaluator + Clone + Send>(
+    searcher: &mut Searcher<E>,
+    board: &Board,
+    ply: usize,
+    kind: PuzzleKind,
+    best: &PvLine,
+    settings: &PuzzleSettings,
+) -> Puzzle {
+    let mut position = board.clone();
+    let solver = position.player_to_move();
+    let is_mate = matches!(best.score, Score::Mate(moves) if moves > 0);
+
+    let mut solution = Vec::new();
+    let mut themes = Vec::new();
+    let mut solver_move = best.pv[0].clone();
+    loop {
+        position
+            .make_move(solver_move.clone())
+            .expect("the searched move to be legal");
+        solution.push(solver_move.clone());
+        for theme in move_themes(&position, &solver_move) {
+            if !themes.contains(&theme) {
+                themes.push(theme);
+            }
+        }
+
+        let solver_moves = solution.len().div_ceil(2);
+        if position.is_check_mate() || (!is_mate && solver_moves >= settings.max_moves) {
+            break;
+        }
+
+        let replies = search(searcher, &mut position, settings);
+        let reply = match replies.first() {
+            Some(reply) => reply.pv[0].clone(),
+            None => break,
+        };
+        position
+            .make_move(reply.clone())
+            .expect("the searched move to be legal");
+
+        let lines = search(searcher, &mut position, settings);
+        let next = match lines.as_slice() {
+            [only] => only,
+            [best, second, ..] if is_only_move(kind, best.score, second.score, settings) => best,
+            _ => {
+                position.undo().expect("the reply to be undoable");
+                break;
+            }
+        };
+        solution.push(reply);
+        solver_move = next.pv[0].clone();
+    }
+
+    if position.is_check_mate() {
+        themes.push(PuzzleTheme::MateIn(solution.len().div_ceil(2)));
+        if is_back_rank_mate(&position, solver.other_player()) {
+            themes.push(PuzzleTheme::BackRankMate);
+        }
+    }
+
+    Puzzle {
+        fen: board.to_fen_string(),
+        ply,
+        kind,
+        score: best.score,
+        notation: san_line(board, &solution),
+        solution,
+        themes,
+    }
+}
+
+fn piece_value(kind: PieceKind) -> i32 {
+    ORDERING_VALUES[kind as usize]
+}
+
+/// The themes shown by `move_`, which has just been played on `board`.
+fn move_themes(board: &Board, move_: &SelectedMove) -> Vec<PuzzleTheme> {
+    let square = square_index(move_.move_().to());
+    let mover = match board.at(move_.move_().to()) {
+        Some(piece) => piece,
+        None => return Vec::new(),
+    };
+    let player = mover.player();
+    let occupied = occupied(board);
+    let attacks = match mover.kind() {
+        PieceKind::Pawn => pawn_attacks(player, 1 << square),
+        kind => piece_attacks(kind, square, occupied),
+    };
+
+    let enemies = occupied_by(board, player.other_player());
+    let value_at = |square: usize| {
+        let location = Location::try_from(1_u64 << square).expect("one bit to be a square");
+        board
+            .at(&location)
+            .map(|piece| piece.kind())
+            .map_or(0, piece_value)
+    };
+
+    let mut themes = Vec::new();
+    let mover_value = piece_value(mover.kind());
+    let mut targets = attacks & enemies;
+    let mut forked = 0;
+    while targets != 0 {
+        let target = targets.trailing_zeros() as usize;
+        targets &= targets - 1;
+        if value_at(target) > mover_value {
+            forked += 1;
+        }
+    }
+    // A king attacking two pieces isn't much of a fork, and neither is a
+    // queen attacking the king and a pawn, but a knight attacking the king
+    // and the queen is.
+    if mover.kind() != PieceKind::King && forked >= 2 {
+        themes.push(PuzzleTheme::Fork);
+    }
+
+    if matches!(
+        mover.kind(),
+        PieceKind::Bishop | PieceKind::Rook | PieceKind::Queen
+    ) {
+        let mut pinned = attacks & enemies;
+        while pinned != 0 {
+            let target = pinned.trailing_zeros() as usize;
+            pinned &= pinned - 1;
+            let x_ray = piece_attacks(mover.kind(), square, occupied & !(1 << target)) & !attacks;
+            let behind = x_ray & enemies;
+            if behind != 0 && value_at(behind.trailing_zeros() as usize) > value_at(target) {
+                themes.push(PuzzleTheme::Pin);
+                break;
+            }
+        }
+    }
+
+    themes
+}
+
+/// Whether `player`'s king is mated on its first rank by a rook or queen
+/// along that rank, with its own pieces blocking every square in front of
+/// it.
+fn is_back_rank_mate(board: &Board, player: Player) -> bool {
+    let king = board.bitboard_for(&Piece::new(player, PieceKind::King));
+    let square = king.trailing_zeros() as usize;
+    let back_rank = match player {
+        Player::White => 0,
+        Player::Black => 7,
+    };
+    if king == 0 || square / 8 != back_rank {
+        return false;
+    }
+
+    let opponent = player.other_player();
+    let checkers = attackers_to(board, square, occupied(board)) & occupied_by(board, opponent);
+    let heavy_pieces = board.bitboard_for(&Piece::new(opponent, PieceKind::Rook))
+        | board.bitboard_for(&Piece::new(opponent, PieceKind::Queen));
+    let rank_mask = 0xFF_u64 << (back_rank * 8);
+    let in_front = king_attacks(square) & !rank_mask;
+
+    checkers != 0
+        && checkers & !(heavy_pieces & rank_mask) == 0
+        && in_front & !occupied_by(board, player) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use chess_core::Board;
+    use chess_parsers::parse_pgn;
+
+    use super::{find_puzzles, move_themes, PuzzleKind, PuzzleSettings, PuzzleTheme};
+    use crate::{Limits, Searcher};
+
+    fn settings() -> PuzzleSettings {
+        PuzzleSettings {
+            limits: Limits::depth(4),
+            skipped_plies: 0,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn finds_back_rank_mates() {
+        // Black's last move leaves the back rank undefended, and only Re8
+        // mates.
+        let pgn = b"[FEN \"2r3k1/5ppp/8/8/8/8/5PPP/4R1K1 b - - 0 1\"]\n\n\
+            1... Rc7 2. Re8# 1-0";
+        let game = parse_pgn(pgn).unwrap().remove(0);
+        let mut searcher = Searcher::new();
+        searcher.set_multi_pv(3);
+        let puzzles = find_puzzles(&game, &mut searcher, &settings()).unwrap();
+
+        assert_eq!(3, searcher.multi_pv());
+        let puzzle = puzzles.last().unwrap();
+        assert_eq!(1, puzzle.ply);
+        assert_eq!(PuzzleKind::Winning, puzzle.kind);
+        assert_eq!("Re8#", puzzle.notation[0].to_string());
+        assert_eq!(1, puzzle.solution.len());
+        assert!(puzzle.themes.contains(&PuzzleTheme::MateIn(1)));
+        assert!(puzzle.themes.contains(&PuzzleTheme::BackRankMate));
+    }
+
+    #[test]
+    fn tags_forks_and_pins() {
+        let mut board = Board::from_str("r3k3/8/8/1N6/8/8/8/4K3 w - - 0 1").unwrap();
+        let fork = board.make_move_acn("Nc7+").unwrap();
+        assert_eq!(vec![PuzzleTheme::Fork], move_themes(&board, &fork));
+
+        let mut board = Board::from_str("4k3/8/2n5/8/8/8/8/4KB2 w - - 0 1").unwrap();
+        let pin = board.make_move_acn("Bb5").unwrap();
+        assert_eq!(vec![PuzzleTheme::Pin], move_themes(&board, &pin));
+        board.undo().unwrap();
+        let quiet = board.make_move_acn("Bd3").unwrap();
+        assert!(move_themes(&board, &quiet).is_empty());
+    }
+}
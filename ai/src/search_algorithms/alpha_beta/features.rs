@@ -0,0 +1,62 @@
+/// Switches for the selective parts of [`Searcher`](super::Searcher)'s
+/// search. Every feature is enabled by default. Turning one off makes it
+/// possible to measure how much it is worth in self-play matches.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SearchFeatures {
+    /// Searches every move after the first with a null window, only
+    /// searching it again with the full window if it beats the first.
+    pub principal_variation_search: bool,
+    /// Starts each iteration with a narrow window around the previous
+    /// iteration's score, widening it if the score falls outside.
+    pub aspiration_windows: bool,
+    /// Lets the opponent move twice in a row, and prunes the node if a
+    /// reduced search still fails high. It is skipped when the side to
+    /// move has only pawns, where zugzwang is common.
+    pub null_move_pruning: bool,
+    /// Searches quiet moves late in the move order less deeply, searching
+    /// them again at full depth if they turn out to be good.
+    pub late_move_reductions: bool,
+    /// Skips quiet moves close to the horizon when the static evaluation is
+    /// too far below alpha for them to catch up.
+    pub futility_pruning: bool,
+    /// Drops straight into the quiescence search close to the horizon when
+    /// the static evaluation is far below alpha.
+    pub razoring: bool,
+    /// Searches positions where the side to move is in check one ply
+    /// deeper.
+    pub check_extensions: bool,
+    /// Searches the hash move one ply deeper when every other move is
+    /// clearly worse.
+    pub singular_extensions: bool,
+}
+
+impl Default for SearchFeatures {
+    fn default() -> Self {
+        Self {
+            principal_variation_search: true,
+            aspiration_windows: true,
+            null_move_pruning: true,
+            late_move_reductions: true,
+            futility_pruning: true,
+            razoring: true,
+            check_extensions: true,
+            singular_extensions: true,
+        }
+    }
+}
+
+impl SearchFeatures {
+    /// Disables every feature, leaving a plain alpha-beta search.
+    pub fn none() -> Self {
+        Self {
+            principal_variation_search: false,
+            aspiration_windows: false,
+            null_move_pruning: false,
+            late_move_reductions: false,
+            futility_pruning: false,
+            razoring: false,
+            check_extensions: false,
+            singular_extensions: false,
+        }
+    }
+}
@@ -0,0 +1,144 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use chess_core::SelectedMove;
+
+/// The conditions under which a search should stop. Every limit that is
+/// set applies, and the search stops as soon as any one of them is hit.
+/// If nothing is set, the search runs until the maximum supported depth
+/// or until it is stopped through its [`StopHandle`].
+#[derive(Clone, Debug, Default)]
+pub struct Limits {
+    /// The maximum number of plies to search.
+    pub depth: Option<usize>,
+    /// The maximum number of nodes to visit. Searches limited only by
+    /// depth and nodes always visit the same nodes in the same order, so
+    /// their results are reproducible.
+    pub nodes: Option<u64>,
+    /// The exact amount of time to spend searching.
+    pub move_time: Option<Duration>,
+    /// The time left on white's clock.
+    pub white_time: Option<Duration>,
+    /// The time left on black's clock.
+    pub black_time: Option<Duration>,
+    /// The time white gains after each move.
+    pub white_increment: Option<Duration>,
+    /// The time black gains after each move.
+    pub black_increment: Option<Duration>,
+    /// The number of moves until the next time control. If not set, the
+    /// remaining time has to last for the rest of the game.
+    pub moves_to_go: Option<u32>,
+    /// Only search for a mate in at most this many moves.
+    pub mate: Option<u32>,
+    /// Search until stopped, ignoring the clock. Depth and node limits
+    /// still apply.
+    pub infinite: bool,
+    /// Search on the opponent's time, in the position after the reply the
+    /// last search expected. The clock is ignored until
+    /// [`PonderHandle::ponder_hit`] is called, after which the search
+    /// carries on with the other limits as if it had just started. A
+    /// pondering search that runs out of things to search waits for the
+    /// ponder hit or for the search to be stopped before returning.
+    pub ponder: bool,
+    /// Only consider these moves in the root position. If empty, or if
+    /// none of them are legal there, every legal move is searched.
+    pub search_moves: Vec<SelectedMove>,
+}
+
+impl Limits {
+    /// Creates limits that search to a fixed depth.
+    pub fn depth(depth: usize) -> Self {
+        Self {
+            depth: Some(depth),
+            ..Default::default()
+        }
+    }
+
+    /// Creates limits that search a fixed number of nodes.
+    pub fn nodes(nodes: u64) -> Self {
+        Self {
+            nodes: Some(nodes),
+            ..Default::default()
+        }
+    }
+
+    /// Creates limits that search for a fixed amount of time.
+    pub fn move_time(move_time: Duration) -> Self {
+        Self {
+            move_time: Some(move_time),
+            ..Default::default()
+        }
+    }
+
+    /// Creates limits that look for a mate in at most `moves` moves.
+    pub fn mate(moves: u32) -> Self {
+        Self {
+            mate: Some(moves),
+            ..Default::default()
+        }
+    }
+
+    /// Creates limits that search until stopped.
+    pub fn infinite() -> Self {
+        Self {
+            infinite: true,
+            ..Default::default()
+        }
+    }
+}
+
+/// A handle that can stop a search from another thread. The search
+/// returns the best move it has found so far shortly after
+/// [`StopHandle::stop`] is called. A stop sent before the search starts
+/// isn't lost, and the flag is cleared when the search returns.
+#[derive(Clone, Debug, Default)]
+pub struct StopHandle(Arc<AtomicBool>);
+
+impl StopHandle {
+    pub fn stop(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// Clears a stop that arrived after the last search had already
+    /// returned, so that it doesn't cancel the next one. Only the owner of
+    /// the searcher should call this, before starting the next search.
+    pub fn reset(&self) {
+        self.0.store(false, Ordering::Relaxed);
+    }
+}
+
+/// A handle that tells a pondering search from another thread that the
+/// opponent played the expected move. The search keeps everything it has
+/// found so far and starts following the clock.
+///
+/// If the opponent plays something else, stop the search through its
+/// [`StopHandle`] instead and start a new one. The transposition table is
+/// kept between searches, so the work spent pondering still helps with
+/// the positions the two have in common.
+#[derive(Clone, Debug, Default)]
+pub struct PonderHandle(Arc<AtomicBool>);
+
+impl PonderHandle {
+    pub fn ponder_hit(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_hit(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// Clears a ponder hit that arrived after the last search had already
+    /// returned, so that the next pondering search doesn't see it.
+    pub fn reset(&self) {
+        self.0.store(false, Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,23 @@
+mod features;
+mod limits;
+mod move_ordering;
+mod score;
+mod search_info;
+mod search_result;
+mod searcher;
+mod see;
+mod skill;
+mod time_manager;
+mod transposition_table;
+
+pub use features::SearchFeatures;
+pub use limits::{Limits, PonderHandle, StopHandle};
+pub(crate) use move_ordering::ORDERING_VALUES;
+pub use score::Score;
+pub(crate) use score::MATE_THRESHOLD;
+pub use search_info::{SearchInfo, SearchInfoKind};
+pub use search_result::{PvLine, SearchResult};
+pub use searcher::Searcher;
+pub use skill::{Skill, MAX_SKILL_LEVEL};
+pub(crate) use transposition_table::square_index;
+pub use transposition_table::TranspositionTable;
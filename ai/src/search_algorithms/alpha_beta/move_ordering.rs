@@ -0,0 +1,378 @@
+use chess_common::{Piece, PieceKind, Player};
+use chess_core::{Board, SelectedMove};
+
+use super::{
+    score::MAX_PLY,
+    see::see,
+    transposition_table::{decode_move, encode_move, square_index},
+};
+
+/// History scores are halved whenever one of them grows past this value,
+/// which keeps recent cutoffs more important than old ones.
+const HISTORY_LIMIT: i32 = 1 << 20;
+
+/// The move ordering knowledge gathered while searching. It is kept
+/// between iterations and between searches of the same game.
+pub(crate) struct MoveOrdering {
+    /// Two quiet moves per ply that recently caused a beta cutoff.
+    killers: Vec<[u16; 2]>,
+    /// Butterfly history, indexed by `[player][from][to]`.
+    history: Vec<[[i32; 64]; 64]>,
+    /// The quiet move that last refuted a move, indexed by the refuted
+    /// move's `[player][piece kind][to]`.
+    counter_moves: Vec<[[u16; 64]; 6]>,
+}
+
+impl MoveOrdering {
+    pub(crate) fn new() -> Self {
+        Self {
+            killers: vec![[0; 2]; MAX_PLY + 1],
+            history: vec![[[0; 64]; 64]; 2],
+            counter_moves: vec![[[0; 64]; 6]; 2],
+        }
+    }
+
+    pub(crate) fn clear(&mut self) {
+        *self = Self::new();
+    }
+
+    /// Forgets the killers from the previous search, since the plies they
+    /// were found at no longer line up with the new root.
+    pub(crate) fn new_search(&mut self) {
+        for killers in self.killers.iter_mut() {
+            *killers = [0; 2];
+        }
+    }
+
+    /// Records that the quiet move `move_` caused a beta cutoff, and that
+    /// every quiet move in `tried` before it did not.
+    pub(crate) fn record_cutoff(
+        &mut self,
+        player: Player,
+        move_: &SelectedMove,
+        tried: &[SelectedMove],
+        depth: usize,
+        ply: usize,
+        previous_move: Option<&PreviousMove>,
+    ) {
+        let encoded = encode_move(move_);
+        let killers = &mut self.killers[ply.min(MAX_PLY)];
+        if killers[0] != encoded {
+            killers[1] = killers[0];
+            killers[0] = encoded;
+        }
+
+        if let Some(previous) = previous_move {
+            self.counter_moves[previous.piece.player().as_index()]
+                [previous.piece.kind() as usize][previous.to] = encoded;
+        }
+
+        let bonus = (depth * depth) as i32;
+        let history = &mut self.history[player.as_index()];
+        let mut overflowed = false;
+        for tried_move in tried {
+            let entry = &mut history[square_index(tried_move.move_().from())]
+                [square_index(tried_move.move_().to())];
+            if tried_move == move_ {
+                *entry += bonus;
+                overflowed |= *entry > HISTORY_LIMIT;
+            } else {
+                *entry -= bonus;
+                overflowed |= *entry < -HISTORY_LIMIT;
+            }
+        }
+
+        if overflowed {
+            for from in history.iter_mut() {
+                for entry in from.iter_mut() {
+                    *entry /= 2;
+                }
+            }
+        }
+    }
+
+    fn history_score(&self, player: Player, move_: &SelectedMove) -> i32 {
+        self.history[player.as_index()][square_index(move_.move_().from())]
+            [square_index(move_.move_().to())]
+    }
+}
+
+/// The move that led to the node being searched, used to look up its
+/// counter move.
+#[derive(Clone)]
+pub(crate) struct PreviousMove {
+    pub(crate) piece: Piece,
+    pub(crate) to: usize,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Stage {
+    HashMove,
+    Captures,
+    Killers,
+    CounterMove,
+    Quiets,
+    BadCaptures,
+    Done,
+}
+
+/// Hands out the moves of a position one at a time in the order most
+/// likely to produce an early cutoff: the hash move, then captures and
+/// promotions by MVV-LVA, then killer moves, the counter move, the
+/// remaining quiet moves by history score, and finally the captures that
+/// static exchange evaluation expects to lose material.
+pub(crate) struct MovePicker {
+    stage: Stage,
+    hash_move: Option<SelectedMove>,
+    killers: [u16; 2],
+    killer_index: usize,
+    counter_move: u16,
+    captures: Vec<(SelectedMove, i32)>,
+    quiets: Vec<(SelectedMove, i32)>,
+    bad_captures: Vec<(SelectedMove, i32)>,
+}
+
+impl MovePicker {
+    pub(crate) fn new(
+        ordering: &MoveOrdering,
+        hash_move: Option<SelectedMove>,
+        ply: usize,
+        previous_move: Option<&PreviousMove>,
+    ) -> Self {
+        let counter_move = match previous_move {
+            None => 0,
+            Some(previous) => {
+                ordering.counter_moves[previous.piece.player().as_index()]
+                    [previous.piece.kind() as usize][previous.to]
+            }
+        };
+
+        Self {
+            stage: Stage::HashMove,
+            hash_move,
+            killers: ordering.killers[ply.min(MAX_PLY)],
+            killer_index: 0,
+            counter_move,
+            captures: Vec::new(),
+            quiets: Vec::new(),
+            bad_captures: Vec::new(),
+        }
+    }
+
+    pub(crate) fn next(&mut self, board: &Board, ordering: &MoveOrdering) -> Option<SelectedMove> {
+        loop {
+            match self.stage {
+                Stage::HashMove => {
+                    self.stage = Stage::Captures;
+                    // The hash move might come from a colliding position, so
+                    // it is only played once move generation confirms it.
+                    self.generate(board, ordering);
+                    if let Some(hash_move) = self.hash_move.take() {
+                        if self.take(&hash_move) {
+                            return Some(hash_move);
+                        }
+                    }
+                }
+                Stage::Captures => match pop_best(&mut self.captures) {
+                    Some((move_, score)) => {
+                        if see(board, &move_) < 0 {
+                            self.bad_captures.push((move_, score));
+                            continue;
+                        }
+                        return Some(move_);
+                    }
+                    None => self.stage = Stage::Killers,
+                },
+                Stage::Killers => {
+                    if self.killer_index >= self.killers.len() {
+                        self.stage = Stage::CounterMove;
+                        continue;
+                    }
+
+                    let killer = self.killers[self.killer_index];
+                    self.killer_index += 1;
+                    if let Some(move_) = decode_move(killer) {
+                        if self.take_quiet(&move_) {
+                            return Some(move_);
+                        }
+                    }
+                }
+                Stage::CounterMove => {
+                    self.stage = Stage::Quiets;
+                    if let Some(move_) = decode_move(self.counter_move) {
+                        if self.take_quiet(&move_) {
+                            return Some(move_);
+                        }
+                    }
+                }
+                Stage::Quiets => match pop_best(&mut self.quiets) {
+                    Some((move_, _)) => return Some(move_),
+                    None => self.stage = Stage::BadCaptures,
+                },
+                Stage::BadCaptures => match pop_best(&mut self.bad_captures) {
+                    Some((move_, _)) => return Some(move_),
+                    None => self.stage = Stage::Done,
+                },
+                Stage::Done => return None,
+            }
+        }
+    }
+
+    fn generate(&mut self, board: &Board, ordering: &MoveOrdering) {
+        let player = board.player_to_move();
+        for move_ in board.possible_moves() {
+            if is_tactical(board, &move_) {
+                let score = mvv_lva(board, &move_);
+                self.captures.push((move_, score));
+            } else {
+                let score = ordering.history_score(player, &move_);
+                self.quiets.push((move_, score));
+            }
+        }
+    }
+
+    /// Removes the move from whichever list holds it, returning whether
+    /// it was found.
+    fn take(&mut self, move_: &SelectedMove) -> bool {
+        if let Some(i) = self.captures.iter().position(|(other, _)| other == move_) {
+            self.captures.swap_remove(i);
+            return true;
+        }
+        self.take_quiet(move_)
+    }
+
+    fn take_quiet(&mut self, move_: &SelectedMove) -> bool {
+        match self.quiets.iter().position(|(other, _)| other == move_) {
+            None => false,
+            Some(i) => {
+                self.quiets.swap_remove(i);
+                true
+            }
+        }
+    }
+}
+
+/// Removes and returns the highest scoring move. Selection is done lazily
+/// rather than sorting up front because most nodes cut off after trying
+/// only a few moves.
+fn pop_best(moves: &mut Vec<(SelectedMove, i32)>) -> Option<(SelectedMove, i32)> {
+    let best = moves
+        .iter()
+        .enumerate()
+        .max_by_key(|(_, (_, score))| *score)
+        .map(|(i, _)| i)?;
+    Some(moves.swap_remove(best))
+}
+
+/// Gets whether the move changes the material balance, which is true of
+/// captures (including en-passant) and promotions.
+pub(crate) fn is_tactical(board: &Board, move_: &SelectedMove) -> bool {
+    move_.promotion_kind().is_some() || captured_piece(board, move_).is_some()
+}
+
+/// Gets the kind of piece the move captures, if any.
+pub(crate) fn captured_piece(board: &Board, move_: &SelectedMove) -> Option<PieceKind> {
+    if let Some(piece) = board.at(move_.move_().to()) {
+        return Some(piece.kind());
+    }
+
+    // A pawn moving diagonally onto an empty square is capturing en-passant.
+    let from = move_.move_().from();
+    let to = move_.move_().to();
+    if from.file() != to.file()
+        && board
+            .at(from)
+            .is_some_and(|piece| piece.kind() == PieceKind::Pawn)
+    {
+        return Some(PieceKind::Pawn);
+    }
+
+    None
+}
+
+/// The value of each piece kind used for ordering, indexed by `PieceKind as usize`.
+pub(crate) const ORDERING_VALUES: [i32; 6] = [100, 320, 330, 500, 900, 20_000];
+
+/// Most valuable victim, least valuable attacker. Promotions are scored as
+/// capturing the material they gain.
+pub(crate) fn mvv_lva(board: &Board, move_: &SelectedMove) -> i32 {
+    let victim = captured_piece(board, move_)
+        .map(|kind| ORDERING_VALUES[kind as usize])
+        .unwrap_or(0);
+    let promotion = move_
+        .promotion_kind()
+        .map(|kind| ORDERING_VALUES[kind as usize] - ORDERING_VALUES[PieceKind::Pawn as usize])
+        .unwrap_or(0);
+    let attacker = board
+        .at(move_.move_().from())
+        .map(|piece| piece.kind() as i32)
+        .unwrap_or(0);
+
+    (victim + promotion) * 8 - attacker
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use chess_core::Board;
+
+    use super::{MoveOrdering, MovePicker};
+
+    fn picked_moves(
+        board: &Board,
+        picker: &mut MovePicker,
+        ordering: &MoveOrdering,
+    ) -> Vec<String> {
+        let mut moves = Vec::new();
+        while let Some(move_) = picker.next(board, ordering) {
+            moves.push(format!("{:?}", move_.move_()));
+        }
+        moves
+    }
+
+    #[test]
+    fn orders_captures_by_mvv_lva() {
+        // The pawn and the queen can both take the rook.
+        let board = Board::from_str("4k3/8/8/2p5/3r4/4P3/8/3QK3 w - - 0 1").unwrap();
+        let ordering = MoveOrdering::new();
+        let mut picker = MovePicker::new(&ordering, None, 0, None);
+        let moves = picked_moves(&board, &mut picker, &ordering);
+
+        assert_eq!("e3 -> d4", moves[0]);
+        // The pawn on c5 recaptures, so the queen taking the rook is tried last.
+        assert_eq!("d1 -> d4", moves[moves.len() - 1]);
+        assert_eq!(board.possible_moves().count(), moves.len());
+    }
+
+    #[test]
+    fn tries_hash_move_then_killers_first() {
+        let board = Board::default();
+        let mut ordering = MoveOrdering::new();
+        let all_moves = board.possible_moves().collect::<Vec<_>>();
+        let killer = all_moves
+            .iter()
+            .find(|move_| format!("{:?}", move_.move_()) == "g1 -> f3")
+            .unwrap()
+            .clone();
+        ordering.record_cutoff(
+            board.player_to_move(),
+            &killer,
+            std::slice::from_ref(&killer),
+            4,
+            3,
+            None,
+        );
+
+        let hash_move = all_moves
+            .iter()
+            .find(|move_| format!("{:?}", move_.move_()) == "e2 -> e4")
+            .cloned();
+        let mut picker = MovePicker::new(&ordering, hash_move, 3, None);
+        let moves = picked_moves(&board, &mut picker, &ordering);
+
+        assert_eq!("e2 -> e4", moves[0]);
+        assert_eq!("g1 -> f3", moves[1]);
+        assert_eq!(20, moves.len());
+    }
+}
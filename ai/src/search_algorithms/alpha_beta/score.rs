@@ -0,0 +1,86 @@
+use std::fmt::Display;
+
+/// The largest number of plies the search will ever look ahead.
+pub(crate) const MAX_PLY: usize = 128;
+
+/// The score given to a position where the side to move has been
+/// checkmated. Mates found deeper in the tree are scored closer to
+/// zero so that the search always prefers the quickest mate.
+pub(crate) const MATE: i32 = 30_000;
+
+/// A bound larger than any score the search can produce.
+pub(crate) const INFINITY: i32 = MATE + 1;
+
+/// Any score at least this far from zero is a forced mate.
+pub(crate) const MATE_THRESHOLD: i32 = MATE - MAX_PLY as i32;
+
+/// The score of a position from the perspective of the side to move.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Score {
+    /// A static evaluation in hundredths of a pawn.
+    Centipawns(i32),
+    /// A forced mate in the given number of full moves. Positive values
+    /// mean the side to move delivers the mate, negative values mean the
+    /// side to move gets mated.
+    Mate(i32),
+}
+
+impl Score {
+    /// Converts the internal search score into a [`Score`].
+    pub(crate) fn from_internal(value: i32) -> Self {
+        if value >= MATE_THRESHOLD {
+            let plies = MATE - value;
+            Score::Mate((plies + 1) / 2)
+        } else if value <= -MATE_THRESHOLD {
+            let plies = MATE + value;
+            Score::Mate(-(plies + 1) / 2)
+        } else {
+            Score::Centipawns(value)
+        }
+    }
+
+    /// Converts the score back into an internal search score.
+    pub(crate) fn to_internal(self) -> i32 {
+        match self {
+            Score::Centipawns(value) => value,
+            Score::Mate(moves) if moves > 0 => MATE - (2 * moves - 1),
+            Score::Mate(moves) => -MATE - 2 * moves,
+        }
+    }
+
+    /// Gets whether this score is a forced mate for either side.
+    pub fn is_mate(&self) -> bool {
+        matches!(self, Score::Mate(_))
+    }
+}
+
+impl Display for Score {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Score::Centipawns(centipawns) => write!(f, "cp {centipawns}"),
+            Score::Mate(moves) => write!(f, "mate {moves}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Score, MATE};
+
+    #[test]
+    fn mate_scores_round_to_full_moves() {
+        assert_eq!(Score::Mate(1), Score::from_internal(MATE - 1));
+        assert_eq!(Score::Mate(2), Score::from_internal(MATE - 3));
+        assert_eq!(Score::Mate(-1), Score::from_internal(-MATE + 2));
+        assert_eq!(Score::Mate(0), Score::from_internal(-MATE));
+        assert_eq!(Score::Centipawns(-250), Score::from_internal(-250));
+    }
+
+    #[test]
+    fn converts_back_to_internal_scores() {
+        for value in [MATE - 1, MATE - 4, -MATE, -MATE + 2, 0, 137] {
+            let score = Score::from_internal(value);
+            assert_eq!(score, Score::from_internal(score.to_internal()));
+        }
+    }
+}
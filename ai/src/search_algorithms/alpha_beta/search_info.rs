@@ -0,0 +1,88 @@
+use std::{str::FromStr, time::Duration};
+
+use chess_core::{Board, SelectedMove};
+
+use super::Score;
+
+/// A snapshot of a running search, passed to the callback set with
+/// [`Searcher::set_info_callback`].
+///
+/// [`Searcher::set_info_callback`]: super::Searcher::set_info_callback
+#[derive(Clone, Debug)]
+pub struct SearchInfo {
+    /// Whether an iteration has just finished, or the search is part of
+    /// the way through one.
+    pub kind: SearchInfoKind,
+    /// The depth of the iteration that finished, or of the one being
+    /// searched.
+    pub depth: usize,
+    /// The furthest ply from the root reached so far, including the
+    /// quiescence search.
+    pub seldepth: usize,
+    /// The nodes visited by every thread so far.
+    pub nodes: u64,
+    /// Nodes per second.
+    pub nps: u64,
+    /// How full the transposition table is, in permille.
+    pub hashfull: usize,
+    pub elapsed: Duration,
+    /// Which of the multi-PV lines this is, counting from 1.
+    pub multi_pv: usize,
+    /// The score of the line from the perspective of the side to move.
+    /// While an iteration is being searched, this is the score the
+    /// previous iteration gave the line.
+    pub score: Score,
+    /// The moves of the line, starting with the root move it is for.
+    pub pv: Vec<SelectedMove>,
+    /// The line in standard algebraic notation, including check and mate
+    /// suffixes.
+    pub pv_san: Vec<String>,
+    /// The line in the long algebraic notation used by UCI.
+    pub pv_uci: Vec<String>,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SearchInfoKind {
+    /// Sent once for each line after every completed iteration.
+    Iteration,
+    /// Sent for the best line at regular intervals during an iteration.
+    Progress,
+}
+
+/// The parts of a [`SearchInfo`] that only change when an iteration
+/// finishes, kept so that progress reports can repeat them.
+#[derive(Clone, Debug)]
+pub(crate) struct InfoLine {
+    pub(crate) score: Score,
+    pub(crate) pv: Vec<SelectedMove>,
+    pub(crate) pv_san: Vec<String>,
+    pub(crate) pv_uci: Vec<String>,
+}
+
+impl InfoLine {
+    pub(crate) fn new(board: &Board, score: Score, pv: Vec<SelectedMove>) -> Self {
+        Self {
+            score,
+            pv_san: san_line(board, &pv),
+            pv_uci: pv.iter().map(SelectedMove::to_uci).collect(),
+            pv,
+        }
+    }
+}
+
+/// Gets each move of the line in standard algebraic notation. The board
+/// is replayed from its FEN so that its earlier moves are left out.
+fn san_line(board: &Board, pv: &[SelectedMove]) -> Vec<String> {
+    let mut position =
+        Board::from_str(&board.to_fen_string()).expect("the board's FEN to be valid");
+    for move_ in pv {
+        if position.make_move(move_.clone()).is_err() {
+            break;
+        }
+    }
+    position
+        .get_move_history_acn()
+        .iter()
+        .map(|move_| move_.to_string())
+        .collect()
+}
@@ -0,0 +1,41 @@
+use chess_core::SelectedMove;
+
+use super::Score;
+
+/// The outcome of a completed search.
+#[derive(Clone, Debug)]
+pub struct SearchResult {
+    /// The best move found, or None if the side to move has no legal moves.
+    pub best_move: Option<SelectedMove>,
+    /// The score of the position from the perspective of the side to move.
+    pub score: Score,
+    /// The principal variation, starting with the best move.
+    pub pv: Vec<SelectedMove>,
+    /// The best lines from the position, best first, one for each of the
+    /// lines requested with [`Searcher::set_multi_pv`]. The first line is
+    /// always the same as the best move, score and principal variation.
+    ///
+    /// [`Searcher::set_multi_pv`]: super::Searcher::set_multi_pv
+    pub lines: Vec<PvLine>,
+    /// The deepest iteration that was completed.
+    pub depth: usize,
+    /// The number of nodes visited during the search.
+    pub nodes: u64,
+}
+
+impl SearchResult {
+    /// Gets the reply the search expects the opponent to play, which is
+    /// the move to ponder on.
+    pub fn ponder_move(&self) -> Option<&SelectedMove> {
+        self.pv.get(1)
+    }
+}
+
+/// One of the lines found by a multi-PV search.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PvLine {
+    /// The score of the line from the perspective of the side to move.
+    pub score: Score,
+    /// The moves of the line, starting with the root move it is for.
+    pub pv: Vec<SelectedMove>,
+}
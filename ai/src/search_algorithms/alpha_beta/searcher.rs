@@ -0,0 +1,1841 @@
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use chess_common::{Piece, PieceKind, Player};
+use chess_core::{Board, SelectedMove};
+
+use super::{
+    move_ordering::{
+        captured_piece, is_tactical, mvv_lva, MoveOrdering, MovePicker, PreviousMove,
+        ORDERING_VALUES,
+    },
+    score::{INFINITY, MATE, MATE_THRESHOLD, MAX_PLY},
+    search_info::InfoLine,
+    see::see,
+    skill::{Skill, CANDIDATE_LINES},
+    time_manager::TimeManager,
+    transposition_table::{square_index, Bound, TranspositionTable},
+    Limits, PonderHandle, PvLine, Score, SearchFeatures, SearchInfo, SearchInfoKind, SearchResult,
+    StopHandle,
+};
+use crate::{
+    random::Rng,
+    zobrist::{self, PositionKey},
+    Evaluator, TaperedEvaluator,
+};
+
+/// The safety margin used by delta pruning in the quiescence search.
+const DELTA_MARGIN: i32 = 200;
+
+/// The transposition table size used until [`Searcher::set_hash_size`] is called.
+const DEFAULT_HASH_SIZE_MB: usize = 16;
+
+/// How many nodes are searched between checks of the clock and the stop
+/// handle. Node limits are checked at every node so that they are exact.
+const CHECK_INTERVAL: u64 = 1024;
+
+/// How far either side of the previous iteration's score the first
+/// aspiration window reaches. The window doubles each time it fails.
+const ASPIRATION_WINDOW: i32 = 25;
+/// Shallower iterations are too unstable for aspiration windows to help.
+const ASPIRATION_MIN_DEPTH: usize = 4;
+
+const NULL_MOVE_MIN_DEPTH: usize = 3;
+/// How many plies shallower than usual the search after a null move is,
+/// growing by one for every six plies of depth.
+const NULL_MOVE_REDUCTION: usize = 2;
+
+/// Late move reductions only apply once this many moves have been searched
+/// at a node, at nodes with at least `LMR_MIN_DEPTH` plies left.
+const LMR_MIN_MOVES: usize = 3;
+const LMR_MIN_DEPTH: usize = 3;
+
+/// Futility pruning applies up to this many plies from the horizon, where
+/// a quiet move is assumed to gain at most `FUTILITY_MARGIN` per ply.
+const FUTILITY_MAX_DEPTH: usize = 3;
+const FUTILITY_MARGIN: i32 = 150;
+
+/// How far below alpha the static evaluation must be for razoring, by
+/// depth from one ply up.
+const RAZORING_MARGINS: [i32; 2] = [250, 400];
+
+const SINGULAR_MIN_DEPTH: usize = 6;
+/// How much worse than the hash move every other move must be, per ply of
+/// depth, for the hash move to be extended.
+const SINGULAR_MARGIN: i32 = 2;
+
+/// How often progress is reported during an iteration until
+/// [`Searcher::set_info_interval`] is called.
+const DEFAULT_INFO_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How often a pondering search that has nothing left to search checks for
+/// the ponder hit or the stop.
+const PONDER_WAIT_INTERVAL: Duration = Duration::from_millis(1);
+
+type InfoCallback = Box<dyn FnMut(&SearchInfo) + Send>;
+
+/// A negamax alpha-beta searcher driven by iterative deepening, scoring
+/// leaf positions with the evaluator `E`.
+///
+/// Searches can use several threads with Lazy SMP: helper threads search
+/// the same position alongside the main thread, and the entries they leave
+/// in the shared transposition table speed up the main thread's search.
+pub struct Searcher<E = TaperedEvaluator> {
+    evaluator: E,
+    nodes: u64,
+    /// The node count at the last periodic check of the limits. Root nodes
+    /// are counted without a check, so the count can step past any given
+    /// multiple of the check interval.
+    checked_nodes: u64,
+    transposition_table: Arc<TranspositionTable>,
+    /// The keys of every position from the start of the game up to the
+    /// node currently being searched, used to detect repetitions. The key
+    /// of each node is updated from its parent's as the move is made.
+    keys: Vec<PositionKey>,
+    ordering: MoveOrdering,
+    /// The moves made from the root to reach the node currently being
+    /// searched, with None for null moves.
+    move_stack: Vec<Option<PreviousMove>>,
+    stop_handle: StopHandle,
+    ponder_handle: PonderHandle,
+    node_limit: Option<u64>,
+    time_manager: Option<TimeManager>,
+    /// How many of the best lines each search reports.
+    multi_pv: usize,
+    features: SearchFeatures,
+    skill: Option<Skill>,
+    /// Makes the random choices for the skill level.
+    skill_rng: Rng,
+    /// Searchers for the helper threads. Helpers have no helpers of their own.
+    helpers: Vec<Searcher<E>>,
+    /// 0 for the main thread, and from 1 up for the helpers.
+    thread_index: usize,
+    /// The nodes searched by every thread in the current search, which each
+    /// adds in batches so that all of them can enforce node limits.
+    searched_nodes: Arc<AtomicU64>,
+    info_callback: Option<InfoCallback>,
+    info_interval: Duration,
+    /// When progress was last reported, if it has been this search.
+    last_info: Option<Instant>,
+    /// The depth of the iteration being searched.
+    iteration_depth: usize,
+    seldepth: usize,
+    /// The best line of the last completed iteration, repeated in progress
+    /// reports.
+    best_info_line: Option<InfoLine>,
+    /// Set once any limit has been hit. Every node returns immediately
+    /// from then on, and scores returned after that are meaningless.
+    stopped: bool,
+}
+
+impl Default for Searcher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Searcher {
+    pub fn new() -> Self {
+        Self::with_evaluator(TaperedEvaluator::default())
+    }
+}
+
+impl<E: Evaluator + Clone + Send> Searcher<E> {
+    pub fn with_evaluator(evaluator: E) -> Self {
+        Self {
+            evaluator,
+            nodes: 0,
+            checked_nodes: 0,
+            transposition_table: Arc::new(TranspositionTable::new(DEFAULT_HASH_SIZE_MB)),
+            keys: Vec::new(),
+            ordering: MoveOrdering::new(),
+            move_stack: Vec::new(),
+            stop_handle: StopHandle::default(),
+            ponder_handle: PonderHandle::default(),
+            node_limit: None,
+            time_manager: None,
+            multi_pv: 1,
+            features: SearchFeatures::default(),
+            skill: None,
+            skill_rng: Rng::new(0),
+            helpers: Vec::new(),
+            thread_index: 0,
+            searched_nodes: Arc::new(AtomicU64::new(0)),
+            info_callback: None,
+            info_interval: DEFAULT_INFO_INTERVAL,
+            last_info: None,
+            iteration_depth: 0,
+            seldepth: 0,
+            best_info_line: None,
+            stopped: false,
+        }
+    }
+
+    pub fn evaluator(&self) -> &E {
+        &self.evaluator
+    }
+
+    /// Gets a handle that can stop this searcher's searches from another
+    /// thread. A stop sent before a search starts makes that search return
+    /// as soon as it has a move.
+    pub fn stop_handle(&self) -> StopHandle {
+        self.stop_handle.clone()
+    }
+
+    /// Gets a handle that tells a pondering search, one started with
+    /// [`Limits::ponder`], that the opponent played the expected move.
+    pub fn ponder_handle(&self) -> PonderHandle {
+        self.ponder_handle.clone()
+    }
+
+    /// Replaces the transposition table with an empty one of roughly
+    /// `size_mb` megabytes.
+    pub fn set_hash_size(&mut self, size_mb: usize) {
+        self.transposition_table = Arc::new(TranspositionTable::new(size_mb));
+        for helper in self.helpers.iter_mut() {
+            helper.transposition_table = self.transposition_table.clone();
+        }
+    }
+
+    /// Sets how many threads each search uses, including the thread that
+    /// calls [`Searcher::search`]. Searches with more than one thread are
+    /// not deterministic, even with a node limit.
+    pub fn set_threads(&mut self, threads: usize) {
+        let helper_count = threads.max(1) - 1;
+        self.helpers.truncate(helper_count);
+        if helper_count == 0 {
+            self.searched_nodes.store(0, Ordering::Relaxed);
+        }
+        while self.helpers.len() < helper_count {
+            let mut helper = Searcher::with_evaluator(self.evaluator.clone());
+            helper.transposition_table = self.transposition_table.clone();
+            helper.stop_handle = self.stop_handle.clone();
+            helper.searched_nodes = self.searched_nodes.clone();
+            helper.features = self.features.clone();
+            helper.thread_index = self.helpers.len() + 1;
+            self.helpers.push(helper);
+        }
+    }
+
+    /// Gets how many threads each search uses.
+    pub fn threads(&self) -> usize {
+        self.helpers.len() + 1
+    }
+
+    /// Gets the transposition table used by this searcher.
+    pub fn transposition_table(&self) -> &Arc<TranspositionTable> {
+        &self.transposition_table
+    }
+
+    /// Sets how many of the best lines each search reports in
+    /// [`SearchResult::lines`]. Each line after the first is found by
+    /// searching the root again without the moves of the lines before it,
+    /// so every extra line costs about as much as the first.
+    pub fn set_multi_pv(&mut self, lines: usize) {
+        self.multi_pv = lines.max(1);
+    }
+
+    /// Gets how many lines each search reports.
+    pub fn multi_pv(&self) -> usize {
+        self.multi_pv
+    }
+
+    /// Chooses which of the selective search features to use.
+    pub fn set_features(&mut self, features: SearchFeatures) {
+        for helper in self.helpers.iter_mut() {
+            helper.features = features.clone();
+        }
+        self.features = features;
+    }
+
+    pub fn features(&self) -> &SearchFeatures {
+        &self.features
+    }
+
+    /// Weakens the searcher to the given skill level, or restores full
+    /// strength with None. This also restarts the skill's random choices
+    /// from its seed.
+    pub fn set_skill(&mut self, skill: Option<Skill>) {
+        if let Some(skill) = &skill {
+            self.skill_rng = Rng::new(skill.seed());
+        }
+        self.skill = skill;
+    }
+
+    pub fn skill(&self) -> Option<&Skill> {
+        self.skill.as_ref()
+    }
+
+    /// Calls `callback` with the progress of every search from now on:
+    /// once for each line after every completed iteration, and for the
+    /// best line at regular intervals during an iteration. The callback
+    /// runs on the thread that called [`Searcher::search`], in the middle
+    /// of the search, so it should return quickly. To consume the reports
+    /// elsewhere, send them down a channel from the callback.
+    pub fn set_info_callback(&mut self, callback: impl FnMut(&SearchInfo) + Send + 'static) {
+        self.info_callback = Some(Box::new(callback));
+    }
+
+    /// Stops reporting the progress of searches.
+    pub fn clear_info_callback(&mut self) {
+        self.info_callback = None;
+    }
+
+    /// Sets how often progress is reported during an iteration. Progress
+    /// is only checked every so many nodes, so reports can come a little
+    /// late.
+    pub fn set_info_interval(&mut self, interval: Duration) {
+        self.info_interval = interval;
+    }
+
+    /// Forgets everything learned from previous searches. This should be
+    /// called whenever the next search is not from the same game.
+    pub fn new_game(&mut self) {
+        self.transposition_table.clear();
+        self.ordering.clear();
+        for helper in self.helpers.iter_mut() {
+            helper.ordering.clear();
+        }
+    }
+
+    /// Searches the position for the best move within the given limits.
+    ///
+    /// If a limit is hit in the middle of an iteration, the result of the
+    /// last completed iteration is returned. At least one move is always
+    /// returned if there are any legal moves. The same goes for multi-PV
+    /// lines, which are all taken from the same iteration.
+    ///
+    /// With more than one thread, the result comes from whichever thread
+    /// completed the deepest iteration, preferring the main thread. Node
+    /// counts include every thread.
+    ///
+    /// The board is used as scratch space while searching, but is always
+    /// returned to its original position before this function returns.
+    pub fn search(&mut self, board: &mut Board, limits: Limits) -> SearchResult {
+        let result = match self.skill.clone() {
+            Some(skill) if !skill.is_full_strength() => {
+                self.search_with_skill(board, limits, &skill)
+            }
+            _ => self.search_at_full_strength(board, limits),
+        };
+        // The flags are only cleared once the search is over, so that a
+        // stop or ponder hit sent before it started still applies to it.
+        self.stop_handle.reset();
+        self.ponder_handle.reset();
+        result
+    }
+
+    /// Searches within the skill level's limits for several candidate
+    /// lines, and plays the one the skill level chooses.
+    fn search_with_skill(
+        &mut self,
+        board: &mut Board,
+        limits: Limits,
+        skill: &Skill,
+    ) -> SearchResult {
+        let limits = skill.limit(limits, &mut self.skill_rng);
+        let multi_pv = self.multi_pv;
+        self.multi_pv = multi_pv.max(CANDIDATE_LINES);
+        let mut result = self.search_at_full_strength(board, limits);
+        self.multi_pv = multi_pv;
+
+        if !result.lines.is_empty() {
+            let chosen = skill.choose(&result.lines, &mut self.skill_rng);
+            let line = result.lines[chosen].clone();
+            result.best_move = line.pv.first().cloned();
+            result.score = line.score;
+            result.pv = line.pv;
+            result.lines.truncate(multi_pv);
+        }
+        result
+    }
+
+    fn search_at_full_strength(&mut self, board: &mut Board, limits: Limits) -> SearchResult {
+        self.transposition_table.new_search();
+        // Reset even without helpers, or the total left over from an
+        // earlier multi-threaded search counts against the node limit.
+        self.searched_nodes.store(0, Ordering::Relaxed);
+        if self.helpers.is_empty() {
+            return self.iterate(board, &limits);
+        }
+
+        let mut helpers = std::mem::take(&mut self.helpers);
+        let (mut result, helper_results) = thread::scope(|scope| {
+            let threads = helpers
+                .iter_mut()
+                .map(|helper| {
+                    let mut board = board.clone();
+                    let limits = &limits;
+                    scope.spawn(move || helper.iterate(&mut board, limits))
+                })
+                .collect::<Vec<_>>();
+
+            let result = self.iterate(board, &limits);
+            // The helpers have no limits of their own, so they keep going
+            // until the main thread is done.
+            self.stop_handle.stop();
+            let helper_results = threads
+                .into_iter()
+                .map(|thread| thread.join().expect("helper threads not to panic"))
+                .collect::<Vec<_>>();
+            (result, helper_results)
+        });
+
+        result.nodes = self.nodes + helpers.iter().map(|helper| helper.nodes).sum::<u64>();
+        self.helpers = helpers;
+
+        // Helpers only search for a single line, so they can't stand in
+        // for the main thread's multi-PV lines.
+        if self.multi_pv == 1 {
+            for helper_result in helper_results {
+                if helper_result.depth > result.depth {
+                    result = SearchResult {
+                        nodes: result.nodes,
+                        ..helper_result
+                    };
+                }
+            }
+        }
+
+        result
+    }
+
+    /// Runs iterative deepening on this thread alone. Helpers ignore the
+    /// limits other than depth and nodes, and start their iterations one ply deeper
+    /// on every other thread, so that they don't all search the same
+    /// depth in step with the main thread.
+    fn iterate(&mut self, board: &mut Board, limits: &Limits) -> SearchResult {
+        self.nodes = 0;
+        self.checked_nodes = 0;
+        self.keys = game_keys(board);
+        self.ordering.new_search();
+        self.move_stack.clear();
+        self.stopped = false;
+        self.last_info = None;
+        self.seldepth = 0;
+        self.best_info_line = None;
+        // Every thread checks the node limit, so that a helper doesn't keep
+        // going while the main thread is waiting to be scheduled.
+        self.node_limit = limits.nodes;
+        self.time_manager =
+            (self.thread_index == 0).then(|| TimeManager::new(limits, board.player_to_move()));
+
+        let mut max_depth = limits.depth.unwrap_or(MAX_PLY).clamp(1, MAX_PLY);
+        if let Some(moves) = limits.mate {
+            // A mate in N moves is N moves by the side to move and N - 1
+            // replies.
+            max_depth = max_depth.min((2 * moves as usize).saturating_sub(1).max(1));
+        }
+        let mut result = SearchResult {
+            best_move: None,
+            score: Score::Centipawns(0),
+            pv: Vec::new(),
+            lines: Vec::new(),
+            depth: 0,
+            nodes: 0,
+        };
+
+        let mut root_moves = Vec::new();
+        let mut picker = MovePicker::new(&self.ordering, None, 0, None);
+        while let Some(move_) = picker.next(board, &self.ordering) {
+            root_moves.push(move_);
+        }
+        if root_moves
+            .iter()
+            .any(|move_| limits.search_moves.contains(move_))
+        {
+            root_moves.retain(|move_| limits.search_moves.contains(move_));
+        }
+        if root_moves.is_empty() {
+            result.score = if board.is_check() {
+                Score::from_internal(-MATE)
+            } else {
+                Score::Centipawns(0)
+            };
+            return result;
+        }
+
+        let multi_pv = self.multi_pv.min(root_moves.len());
+        let first_depth = (1 + self.thread_index % 2).min(max_depth);
+        let mut previous_score = None;
+        for depth in first_depth..=max_depth {
+            self.check_ponder_hit();
+            if depth > first_depth && !self.can_start_iteration() {
+                break;
+            }
+            self.iteration_depth = depth;
+
+            let mut lines = Vec::with_capacity(multi_pv);
+            for pv_index in 0..multi_pv {
+                let mut pv = Vec::new();
+                let score = if pv_index == 0 {
+                    self.aspiration_search(board, &mut root_moves, depth, previous_score, &mut pv)
+                } else {
+                    self.search_root(
+                        board,
+                        &mut root_moves,
+                        pv_index,
+                        depth,
+                        -INFINITY,
+                        INFINITY,
+                        &mut pv,
+                    )
+                };
+                if self.stopped {
+                    break;
+                }
+                self.extend_pv(board, &mut pv, depth);
+                lines.push((score, pv));
+            }
+
+            if self.stopped {
+                // A partial iteration is only better than nothing.
+                if result.best_move.is_none() {
+                    let line = PvLine {
+                        score: Score::Centipawns(0),
+                        pv: vec![root_moves[0].clone()],
+                    };
+                    result.best_move = Some(root_moves[0].clone());
+                    result.pv = line.pv.clone();
+                    result.lines = vec![line];
+                    result.nodes = self.nodes;
+                }
+                break;
+            }
+
+            // Each line is searched without the moves of the lines before
+            // it, so only table entries from earlier lines can make a later
+            // line score better. Keep the lines ranked, and search them in
+            // that order next iteration.
+            lines.sort_by_key(|(score, _)| -score);
+            for (root_move, (_, pv)) in root_moves.iter_mut().zip(&lines) {
+                *root_move = pv[0].clone();
+            }
+            previous_score = Some(lines[0].0);
+            let lines = lines
+                .into_iter()
+                .map(|(score, pv)| PvLine {
+                    score: Score::from_internal(score),
+                    pv,
+                })
+                .collect::<Vec<_>>();
+
+            result.best_move = lines[0].pv.first().cloned();
+            result.score = lines[0].score;
+            result.pv = lines[0].pv.clone();
+            result.lines = lines;
+            result.depth = depth;
+            result.nodes = self.nodes;
+            self.report_iteration(board, &result.lines);
+
+            // A forced mate can't be improved upon by searching deeper.
+            if result.score.is_mate() {
+                break;
+            }
+        }
+
+        self.wait_while_pondering();
+        result
+    }
+
+    /// Starts following the clock if the opponent played the move being
+    /// pondered on.
+    fn check_ponder_hit(&mut self) {
+        if let Some(time_manager) = self.time_manager.as_mut() {
+            if time_manager.is_pondering() && self.ponder_handle.is_hit() {
+                time_manager.ponder_hit();
+            }
+        }
+    }
+
+    /// A search can't return while it is pondering, since its result is
+    /// only wanted if the opponent plays the expected move.
+    fn wait_while_pondering(&mut self) {
+        loop {
+            self.check_ponder_hit();
+            let pondering = self
+                .time_manager
+                .as_ref()
+                .is_some_and(|time_manager| time_manager.is_pondering());
+            if !pondering || self.stop_handle.is_stopped() {
+                break;
+            }
+            thread::sleep(PONDER_WAIT_INTERVAL);
+        }
+    }
+
+    /// Searches the first line with an aspiration window around the score
+    /// of the previous iteration, widening the window whenever the score
+    /// falls outside of it.
+    fn aspiration_search(
+        &mut self,
+        board: &mut Board,
+        root_moves: &mut [SelectedMove],
+        depth: usize,
+        previous_score: Option<i32>,
+        pv: &mut Vec<SelectedMove>,
+    ) -> i32 {
+        let mut delta = ASPIRATION_WINDOW;
+        let (mut alpha, mut beta) = match previous_score {
+            Some(score) if self.features.aspiration_windows && depth >= ASPIRATION_MIN_DEPTH => (
+                (score - delta).max(-INFINITY),
+                (score + delta).min(INFINITY),
+            ),
+            _ => (-INFINITY, INFINITY),
+        };
+
+        loop {
+            let score = self.search_root(board, root_moves, 0, depth, alpha, beta, pv);
+            if self.stopped {
+                return 0;
+            }
+
+            if score <= alpha && alpha > -INFINITY {
+                alpha = (alpha - delta).max(-INFINITY);
+            } else if score >= beta && beta < INFINITY {
+                beta = (beta + delta).min(INFINITY);
+            } else {
+                return score;
+            }
+            delta *= 2;
+        }
+    }
+
+    /// Searches every root move from `pv_index` on, moving the best one to
+    /// `pv_index` so that it is excluded from the search for the next line
+    /// and searched first by the next iteration.
+    ///
+    /// If no move scores inside the window, the score is only a bound and
+    /// the principal variation is left empty when it fails low.
+    #[allow(clippy::too_many_arguments)]
+    fn search_root(
+        &mut self,
+        board: &mut Board,
+        root_moves: &mut [SelectedMove],
+        pv_index: usize,
+        depth: usize,
+        mut alpha: i32,
+        beta: i32,
+        pv: &mut Vec<SelectedMove>,
+    ) -> i32 {
+        self.nodes += 1;
+        pv.clear();
+
+        let original_alpha = alpha;
+        let mut best = -INFINITY;
+        let mut best_index = 0;
+        let mut child_pv = Vec::new();
+        let key = self.key();
+
+        let root_moves = &mut root_moves[pv_index..];
+        for (i, move_) in root_moves.iter().enumerate() {
+            self.make_move(board, move_);
+            let score = if i == 0 || !self.features.principal_variation_search {
+                -self.negamax(board, depth - 1, 1, -beta, -alpha, &mut child_pv, None)
+            } else {
+                let score =
+                    -self.negamax(board, depth - 1, 1, -alpha - 1, -alpha, &mut child_pv, None);
+                if score > alpha && score < beta {
+                    -self.negamax(board, depth - 1, 1, -beta, -alpha, &mut child_pv, None)
+                } else {
+                    score
+                }
+            };
+            self.undo_move(board);
+            if self.stopped {
+                return 0;
+            }
+
+            best = best.max(score);
+            if score > alpha {
+                alpha = score;
+                best_index = i;
+                pv.clear();
+                pv.push(move_.clone());
+                pv.append(&mut child_pv);
+
+                if alpha >= beta {
+                    break;
+                }
+            }
+        }
+
+        root_moves[..=best_index].rotate_right(1);
+        // Later lines leave out the best moves, so only the first line's
+        // result is true of the position as a whole.
+        if pv_index == 0 && best > original_alpha && best < beta {
+            self.transposition_table
+                .store(key, depth, 0, Bound::Exact, best, Some(&root_moves[0]));
+        }
+        best
+    }
+
+    /// Searches the position to the given depth. `excluded` is left out of
+    /// the moves searched, which singular extensions use to find out how
+    /// good the position is without the hash move.
+    #[allow(clippy::too_many_arguments)]
+    fn negamax(
+        &mut self,
+        board: &mut Board,
+        depth: usize,
+        ply: usize,
+        mut alpha: i32,
+        beta: i32,
+        pv: &mut Vec<SelectedMove>,
+        excluded: Option<&SelectedMove>,
+    ) -> i32 {
+        self.nodes += 1;
+        self.seldepth = self.seldepth.max(ply);
+        pv.clear();
+        if self.should_stop() {
+            return 0;
+        }
+
+        let key = self.key();
+        if self.is_repetition() {
+            return 0;
+        }
+
+        let in_check = board.is_check();
+        let depth = if in_check && self.features.check_extensions {
+            depth + 1
+        } else {
+            depth
+        };
+
+        if depth == 0 || ply >= MAX_PLY {
+            return self.quiescence(board, ply, alpha, beta);
+        }
+
+        let mut hash_move = None;
+        let mut singular_candidate = None;
+        if excluded.is_none() {
+            if let Some(entry) = self.transposition_table.probe(key, ply) {
+                if entry.depth >= depth {
+                    match entry.bound {
+                        Bound::Exact => return entry.score,
+                        Bound::Lower if entry.score >= beta => return entry.score,
+                        Bound::Upper if entry.score <= alpha => return entry.score,
+                        _ => {}
+                    }
+                }
+
+                if self.features.singular_extensions
+                    && depth >= SINGULAR_MIN_DEPTH
+                    && entry.depth + 3 >= depth
+                    && entry.bound != Bound::Upper
+                    && entry.score.abs() < MATE_THRESHOLD
+                {
+                    singular_candidate = entry.best_move.clone().map(|move_| (move_, entry.score));
+                }
+                hash_move = entry.best_move;
+            }
+        }
+
+        let is_pv = beta - alpha > 1;
+        let player = board.player_to_move();
+        let static_eval = if in_check {
+            -INFINITY
+        } else {
+            self.evaluator.evaluate(board)
+        };
+        let can_prune = !is_pv && !in_check && excluded.is_none() && alpha.abs() < MATE_THRESHOLD;
+
+        if can_prune
+            && self.features.razoring
+            && depth <= RAZORING_MARGINS.len()
+            && static_eval + RAZORING_MARGINS[depth - 1] <= alpha
+        {
+            let score = self.quiescence(board, ply, alpha, beta);
+            if self.stopped {
+                return 0;
+            }
+            if score <= alpha {
+                return score;
+            }
+        }
+
+        if can_prune
+            && self.features.null_move_pruning
+            && depth >= NULL_MOVE_MIN_DEPTH
+            && static_eval >= beta
+            && beta.abs() < MATE_THRESHOLD
+            && has_pieces(board, player)
+            // Two null moves in a row would just search the same position
+            // with less depth.
+            && self.move_stack.last().is_some_and(|previous| previous.is_some())
+            && self.make_null_move(board)
+        {
+            let reduction = NULL_MOVE_REDUCTION + depth / 6;
+            let mut child_pv = Vec::new();
+            let score = -self.negamax(
+                board,
+                depth.saturating_sub(1 + reduction),
+                ply + 1,
+                -beta,
+                -beta + 1,
+                &mut child_pv,
+                None,
+            );
+            self.undo_null_move(board);
+            if self.stopped {
+                return 0;
+            }
+
+            if score >= beta {
+                // Mates found after passing can't be trusted.
+                return if score >= MATE_THRESHOLD { beta } else { score };
+            }
+        }
+
+        let singular_move = match singular_candidate {
+            Some((move_, score)) => {
+                let singular_beta = score - SINGULAR_MARGIN * depth as i32;
+                let mut child_pv = Vec::new();
+                let score = self.negamax(
+                    board,
+                    (depth - 1) / 2,
+                    ply,
+                    singular_beta - 1,
+                    singular_beta,
+                    &mut child_pv,
+                    Some(&move_),
+                );
+                if self.stopped {
+                    return 0;
+                }
+                (score < singular_beta).then_some(move_)
+            }
+            None => None,
+        };
+
+        let futility_pruning = can_prune
+            && self.features.futility_pruning
+            && depth <= FUTILITY_MAX_DEPTH
+            && static_eval + FUTILITY_MARGIN * depth as i32 <= alpha;
+
+        let previous_move = self.move_stack.last().cloned().flatten();
+        let mut picker = MovePicker::new(&self.ordering, hash_move, ply, previous_move.as_ref());
+
+        let original_alpha = alpha;
+        let mut best = -INFINITY;
+        let mut best_move = None;
+        let mut child_pv = Vec::new();
+        let mut quiets_tried = Vec::new();
+        let mut moves_searched = 0;
+        while let Some(move_) = picker.next(board, &self.ordering) {
+            if excluded == Some(&move_) {
+                continue;
+            }
+
+            let is_quiet = !is_tactical(board, &move_);
+            self.make_move(board, &move_);
+            let gives_check = board.is_check();
+            if futility_pruning && is_quiet && !gives_check && moves_searched > 0 {
+                self.undo_move(board);
+                continue;
+            }
+
+            let extension = usize::from(singular_move.as_ref() == Some(&move_));
+            let child_depth = depth - 1 + extension;
+            let reduction = if self.features.late_move_reductions
+                && is_quiet
+                && !in_check
+                && !gives_check
+                && depth >= LMR_MIN_DEPTH
+                && moves_searched >= LMR_MIN_MOVES
+            {
+                let reduction = if moves_searched >= 2 * LMR_MIN_MOVES && depth >= 6 {
+                    2
+                } else {
+                    1
+                };
+                reduction.min(child_depth - 1)
+            } else {
+                0
+            };
+
+            let mut score = 0;
+            let mut full_search = true;
+            if reduction > 0 {
+                score = -self.negamax(
+                    board,
+                    child_depth - reduction,
+                    ply + 1,
+                    -alpha - 1,
+                    -alpha,
+                    &mut child_pv,
+                    None,
+                );
+                full_search = score > alpha;
+            }
+            if full_search && moves_searched > 0 && self.features.principal_variation_search {
+                score = -self.negamax(
+                    board,
+                    child_depth,
+                    ply + 1,
+                    -alpha - 1,
+                    -alpha,
+                    &mut child_pv,
+                    None,
+                );
+                full_search = score > alpha && score < beta;
+            }
+            if full_search {
+                score = -self.negamax(
+                    board,
+                    child_depth,
+                    ply + 1,
+                    -beta,
+                    -alpha,
+                    &mut child_pv,
+                    None,
+                );
+            }
+            self.undo_move(board);
+            if self.stopped {
+                return 0;
+            }
+            moves_searched += 1;
+
+            if is_quiet {
+                quiets_tried.push(move_.clone());
+            }
+
+            if score > best {
+                best = score;
+                best_move = Some(move_.clone());
+            }
+
+            if score > alpha {
+                alpha = score;
+                pv.clear();
+                pv.push(move_.clone());
+                pv.append(&mut child_pv);
+
+                if alpha >= beta {
+                    if is_quiet {
+                        self.ordering.record_cutoff(
+                            player,
+                            &move_,
+                            &quiets_tried,
+                            depth,
+                            ply,
+                            previous_move.as_ref(),
+                        );
+                    }
+                    break;
+                }
+            }
+        }
+
+        if best_move.is_none() {
+            if excluded.is_some() {
+                // Only the excluded move was legal, so it is as singular
+                // as a move can be.
+                return -INFINITY + 1;
+            }
+            if board.is_check() {
+                return -MATE + ply as i32;
+            }
+            return 0;
+        }
+
+        // Without the excluded move, the result says nothing about the
+        // position itself.
+        if excluded.is_none() {
+            let bound = if best >= beta {
+                Bound::Lower
+            } else if best > original_alpha {
+                Bound::Exact
+            } else {
+                Bound::Upper
+            };
+            self.transposition_table
+                .store(key, depth, ply, bound, best, best_move.as_ref());
+        }
+
+        best
+    }
+
+    /// Searches only captures and promotions (or every evasion when in
+    /// check) until the position is quiet, so that the evaluation is never
+    /// taken in the middle of an exchange. The side to move may also
+    /// "stand pat" and decline to capture at all.
+    ///
+    /// Every capture removes a piece and evasions are only searched after
+    /// a capture gave check, so the search always terminates.
+    fn quiescence(&mut self, board: &mut Board, ply: usize, mut alpha: i32, beta: i32) -> i32 {
+        self.nodes += 1;
+        self.seldepth = self.seldepth.max(ply);
+        if self.should_stop() {
+            return 0;
+        }
+
+        if ply >= MAX_PLY {
+            return self.evaluator.evaluate(board);
+        }
+
+        let in_check = board.is_check();
+        let mut best = -INFINITY;
+        let mut stand_pat = -INFINITY;
+        if !in_check {
+            stand_pat = self.evaluator.evaluate(board);
+            if stand_pat >= beta {
+                return stand_pat;
+            }
+            alpha = alpha.max(stand_pat);
+            best = stand_pat;
+        }
+
+        let mut moves = board
+            .possible_moves()
+            .filter(|move_| in_check || is_tactical(board, move_))
+            .map(|move_| {
+                let score = mvv_lva(board, &move_);
+                (move_, score)
+            })
+            .collect::<Vec<_>>();
+        if in_check && moves.is_empty() {
+            return -MATE + ply as i32;
+        }
+        moves.sort_by_key(|(_, score)| -score);
+
+        for (move_, _) in moves {
+            if !in_check {
+                // Delta pruning: even winning the captured piece for free
+                // can't bring the score back up to alpha.
+                let gain = captured_piece(board, &move_)
+                    .map(|kind| ORDERING_VALUES[kind as usize])
+                    .unwrap_or(0);
+                if move_.promotion_kind().is_none() && stand_pat + gain + DELTA_MARGIN < alpha {
+                    continue;
+                }
+
+                if see(board, &move_) < 0 {
+                    continue;
+                }
+            }
+
+            self.make_move(board, &move_);
+            let score = -self.quiescence(board, ply + 1, -beta, -alpha);
+            self.undo_move(board);
+            if self.stopped {
+                return 0;
+            }
+
+            if score > best {
+                best = score;
+            }
+
+            if score > alpha {
+                alpha = score;
+                if alpha >= beta {
+                    break;
+                }
+            }
+        }
+
+        best
+    }
+
+    /// Finds the captures that quiescence search expects both sides to play
+    /// from the position, ending in a quiet position where the static
+    /// evaluation can be trusted. Positions in check are left as they are.
+    pub(crate) fn quiet_line(&mut self, board: &mut Board) -> Vec<SelectedMove> {
+        self.stop_handle.reset();
+        self.stopped = false;
+        self.node_limit = None;
+        self.time_manager = None;
+        self.move_stack.clear();
+        self.keys = vec![PositionKey::new(board)];
+
+        let mut line = Vec::new();
+        while line.len() < MAX_PLY && !board.is_check() {
+            let mut best = self.evaluator.evaluate(board);
+            let mut best_move = None;
+            let moves = board
+                .possible_moves()
+                .filter(|move_| is_tactical(board, move_))
+                .collect::<Vec<_>>();
+            for move_ in moves {
+                self.make_move(board, &move_);
+                let score = -self.quiescence(board, line.len() + 1, -INFINITY, -best);
+                self.undo_move(board);
+                if score > best {
+                    best = score;
+                    best_move = Some(move_);
+                }
+            }
+
+            match best_move {
+                None => break,
+                Some(move_) => {
+                    self.make_move(board, &move_);
+                    line.push(move_);
+                }
+            }
+        }
+
+        for _ in 0..line.len() {
+            self.undo_move(board);
+        }
+        line
+    }
+
+    /// Checks the search limits, remembering whether any of them was hit.
+    fn should_stop(&mut self) -> bool {
+        if self.stopped {
+            return true;
+        }
+
+        let unreported = self.nodes - self.checked_nodes;
+        let searched_nodes = &self.searched_nodes;
+        if self
+            .node_limit
+            .is_some_and(|limit| searched_nodes.load(Ordering::Relaxed) + unreported >= limit)
+        {
+            self.stopped = true;
+        } else if unreported >= CHECK_INTERVAL {
+            self.searched_nodes.fetch_add(unreported, Ordering::Relaxed);
+            self.checked_nodes = self.nodes;
+            self.check_ponder_hit();
+            self.stopped = self.stop_handle.is_stopped()
+                || self
+                    .time_manager
+                    .as_ref()
+                    .is_some_and(|time_manager| time_manager.is_out_of_time());
+            if !self.stopped {
+                self.report_progress();
+            }
+        }
+
+        self.stopped
+    }
+
+    /// Reports every line of the iteration that just finished. Only the
+    /// main thread reports, since it is the one the limits apply to.
+    fn report_iteration(&mut self, board: &Board, lines: &[PvLine]) {
+        if self.info_callback.is_none() || self.thread_index > 0 {
+            return;
+        }
+
+        for (index, line) in lines.iter().enumerate() {
+            let line = InfoLine::new(board, line.score, line.pv.clone());
+            self.report(SearchInfoKind::Iteration, index + 1, &line);
+            if index == 0 {
+                self.best_info_line = Some(line);
+            }
+        }
+        self.last_info = Some(Instant::now());
+    }
+
+    /// Reports the best line so far if it has been long enough since the
+    /// last report. Nothing is reported before the first iteration
+    /// finishes, since there is no line to report yet.
+    fn report_progress(&mut self) {
+        if self.info_callback.is_none() || self.thread_index > 0 {
+            return;
+        }
+        let Some(line) = self.best_info_line.take() else {
+            return;
+        };
+
+        if self
+            .last_info
+            .is_none_or(|last_info| last_info.elapsed() >= self.info_interval)
+        {
+            self.report(SearchInfoKind::Progress, 1, &line);
+            self.last_info = Some(Instant::now());
+        }
+        self.best_info_line = Some(line);
+    }
+
+    fn report(&mut self, kind: SearchInfoKind, multi_pv: usize, line: &InfoLine) {
+        let elapsed = self
+            .time_manager
+            .as_ref()
+            .map(|time_manager| time_manager.elapsed())
+            .unwrap_or_default();
+        let nodes = self.searched_nodes.load(Ordering::Relaxed) + self.nodes - self.checked_nodes;
+        let info = SearchInfo {
+            kind,
+            depth: self.iteration_depth,
+            seldepth: self.seldepth,
+            nodes,
+            nps: (nodes as u128 * 1_000_000 / elapsed.as_micros().max(1)) as u64,
+            hashfull: self.transposition_table.hashfull(),
+            elapsed,
+            multi_pv,
+            score: line.score,
+            pv: line.pv.clone(),
+            pv_san: line.pv_san.clone(),
+            pv_uci: line.pv_uci.clone(),
+        };
+        if let Some(callback) = self.info_callback.as_mut() {
+            callback(&info);
+        }
+    }
+
+    /// Gets whether there is enough time left for another iteration to be
+    /// worth starting.
+    fn can_start_iteration(&self) -> bool {
+        !self.stop_handle.is_stopped()
+            && self
+                .time_manager
+                .as_ref()
+                .is_none_or(|time_manager| time_manager.can_start_iteration())
+    }
+
+    fn make_move(&mut self, board: &mut Board, move_: &SelectedMove) {
+        let piece = board
+            .at(move_.move_().from())
+            .expect("legal moves to start on an occupied square");
+        let from = square_index(move_.move_().from());
+        let to = square_index(move_.move_().to());
+        let irreversible =
+            piece.kind() == PieceKind::Pawn || board.at(move_.move_().to()).is_some();
+        let before = zobrist::piece_bitboards(board);
+        self.move_stack.push(Some(PreviousMove { piece, to }));
+        board
+            .make_move_unchecked(move_.clone())
+            .expect("legal moves to be playable");
+        let key = self.keys.last().expect("the root position to have a key");
+        self.keys
+            .push(key.after_move(&before, board, from, to, irreversible));
+        self.evaluator.on_make_move(board);
+    }
+
+    fn undo_move(&mut self, board: &mut Board) {
+        self.move_stack.pop();
+        self.keys.pop();
+        board.undo().expect("a move to be on the undo stack");
+        self.evaluator.on_undo_move();
+    }
+
+    /// Makes a null move if the board allows one, returning whether it did.
+    fn make_null_move(&mut self, board: &mut Board) -> bool {
+        if board.make_null_move().is_err() {
+            return false;
+        }
+        self.move_stack.push(None);
+        let key = self.keys.last().expect("the root position to have a key");
+        self.keys.push(key.after_null_move());
+        self.evaluator.on_make_move(board);
+        true
+    }
+
+    fn undo_null_move(&mut self, board: &mut Board) {
+        self.move_stack.pop();
+        self.keys.pop();
+        board
+            .undo_null_move()
+            .expect("a null move to be on the undo stack");
+        self.evaluator.on_undo_move();
+    }
+
+    /// Gets the hash of the node currently being searched.
+    fn key(&self) -> u64 {
+        self.keys
+            .last()
+            .expect("the root position to have a key")
+            .key
+    }
+
+    /// Gets whether the position has already occurred earlier in the game
+    /// or the current line. Only positions with the same side to move
+    /// since the last capture or pawn move are checked.
+    fn is_repetition(&self) -> bool {
+        let current = self.keys.last().expect("the root position to have a key");
+        self.keys
+            .iter()
+            .rev()
+            .take(current.reversible_plies + 1)
+            .skip(2)
+            .step_by(2)
+            .any(|previous| previous.key == current.key)
+    }
+
+    /// Table cutoffs can cut the principal variation short, so follow the
+    /// stored best moves to fill it back out to the searched depth.
+    fn extend_pv(&self, board: &mut Board, pv: &mut Vec<SelectedMove>, depth: usize) {
+        for move_ in pv.iter() {
+            board
+                .make_move_unchecked(move_.clone())
+                .expect("principal variation moves to be playable");
+        }
+
+        while pv.len() < depth {
+            let key = zobrist::hash(board);
+            let move_ = match self.transposition_table.probe(key, pv.len()) {
+                Some(entry) => entry.best_move,
+                None => None,
+            };
+
+            match move_ {
+                Some(move_) if board.possible_moves().any(|legal| legal == move_) => {
+                    board
+                        .make_move_unchecked(move_.clone())
+                        .expect("legal moves to be playable");
+                    pv.push(move_);
+                }
+                _ => break,
+            }
+        }
+
+        for _ in 0..pv.len() {
+            board.undo().expect("a move to be on the undo stack");
+        }
+    }
+}
+
+/// Gets whether the player has any pieces other than pawns and their king.
+/// Zugzwang is common without them, which null-move pruning can't detect.
+fn has_pieces(board: &Board, player: Player) -> bool {
+    [
+        PieceKind::Knight,
+        PieceKind::Bishop,
+        PieceKind::Rook,
+        PieceKind::Queen,
+    ]
+    .into_iter()
+    .any(|kind| board.bitboard_for(&Piece::new(player, kind)) != 0)
+}
+
+/// Computes the keys of every position in the game up to and including the
+/// board's current position, oldest first.
+fn game_keys(board: &Board) -> Vec<PositionKey> {
+    // The pawns and the number of pieces are enough to tell whether each
+    // move was a capture or a pawn move.
+    let irreversible_state = |board: &Board| {
+        let pieces: u32 = zobrist::piece_bitboards(board)
+            .iter()
+            .flatten()
+            .map(|bitboard| bitboard.count_ones())
+            .sum();
+        (zobrist::pawn_hash(board), pieces)
+    };
+
+    let mut previous = board.clone();
+    let mut positions = vec![(PositionKey::new(&previous), irreversible_state(&previous))];
+    while previous.undo().is_ok() {
+        positions.push((PositionKey::new(&previous), irreversible_state(&previous)));
+    }
+    positions.reverse();
+
+    let mut keys: Vec<PositionKey> = Vec::with_capacity(positions.len());
+    let mut last_state = None;
+    for (mut key, state) in positions {
+        if last_state == Some(state) {
+            key.reversible_plies = keys.last().map_or(0, |last| last.reversible_plies + 1);
+        }
+        keys.push(key);
+        last_state = Some(state);
+    }
+    keys
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        str::FromStr,
+        sync::mpsc,
+        thread,
+        time::{Duration, Instant},
+    };
+
+    use chess_core::Board;
+
+    use super::CHECK_INTERVAL;
+    use crate::{Limits, Score, SearchFeatures, SearchInfoKind, Searcher, Skill};
+
+    #[test]
+    fn finds_mate_in_one() {
+        let mut board = Board::from_str("6k1/5ppp/8/8/8/8/5PPP/R5K1 w - - 0 1").unwrap();
+        let result = Searcher::new().search(&mut board, Limits::depth(3));
+
+        assert_eq!(Score::Mate(1), result.score);
+        assert_eq!(
+            "a1 -> a8",
+            format!("{:?}", result.best_move.unwrap().move_())
+        );
+        assert_eq!(1, result.pv.len());
+    }
+
+    #[test]
+    fn captures_hanging_queen() {
+        let mut board = Board::from_str("4k3/8/8/3q4/8/8/3R4/4K3 w - - 0 1").unwrap();
+        let result = Searcher::new().search(&mut board, Limits::depth(2));
+
+        assert_eq!(
+            "d2 -> d5",
+            format!("{:?}", result.best_move.unwrap().move_())
+        );
+        assert_eq!(2, result.depth);
+        assert!(matches!(result.score, Score::Centipawns(score) if score > 0));
+    }
+
+    #[test]
+    fn sees_recapture_beyond_horizon() {
+        // Taking on e5 wins a pawn at depth 1, but the d6 pawn recaptures.
+        // White keeps a pawn, since a lone knight can never win.
+        let mut board = Board::from_str("4k3/8/3p4/4p3/8/5N2/7P/4K3 w - - 0 1").unwrap();
+        let result = Searcher::new().search(&mut board, Limits::depth(1));
+
+        assert_ne!(
+            "f3 -> e5",
+            format!("{:?}", result.best_move.unwrap().move_())
+        );
+        // Still a knight and a pawn against two pawns, rather than a pawn
+        // against a pawn.
+        assert!(matches!(result.score, Score::Centipawns(score) if score > 0));
+    }
+
+    #[test]
+    fn leaves_board_unchanged() {
+        let mut board = Board::default();
+        let fen_before = board.to_fen_string();
+        let result = Searcher::new().search(&mut board, Limits::depth(2));
+
+        assert_eq!(fen_before, board.to_fen_string());
+        assert!(result.best_move.is_some());
+        assert!(result.nodes > 20);
+    }
+
+    #[test]
+    fn repeated_search_reuses_transposition_table() {
+        let mut board = Board::from_str("4k3/8/8/3q4/8/8/3R4/4K3 w - - 0 1").unwrap();
+        let mut searcher = Searcher::new();
+        let first = searcher.search(&mut board, Limits::depth(3));
+        let second = searcher.search(&mut board, Limits::depth(3));
+
+        assert_eq!(first.best_move, second.best_move);
+        assert!(second.nodes < first.nodes);
+
+        searcher.new_game();
+        let third = searcher.search(&mut board, Limits::depth(3));
+        assert_eq!(first.nodes, third.nodes);
+    }
+
+    #[test]
+    fn scores_repetition_as_draw() {
+        // Black is material down, but Nf6 repeats a position from earlier in the game.
+        let mut board = Board::from_str("6nk/8/8/8/8/8/8/K2Q4 b - - 0 1").unwrap();
+        for move_ in ["Nf6", "Qd2", "Ng8", "Qd1"] {
+            board.make_move_acn(move_).unwrap();
+        }
+
+        let result = Searcher::new().search(&mut board, Limits::depth(1));
+        assert_eq!(
+            "g8 -> f6",
+            format!("{:?}", result.best_move.unwrap().move_())
+        );
+        assert_eq!(Score::Centipawns(0), result.score);
+    }
+
+    #[test]
+    fn reports_checkmated_root() {
+        let mut board = Board::from_str("R5k1/5ppp/8/8/8/8/5PPP/6K1 b - - 0 1").unwrap();
+        let result = Searcher::new().search(&mut board, Limits::depth(2));
+
+        assert!(result.best_move.is_none());
+        assert_eq!(Score::Mate(0), result.score);
+    }
+
+    #[test]
+    fn node_limited_searches_are_deterministic() {
+        let mut board = Board::default();
+        let first = Searcher::new().search(&mut board, Limits::nodes(3000));
+        let second = Searcher::new().search(&mut board, Limits::nodes(3000));
+
+        assert!(first.best_move.is_some());
+        assert_eq!(first.best_move, second.best_move);
+        assert_eq!(first.pv, second.pv);
+        assert!(first.nodes <= 3000);
+    }
+
+    #[test]
+    fn stop_before_the_search_starts_is_not_lost() {
+        let mut board = Board::default();
+        let mut searcher = Searcher::new();
+        searcher.stop_handle().stop();
+
+        let result = searcher.search(&mut board, Limits::infinite());
+        assert!(result.best_move.is_some());
+        assert!(!searcher.stop_handle().is_stopped());
+
+        let result = searcher.search(&mut board, Limits::depth(3));
+        assert_eq!(3, result.depth);
+    }
+
+    #[test]
+    fn stop_handle_cancels_search() {
+        let mut board = Board::default();
+        let mut searcher = Searcher::new();
+        let stop_handle = searcher.stop_handle();
+        let stopper = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(100));
+            stop_handle.stop();
+        });
+
+        let start = Instant::now();
+        let result = searcher.search(&mut board, Limits::infinite());
+        stopper.join().unwrap();
+
+        assert!(start.elapsed() < Duration::from_secs(10));
+        assert!(result.best_move.is_some());
+    }
+
+    #[test]
+    fn respects_move_time() {
+        let mut board = Board::default();
+        let start = Instant::now();
+        let result =
+            Searcher::new().search(&mut board, Limits::move_time(Duration::from_millis(200)));
+
+        assert!(start.elapsed() < Duration::from_secs(2));
+        assert!(result.best_move.is_some());
+    }
+
+    #[test]
+    fn multi_pv_reports_ranked_lines() {
+        let mut board = Board::default();
+        let mut searcher = Searcher::new();
+        searcher.set_multi_pv(3);
+        let result = searcher.search(&mut board, Limits::depth(3));
+
+        assert_eq!(3, result.lines.len());
+        assert_eq!(result.pv, result.lines[0].pv);
+        assert_eq!(result.score, result.lines[0].score);
+        for pair in result.lines.windows(2) {
+            assert_ne!(pair[0].pv[0], pair[1].pv[0]);
+            assert!(score_value(pair[0].score) >= score_value(pair[1].score));
+        }
+    }
+
+    #[test]
+    fn multi_pv_is_limited_to_legal_moves() {
+        // Taking the queen is the only legal move.
+        let mut board = Board::from_str("k7/8/8/8/8/8/1q6/K7 w - - 0 1").unwrap();
+        let mut searcher = Searcher::new();
+        searcher.set_multi_pv(5);
+        let result = searcher.search(&mut board, Limits::depth(2));
+
+        assert_eq!(1, result.lines.len());
+        assert_eq!("a1 -> b2", format!("{:?}", result.lines[0].pv[0].move_()));
+    }
+
+    #[test]
+    fn search_moves_restrict_the_root() {
+        let mut board = Board::default();
+        let knight_move = board
+            .possible_moves()
+            .find(|move_| move_.to_uci() == "g1h3")
+            .unwrap();
+        let mut searcher = Searcher::new();
+        searcher.set_multi_pv(3);
+        let limits = Limits {
+            depth: Some(3),
+            search_moves: vec![knight_move.clone()],
+            ..Default::default()
+        };
+        let result = searcher.search(&mut board, limits);
+
+        assert_eq!(Some(knight_move), result.best_move);
+        assert_eq!(1, result.lines.len());
+    }
+
+    fn score_value(score: Score) -> i32 {
+        match score {
+            Score::Centipawns(centipawns) => centipawns,
+            Score::Mate(moves) => 100_000 * moves.signum() - moves,
+        }
+    }
+
+    #[test]
+    fn searches_with_several_threads() {
+        let mut board = Board::from_str("4k3/8/8/3q4/8/8/3R4/4K3 w - - 0 1").unwrap();
+        let fen_before = board.to_fen_string();
+        let mut searcher = Searcher::new();
+        searcher.set_threads(4);
+        let result = searcher.search(&mut board, Limits::depth(4));
+
+        assert_eq!(4, searcher.threads());
+        assert_eq!(fen_before, board.to_fen_string());
+        assert_eq!(
+            "d2 -> d5",
+            format!("{:?}", result.best_move.unwrap().move_())
+        );
+        assert!(result.depth >= 4);
+    }
+
+    #[test]
+    fn threads_honour_limits() {
+        let mut board = Board::default();
+        let mut searcher = Searcher::new();
+        searcher.set_threads(3);
+
+        // Threads add their nodes to the shared count in batches, so the
+        // search can go over by the batches the others haven't added yet.
+        let result = searcher.search(&mut board, Limits::nodes(5000));
+        assert!(result.best_move.is_some());
+        assert!(result.nodes <= 5000 + 2 * 2 * CHECK_INTERVAL);
+
+        let stop_handle = searcher.stop_handle();
+        let stopper = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(100));
+            stop_handle.stop();
+        });
+        let start = Instant::now();
+        let result = searcher.search(&mut board, Limits::infinite());
+        stopper.join().unwrap();
+
+        assert!(start.elapsed() < Duration::from_secs(10));
+        assert!(result.best_move.is_some());
+    }
+
+    #[test]
+    fn node_counts_do_not_outlive_helpers() {
+        let mut board = Board::default();
+        let expected = Searcher::new().search(&mut board, Limits::nodes(5000));
+
+        let mut searcher = Searcher::new();
+        searcher.set_threads(2);
+        searcher.search(&mut board, Limits::nodes(20_000));
+        searcher.set_threads(1);
+        searcher.new_game();
+
+        // Left over, the helpers' total from the first search would use up
+        // the limit before the second search got going.
+        let (sender, receiver) = mpsc::channel();
+        searcher.set_info_callback(move |info| sender.send(info.clone()).unwrap());
+        let result = searcher.search(&mut board, Limits::nodes(5000));
+        searcher.clear_info_callback();
+
+        assert_eq!(expected.nodes, result.nodes);
+        assert_eq!(expected.pv, result.pv);
+        let reported = receiver.try_iter().map(|info| info.nodes).max().unwrap();
+        assert!(reported <= 5000);
+    }
+
+    #[test]
+    fn selective_search_visits_fewer_nodes() {
+        let mut board = Board::default();
+        let selective = Searcher::new().search(&mut board, Limits::depth(4));
+        let mut searcher = Searcher::new();
+        searcher.set_features(SearchFeatures::none());
+        let exhaustive = searcher.search(&mut board, Limits::depth(4));
+
+        assert!(selective.nodes < exhaustive.nodes);
+    }
+
+    #[test]
+    fn each_feature_keeps_tactics() {
+        let features = [
+            SearchFeatures {
+                principal_variation_search: true,
+                ..SearchFeatures::none()
+            },
+            SearchFeatures {
+                aspiration_windows: true,
+                ..SearchFeatures::none()
+            },
+            SearchFeatures {
+                null_move_pruning: true,
+                ..SearchFeatures::none()
+            },
+            SearchFeatures {
+                late_move_reductions: true,
+                ..SearchFeatures::none()
+            },
+            SearchFeatures {
+                futility_pruning: true,
+                ..SearchFeatures::none()
+            },
+            SearchFeatures {
+                razoring: true,
+                ..SearchFeatures::none()
+            },
+            SearchFeatures {
+                check_extensions: true,
+                ..SearchFeatures::none()
+            },
+            SearchFeatures {
+                singular_extensions: true,
+                ..SearchFeatures::none()
+            },
+        ];
+
+        for features in features {
+            let mut searcher = Searcher::new();
+            searcher.set_features(features.clone());
+
+            let mut board = Board::from_str("4k3/8/8/3q4/8/8/3R4/4K3 w - - 0 1").unwrap();
+            let result = searcher.search(&mut board, Limits::depth(4));
+            assert_eq!(
+                "d2 -> d5",
+                format!("{:?}", result.best_move.unwrap().move_()),
+                "{features:?}"
+            );
+
+            let mut board = Board::from_str("6k1/5ppp/8/8/8/8/5PPP/R5K1 w - - 0 1").unwrap();
+            let result = searcher.search(&mut board, Limits::depth(3));
+            assert_eq!(Score::Mate(1), result.score, "{features:?}");
+        }
+    }
+
+    #[test]
+    fn seeded_skill_levels_are_reproducible() {
+        let play = |seed| {
+            let mut searcher = Searcher::new();
+            searcher.set_skill(Some(Skill::new(0).with_seed(seed)));
+            let mut board = Board::default();
+            let mut moves = Vec::new();
+            for _ in 0..6 {
+                let result = searcher.search(&mut board, Limits::default());
+                assert!(result.depth <= 1);
+                let move_ = result.best_move.unwrap();
+                board.make_move(move_.clone()).unwrap();
+                moves.push(move_);
+            }
+            moves
+        };
+
+        assert_eq!(play(7), play(7));
+        assert!((0..10).any(|seed| play(seed) != play(7)));
+    }
+
+    #[test]
+    fn weakened_search_still_takes_free_material() {
+        // At middling levels, the random bonus can't make up for leaving a
+        // queen on the board.
+        let mut board = Board::from_str("4k3/8/8/3q4/8/8/3R4/4K3 w - - 0 1").unwrap();
+        let mut searcher = Searcher::new();
+        searcher.set_skill(Some(Skill::new(10).with_seed(3)));
+        let result = searcher.search(&mut board, Limits::default());
+
+        assert_eq!(
+            "d2 -> d5",
+            format!("{:?}", result.best_move.unwrap().move_())
+        );
+    }
+
+    #[test]
+    fn mate_limit_caps_depth() {
+        let mut board = Board::from_str("6k1/5ppp/8/8/8/8/5PPP/R5K1 w - - 0 1").unwrap();
+        let result = Searcher::new().search(&mut board, Limits::mate(1));
+
+        assert_eq!(Score::Mate(1), result.score);
+        assert_eq!(1, result.depth);
+    }
+
+    #[test]
+    fn reports_each_iteration() {
+        let mut board = Board::from_str("6k1/5ppp/8/8/8/8/5PPP/R5K1 w - - 0 1").unwrap();
+        let mut searcher = Searcher::new();
+        searcher.set_multi_pv(2);
+        let (sender, receiver) = mpsc::channel();
+        searcher.set_info_callback(move |info| sender.send(info.clone()).unwrap());
+        let result = searcher.search(&mut board, Limits::depth(3));
+        searcher.clear_info_callback();
+
+        let infos = receiver.try_iter().collect::<Vec<_>>();
+        let iterations = infos
+            .iter()
+            .filter(|info| info.kind == SearchInfoKind::Iteration)
+            .collect::<Vec<_>>();
+        // Finding mate at depth 1 ends the search, with one report per line.
+        assert_eq!(2, iterations.len());
+        assert_eq!([1, 2], [iterations[0].multi_pv, iterations[1].multi_pv]);
+        let best = iterations[0];
+        assert_eq!(result.depth, best.depth);
+        assert_eq!(result.score, best.score);
+        assert_eq!(result.pv, best.pv);
+        assert_eq!(vec!["Ra8#"], best.pv_san);
+        assert_eq!(vec!["a1a8"], best.pv_uci);
+        assert!(best.seldepth >= best.depth);
+        assert!(best.nodes > 0 && best.nodes <= result.nodes);
+    }
+
+    #[test]
+    fn reports_progress_during_iterations() {
+        let mut board = Board::default();
+        let mut searcher = Searcher::new();
+        let (sender, receiver) = mpsc::channel();
+        searcher.set_info_callback(move |info| sender.send(info.clone()).unwrap());
+        searcher.set_info_interval(Duration::ZERO);
+        searcher.search(&mut board, Limits::nodes(20_000));
+
+        let infos = receiver.try_iter().collect::<Vec<_>>();
+        assert!(infos
+            .iter()
+            .any(|info| info.kind == SearchInfoKind::Progress));
+        for pair in infos.windows(2) {
+            assert!(pair[0].depth <= pair[1].depth);
+            assert!(pair[0].nodes <= pair[1].nodes);
+        }
+        for info in infos.iter() {
+            assert_eq!(info.pv.len(), info.pv_san.len());
+            assert_eq!(info.pv.len(), info.pv_uci.len());
+            assert!(!info.pv.is_empty());
+        }
+    }
+
+    #[test]
+    fn ponder_hit_starts_the_clock() {
+        let mut board = Board::default();
+        let mut searcher = Searcher::new();
+        let expected = searcher.search(&mut board, Limits::depth(3));
+        board.make_move(expected.pv[0].clone()).unwrap();
+        board
+            .make_move(expected.ponder_move().unwrap().clone())
+            .unwrap();
+
+        let ponder_handle = searcher.ponder_handle();
+        let hitter = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(300));
+            ponder_handle.ponder_hit();
+        });
+        let start = Instant::now();
+        let limits = Limits {
+            move_time: Some(Duration::from_millis(100)),
+            ponder: true,
+            ..Default::default()
+        };
+        let result = searcher.search(&mut board, limits);
+        hitter.join().unwrap();
+
+        assert!(start.elapsed() >= Duration::from_millis(350));
+        assert!(start.elapsed() < Duration::from_secs(5));
+        assert!(result.best_move.is_some());
+    }
+
+    #[test]
+    fn pondering_waits_to_be_stopped() {
+        // Mate is found straight away, but the result isn't wanted until
+        // the opponent has moved.
+        let mut board = Board::from_str("6k1/5ppp/8/8/8/8/5PPP/R5K1 w - - 0 1").unwrap();
+        let mut searcher = Searcher::new();
+        let stop_handle = searcher.stop_handle();
+        let stopper = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(200));
+            stop_handle.stop();
+        });
+        let start = Instant::now();
+        let limits = Limits {
+            ponder: true,
+            ..Limits::depth(2)
+        };
+        let result = searcher.search(&mut board, limits);
+        stopper.join().unwrap();
+
+        assert!(start.elapsed() >= Duration::from_millis(200));
+        assert_eq!(Score::Mate(1), result.score);
+    }
+}
@@ -0,0 +1,146 @@
+use chess_common::{Piece, PieceKind, Player};
+use chess_core::{Board, SelectedMove};
+
+use super::{
+    move_ordering::{captured_piece, ORDERING_VALUES},
+    transposition_table::square_index,
+};
+use crate::{
+    attacks::{attackers_to, occupied, occupied_by},
+    zobrist::PIECE_KINDS,
+};
+
+/// Static exchange evaluation. Plays out every capture on the move's
+/// destination square, least valuable attacker first, and returns the
+/// material the side to move can expect to win (or lose, if negative).
+/// Either side may stop capturing whenever continuing would lose material.
+/// Pins are not taken into account.
+pub(crate) fn see(board: &Board, move_: &SelectedMove) -> i32 {
+    let from = square_index(move_.move_().from());
+    let to = square_index(move_.move_().to());
+    let mover = match board.at(move_.move_().from()) {
+        None => return 0,
+        Some(piece) => piece,
+    };
+
+    let mut occupied = occupied(board) ^ (1 << from);
+    let captured = captured_piece(board, move_);
+    let mut gains = [0; 32];
+    gains[0] = captured
+        .map(|kind| ORDERING_VALUES[kind as usize])
+        .unwrap_or(0);
+
+    // The piece that will be captured next is the one that just moved
+    // onto the square, which may have promoted on the way.
+    let mut piece_on_square = mover.kind();
+    if let Some(promotion_kind) = move_.promotion_kind() {
+        gains[0] +=
+            ORDERING_VALUES[promotion_kind as usize] - ORDERING_VALUES[PieceKind::Pawn as usize];
+        piece_on_square = promotion_kind;
+    }
+
+    if captured.is_some() && board.at(move_.move_().to()).is_none() {
+        // En-passant removes a pawn that isn't on the destination square.
+        let captured_square = match mover.player() {
+            Player::White => to - 8,
+            Player::Black => to + 8,
+        };
+        occupied &= !(1 << captured_square);
+    }
+
+    let mut player = mover.player().other_player();
+    let mut depth = 0;
+    loop {
+        let attackers = attackers_to(board, to, occupied);
+        let (attacker_square, attacker_kind) =
+            match least_valuable_attacker(board, attackers, player) {
+                None => break,
+                Some(attacker) => attacker,
+            };
+
+        // A king can't capture onto a square that is still defended.
+        if attacker_kind == PieceKind::King {
+            let defenders = attackers_to(board, to, occupied ^ (1 << attacker_square))
+                & occupied_by(board, player.other_player());
+            if defenders != 0 {
+                break;
+            }
+        }
+
+        depth += 1;
+        if depth >= gains.len() {
+            break;
+        }
+        gains[depth] = ORDERING_VALUES[piece_on_square as usize] - gains[depth - 1];
+
+        // Neither side can do better than stopping here, so the result
+        // won't change by looking further.
+        if (-gains[depth - 1]).max(gains[depth]) < 0 {
+            break;
+        }
+
+        occupied ^= 1 << attacker_square;
+        piece_on_square = attacker_kind;
+        player = player.other_player();
+    }
+
+    while depth > 0 {
+        gains[depth - 1] = -(-gains[depth - 1]).max(gains[depth]);
+        depth -= 1;
+    }
+
+    gains[0]
+}
+
+fn least_valuable_attacker(
+    board: &Board,
+    attackers: u64,
+    player: Player,
+) -> Option<(usize, PieceKind)> {
+    for kind in PIECE_KINDS {
+        let candidates = attackers & board.bitboard_for(&Piece::new(player, kind));
+        if candidates != 0 {
+            return Some((candidates.trailing_zeros() as usize, kind));
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use chess_core::{Board, SelectedMove};
+
+    use super::see;
+
+    fn find_move(board: &Board, description: &str) -> SelectedMove {
+        board
+            .possible_moves()
+            .find(|move_| format!("{:?}", move_.move_()) == description)
+            .unwrap()
+    }
+
+    #[test]
+    fn evaluates_exchanges() {
+        // Rook takes an undefended pawn.
+        let board = Board::from_str("1k1r4/1pp4p/p7/4p3/8/P5P1/1PP4P/2K1R3 w - - 0 1").unwrap();
+        assert_eq!(100, see(&board, &find_move(&board, "e1 -> e5")));
+
+        // Knight takes a pawn defended by a pawn, losing the knight for a pawn.
+        let board = Board::from_str("4k3/8/3p4/4p3/8/5N2/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(100 - 320, see(&board, &find_move(&board, "f3 -> e5")));
+
+        // Queen takes a rook defended by a bishop.
+        let board = Board::from_str("4k3/8/2b5/8/4r3/8/8/4QK2 w - - 0 1").unwrap();
+        assert_eq!(500 - 900, see(&board, &find_move(&board, "e1 -> e4")));
+    }
+
+    #[test]
+    fn counts_x_ray_attackers() {
+        // The rook behind the queen recaptures after the queen is taken,
+        // but the exchange still loses the queen for a rook and a pawn.
+        let board = Board::from_str("4k3/8/4r3/4p3/8/8/4Q3/4RK2 w - - 0 1").unwrap();
+        assert_eq!(100 + 500 - 900, see(&board, &find_move(&board, "e2 -> e5")));
+    }
+}
@@ -0,0 +1,146 @@
+use crate::random::Rng;
+
+use super::{Limits, PvLine};
+
+/// The highest skill level, which plays at full strength.
+pub const MAX_SKILL_LEVEL: u8 = 20;
+
+/// The Elo ratings that [`Skill::from_elo`] maps to the lowest and highest
+/// skill levels. They are rough estimates rather than measured ratings.
+const MIN_ELO: u32 = 800;
+const MAX_ELO: u32 = 2800;
+
+/// How many candidate moves a weakened search chooses between.
+pub(crate) const CANDIDATE_LINES: usize = 4;
+
+/// The largest spread of candidate scores, in centipawns, that the random
+/// part of move selection scales with.
+const MAX_SPREAD: i32 = 100;
+
+/// A playing strength below full strength. Lower levels search less deeply
+/// and fewer nodes, and sometimes choose a worse move than the best one,
+/// with moves that lose less being chosen more often.
+///
+/// All randomness comes from the seed, so a searcher with the same skill
+/// and seed plays the same moves given the same searches.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Skill {
+    level: u8,
+    seed: u64,
+    time_variation: bool,
+}
+
+impl Skill {
+    /// Creates a skill level from 0 (weakest) to [`MAX_SKILL_LEVEL`]
+    /// (full strength). Higher levels are clamped to full strength.
+    pub fn new(level: u8) -> Self {
+        Self {
+            level: level.min(MAX_SKILL_LEVEL),
+            seed: 0,
+            time_variation: false,
+        }
+    }
+
+    /// Creates the skill level that plays at roughly the given Elo rating.
+    pub fn from_elo(elo: u32) -> Self {
+        let elo = elo.clamp(MIN_ELO, MAX_ELO);
+        let level = (elo - MIN_ELO) * MAX_SKILL_LEVEL as u32 / (MAX_ELO - MIN_ELO);
+        Self::new(level as u8)
+    }
+
+    /// Sets the seed for the random choices.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Sets whether to randomly think for less than the clock allows, so
+    /// that moves don't all take the same time.
+    pub fn with_time_variation(mut self, time_variation: bool) -> Self {
+        self.time_variation = time_variation;
+        self
+    }
+
+    pub fn level(&self) -> u8 {
+        self.level
+    }
+
+    /// The approximate Elo rating of this skill level.
+    pub fn elo(&self) -> u32 {
+        MIN_ELO + self.level as u32 * (MAX_ELO - MIN_ELO) / MAX_SKILL_LEVEL as u32
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn is_full_strength(&self) -> bool {
+        self.level == MAX_SKILL_LEVEL
+    }
+
+    /// Tightens the limits to this skill level's depth and node caps, and
+    /// varies the thinking time if enabled.
+    pub(crate) fn limit(&self, mut limits: Limits, rng: &mut Rng) -> Limits {
+        let depth = 1 + self.level as usize / 2;
+        let nodes = 200 << (self.level / 2);
+        limits.depth = Some(limits.depth.map_or(depth, |limit| limit.min(depth)));
+        limits.nodes = Some(limits.nodes.map_or(nodes, |limit| limit.min(nodes)));
+
+        if self.time_variation {
+            // Only ever think for less time, so the clock can't run out.
+            let percent = rng.range(50, 100) as u32;
+            for time in [
+                &mut limits.move_time,
+                &mut limits.white_time,
+                &mut limits.black_time,
+            ]
+            .into_iter()
+            .flatten()
+            {
+                *time = *time * percent / 100;
+            }
+        }
+
+        limits
+    }
+
+    /// Chooses one of the candidate lines, which are ranked best first.
+    /// Every line's score is given a random bonus, which is larger at lower
+    /// levels and for lines further behind the best, and the line with the
+    /// highest total is chosen.
+    pub(crate) fn choose(&self, lines: &[PvLine], rng: &mut Rng) -> usize {
+        let scores = lines
+            .iter()
+            .map(|line| line.score.to_internal())
+            .collect::<Vec<_>>();
+        let best = scores[0];
+        let spread = (best - scores[scores.len() - 1]).min(MAX_SPREAD);
+        let weakness = 120 - 2 * self.level as i32;
+
+        let mut chosen = 0;
+        let mut chosen_total = i32::MIN;
+        for (i, score) in scores.into_iter().enumerate() {
+            let bonus = (weakness * (best - score)
+                + spread * rng.range(0, weakness as i64 - 1) as i32)
+                / 128;
+            if score + bonus > chosen_total {
+                chosen = i;
+                chosen_total = score + bonus;
+            }
+        }
+        chosen
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Skill, MAX_SKILL_LEVEL};
+
+    #[test]
+    fn maps_elo_to_levels() {
+        assert_eq!(0, Skill::from_elo(100).level());
+        assert_eq!(10, Skill::from_elo(1800).level());
+        assert_eq!(MAX_SKILL_LEVEL, Skill::from_elo(3500).level());
+        assert_eq!(1800, Skill::new(10).elo());
+    }
+}
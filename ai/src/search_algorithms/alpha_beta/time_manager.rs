@@ -0,0 +1,176 @@
+use std::time::{Duration, Instant};
+
+use chess_common::Player;
+
+use super::Limits;
+
+/// Time held back from every move to cover the delay between the engine
+/// choosing a move and the move reaching the clock.
+const MOVE_OVERHEAD: Duration = Duration::from_millis(10);
+
+/// How many more moves to budget for when the time control doesn't say.
+const DEFAULT_MOVES_TO_GO: u32 = 30;
+
+/// Decides how long a search may run for.
+///
+/// A search has two deadlines. Once the soft deadline has passed, no new
+/// iteration is started, since it is unlikely to finish in time. The hard
+/// deadline stops the search immediately, even in the middle of an
+/// iteration.
+///
+/// While pondering, neither deadline applies. They are measured from the
+/// ponder hit instead of from the start of the search.
+#[derive(Clone, Debug)]
+pub(crate) struct TimeManager {
+    start: Instant,
+    /// When the deadlines started counting down, or None while pondering.
+    clock_start: Option<Instant>,
+    soft_limit: Option<Duration>,
+    hard_limit: Option<Duration>,
+}
+
+impl TimeManager {
+    pub(crate) fn new(limits: &Limits, player: Player) -> Self {
+        let (soft_limit, hard_limit) = Self::deadlines(limits, player);
+        let start = Instant::now();
+        Self {
+            start,
+            clock_start: (!limits.ponder).then_some(start),
+            soft_limit,
+            hard_limit,
+        }
+    }
+
+    /// Works out the soft and hard deadlines from the limits.
+    fn deadlines(limits: &Limits, player: Player) -> (Option<Duration>, Option<Duration>) {
+        if limits.infinite {
+            return (None, None);
+        }
+
+        if let Some(move_time) = limits.move_time {
+            let budget = move_time
+                .saturating_sub(MOVE_OVERHEAD)
+                .max(Duration::from_millis(1));
+            return (Some(budget), Some(budget));
+        }
+
+        let (time_left, increment) = match player {
+            Player::White => (limits.white_time, limits.white_increment),
+            Player::Black => (limits.black_time, limits.black_increment),
+        };
+
+        match time_left {
+            None => (None, None),
+            Some(time_left) => {
+                let increment = increment.unwrap_or_default();
+                let moves_to_go = limits.moves_to_go.unwrap_or(DEFAULT_MOVES_TO_GO).max(1);
+                let usable = time_left.saturating_sub(MOVE_OVERHEAD);
+
+                let soft = (usable / moves_to_go + increment * 3 / 4).min(usable / 2);
+                // Never use more than a fifth of the clock on a single move,
+                // unless the time control ends on this move.
+                let hard = if moves_to_go == 1 {
+                    usable
+                } else {
+                    (soft * 4).min(usable / 5).max(soft)
+                };
+
+                (
+                    Some(soft.max(Duration::from_millis(1))),
+                    Some(hard.max(Duration::from_millis(1))),
+                )
+            }
+        }
+    }
+
+    /// Gets how long the search has been running, including any time
+    /// spent pondering.
+    pub(crate) fn elapsed(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    pub(crate) fn is_pondering(&self) -> bool {
+        self.clock_start.is_none()
+    }
+
+    /// Starts the deadlines counting down from now.
+    pub(crate) fn ponder_hit(&mut self) {
+        self.clock_start.get_or_insert_with(Instant::now);
+    }
+
+    /// Gets whether there is time left to start another iteration.
+    pub(crate) fn can_start_iteration(&self) -> bool {
+        match (self.clock_start, self.soft_limit) {
+            (Some(clock_start), Some(limit)) => clock_start.elapsed() < limit,
+            _ => true,
+        }
+    }
+
+    /// Gets whether the search must stop right away.
+    pub(crate) fn is_out_of_time(&self) -> bool {
+        match (self.clock_start, self.hard_limit) {
+            (Some(clock_start), Some(limit)) => clock_start.elapsed() >= limit,
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use chess_common::Player;
+
+    use super::TimeManager;
+    use crate::Limits;
+
+    #[test]
+    fn budgets_a_share_of_the_clock() {
+        let limits = Limits {
+            white_time: Some(Duration::from_secs(60)),
+            black_time: Some(Duration::from_secs(1)),
+            white_increment: Some(Duration::from_secs(1)),
+            ..Default::default()
+        };
+
+        let white = TimeManager::new(&limits, Player::White);
+        let soft = white.soft_limit.unwrap();
+        assert!(soft > Duration::from_secs(2) && soft < Duration::from_secs(4));
+        assert!(white.hard_limit.unwrap() <= Duration::from_secs(12));
+
+        let black = TimeManager::new(&limits, Player::Black);
+        assert!(black.hard_limit.unwrap() <= Duration::from_millis(200));
+    }
+
+    #[test]
+    fn infinite_search_has_no_deadline() {
+        let limits = Limits {
+            white_time: Some(Duration::from_millis(1)),
+            infinite: true,
+            ..Default::default()
+        };
+        let manager = TimeManager::new(&limits, Player::White);
+        assert!(manager.can_start_iteration());
+        assert!(!manager.is_out_of_time());
+    }
+
+    #[test]
+    fn pondering_has_no_deadline_until_the_ponder_hit() {
+        let limits = Limits {
+            move_time: Some(Duration::from_millis(20)),
+            ponder: true,
+            ..Default::default()
+        };
+        let mut manager = TimeManager::new(&limits, Player::White);
+        std::thread::sleep(Duration::from_millis(30));
+        assert!(manager.is_pondering());
+        assert!(manager.can_start_iteration());
+        assert!(!manager.is_out_of_time());
+
+        manager.ponder_hit();
+        assert!(!manager.is_pondering());
+        assert!(!manager.is_out_of_time());
+        std::thread::sleep(Duration::from_millis(30));
+        assert!(manager.is_out_of_time());
+    }
+}
@@ -0,0 +1,349 @@
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+
+use chess_common::{Location, PieceKind};
+use chess_core::{Move, SelectedMove};
+
+use super::score::MATE_THRESHOLD;
+
+const ENTRIES_PER_BUCKET: usize = 4;
+const BYTES_PER_BUCKET: usize = ENTRIES_PER_BUCKET * 2 * std::mem::size_of::<u64>();
+
+/// How a stored score relates to the true score of the position.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum Bound {
+    /// The score is exact.
+    Exact,
+    /// The true score is at least the stored score (a beta cutoff occurred).
+    Lower,
+    /// The true score is at most the stored score (no move raised alpha).
+    Upper,
+}
+
+/// An entry read back out of the transposition table.
+#[derive(Clone, Debug)]
+pub(crate) struct TtEntry {
+    pub(crate) depth: usize,
+    pub(crate) bound: Bound,
+    /// The score, already adjusted back to the ply it was probed from.
+    pub(crate) score: i32,
+    pub(crate) best_move: Option<SelectedMove>,
+}
+
+/// A fixed-size hash table of previously searched positions keyed by
+/// Zobrist hash.
+///
+/// Each entry is stored as two atomics, the key XORed with the data and
+/// the data itself. A torn write from another thread then simply fails
+/// the key check instead of producing a corrupt entry, which lets the
+/// table be shared across search threads without locking.
+pub struct TranspositionTable {
+    entries: Vec<[AtomicU64; 2]>,
+    bucket_count: usize,
+    generation: AtomicU8,
+}
+
+impl TranspositionTable {
+    /// Creates a table that uses roughly `size_mb` megabytes of memory.
+    pub fn new(size_mb: usize) -> Self {
+        let bucket_count = (size_mb.max(1) * 1024 * 1024 / BYTES_PER_BUCKET).max(1);
+        let entries = (0..bucket_count * ENTRIES_PER_BUCKET)
+            .map(|_| [AtomicU64::new(0), AtomicU64::new(0)])
+            .collect();
+
+        Self {
+            entries,
+            bucket_count,
+            generation: AtomicU8::new(0),
+        }
+    }
+
+    /// Removes every entry from the table. This should be called between
+    /// games so that results from an unrelated game can't leak into the next.
+    pub fn clear(&self) {
+        for entry in self.entries.iter() {
+            entry[0].store(0, Ordering::Relaxed);
+            entry[1].store(0, Ordering::Relaxed);
+        }
+        self.generation.store(0, Ordering::Relaxed);
+    }
+
+    /// Marks the start of a new search so that entries from older
+    /// searches are replaced first.
+    pub(crate) fn new_search(&self) {
+        let generation = self.generation.load(Ordering::Relaxed);
+        self.generation.store(
+            generation.wrapping_add(1) & AGE_MASK as u8,
+            Ordering::Relaxed,
+        );
+    }
+
+    pub(crate) fn probe(&self, key: u64, ply: usize) -> Option<TtEntry> {
+        let bucket = self.bucket(key);
+        for entry in &self.entries[bucket..bucket + ENTRIES_PER_BUCKET] {
+            let data = entry[1].load(Ordering::Relaxed);
+            if data == 0 || entry[0].load(Ordering::Relaxed) ^ data != key {
+                continue;
+            }
+
+            let packed = PackedEntry(data);
+            return Some(TtEntry {
+                depth: packed.depth(),
+                bound: packed.bound(),
+                score: score_from_tt(packed.score(), ply),
+                best_move: packed.best_move(),
+            });
+        }
+
+        None
+    }
+
+    pub(crate) fn store(
+        &self,
+        key: u64,
+        depth: usize,
+        ply: usize,
+        bound: Bound,
+        score: i32,
+        best_move: Option<&SelectedMove>,
+    ) {
+        let generation = self.generation.load(Ordering::Relaxed);
+        let bucket = self.bucket(key);
+
+        // Prefer the slot that already holds this position. Otherwise
+        // replace the slot with the least valuable entry, where entries
+        // from older searches are worth less than shallow ones.
+        let mut replace = bucket;
+        let mut replace_value = i32::MAX;
+        for i in bucket..bucket + ENTRIES_PER_BUCKET {
+            let data = self.entries[i][1].load(Ordering::Relaxed);
+            if data == 0 {
+                if replace_value > i32::MIN {
+                    replace = i;
+                    replace_value = i32::MIN;
+                }
+                continue;
+            }
+
+            let existing = PackedEntry(data);
+            if self.entries[i][0].load(Ordering::Relaxed) ^ data == key {
+                // Don't let a shallow non-exact result overwrite a deeper one,
+                // but keep the existing move if the new result doesn't have one.
+                if bound != Bound::Exact && depth + 2 < existing.depth() {
+                    return;
+                }
+                replace = i;
+                break;
+            }
+
+            let age = generation.wrapping_sub(existing.generation()) & AGE_MASK as u8;
+            let value = existing.depth() as i32 - 8 * age as i32;
+            if value < replace_value {
+                replace = i;
+                replace_value = value;
+            }
+        }
+
+        let mut best_move = best_move.map(encode_move).unwrap_or(0);
+        if best_move == 0 {
+            let data = self.entries[replace][1].load(Ordering::Relaxed);
+            if self.entries[replace][0].load(Ordering::Relaxed) ^ data == key {
+                best_move = PackedEntry(data).move_bits();
+            }
+        }
+
+        let data = PackedEntry::new(best_move, score_to_tt(score, ply), depth, bound, generation).0;
+        self.entries[replace][0].store(key ^ data, Ordering::Relaxed);
+        self.entries[replace][1].store(data, Ordering::Relaxed);
+    }
+
+    /// Gets how full the table is in permill, estimated from the first
+    /// thousand entries. Only entries written during the current search
+    /// are counted.
+    pub fn hashfull(&self) -> usize {
+        let generation = self.generation.load(Ordering::Relaxed);
+        let sample = self.entries.len().min(1000);
+        let used = self.entries[..sample]
+            .iter()
+            .filter(|entry| {
+                let data = entry[1].load(Ordering::Relaxed);
+                data != 0 && PackedEntry(data).generation() == generation
+            })
+            .count();
+        used * 1000 / sample
+    }
+
+    fn bucket(&self, key: u64) -> usize {
+        // Map the key onto the buckets using the high bits of a 128 bit
+        // multiply, which avoids needing a power of two bucket count.
+        let bucket = ((key as u128 * self.bucket_count as u128) >> 64) as usize;
+        bucket * ENTRIES_PER_BUCKET
+    }
+}
+
+const MOVE_BITS: u32 = 16;
+const SCORE_SHIFT: u32 = MOVE_BITS;
+const DEPTH_SHIFT: u32 = SCORE_SHIFT + 16;
+const BOUND_SHIFT: u32 = DEPTH_SHIFT + 8;
+const AGE_SHIFT: u32 = BOUND_SHIFT + 2;
+const AGE_MASK: u64 = 0x3F;
+/// Set on every stored entry so that no stored entry is ever all zeroes.
+const OCCUPIED_BIT: u64 = 1 << 63;
+
+/// The data half of an entry, laid out as
+/// `| occupied:1 | unused:15 | age:6 | bound:2 | depth:8 | score:16 | move:16 |`.
+struct PackedEntry(u64);
+
+impl PackedEntry {
+    fn new(move_bits: u16, score: i32, depth: usize, bound: Bound, generation: u8) -> Self {
+        let bound_bits = match bound {
+            Bound::Exact => 0,
+            Bound::Lower => 1,
+            Bound::Upper => 2,
+        };
+
+        Self(
+            move_bits as u64
+                | ((score as i16 as u16 as u64) << SCORE_SHIFT)
+                | ((depth.min(u8::MAX as usize) as u64) << DEPTH_SHIFT)
+                | (bound_bits << BOUND_SHIFT)
+                | ((generation as u64 & AGE_MASK) << AGE_SHIFT)
+                | OCCUPIED_BIT,
+        )
+    }
+
+    fn move_bits(&self) -> u16 {
+        self.0 as u16
+    }
+
+    fn best_move(&self) -> Option<SelectedMove> {
+        decode_move(self.move_bits())
+    }
+
+    fn score(&self) -> i32 {
+        (self.0 >> SCORE_SHIFT) as u16 as i16 as i32
+    }
+
+    fn depth(&self) -> usize {
+        ((self.0 >> DEPTH_SHIFT) & 0xFF) as usize
+    }
+
+    fn bound(&self) -> Bound {
+        match (self.0 >> BOUND_SHIFT) & 0x3 {
+            0 => Bound::Exact,
+            1 => Bound::Lower,
+            _ => Bound::Upper,
+        }
+    }
+
+    fn generation(&self) -> u8 {
+        ((self.0 >> AGE_SHIFT) & AGE_MASK) as u8
+    }
+}
+
+/// Mate scores are stored relative to the node they were found at rather
+/// than the root, so that the entry is valid wherever it is probed from.
+fn score_to_tt(score: i32, ply: usize) -> i32 {
+    if score >= MATE_THRESHOLD {
+        score + ply as i32
+    } else if score <= -MATE_THRESHOLD {
+        score - ply as i32
+    } else {
+        score
+    }
+}
+
+fn score_from_tt(score: i32, ply: usize) -> i32 {
+    if score >= MATE_THRESHOLD {
+        score - ply as i32
+    } else if score <= -MATE_THRESHOLD {
+        score + ply as i32
+    } else {
+        score
+    }
+}
+
+/// Packs a move into 16 bits as `| promotion:4 | to:6 | from:6 |`.
+/// Zero is reserved for "no move", which can't collide with a real move
+/// because a move never starts and ends on a1.
+pub(crate) fn encode_move(move_: &SelectedMove) -> u16 {
+    let from = square_index(move_.move_().from());
+    let to = square_index(move_.move_().to());
+    let promotion = match move_.promotion_kind() {
+        None => 0,
+        Some(PieceKind::Knight) => 1,
+        Some(PieceKind::Bishop) => 2,
+        Some(PieceKind::Rook) => 3,
+        Some(_) => 4,
+    };
+
+    (from | (to << 6) | (promotion << 12)) as u16
+}
+
+pub(crate) fn decode_move(bits: u16) -> Option<SelectedMove> {
+    if bits == 0 {
+        return None;
+    }
+
+    let from = Location::try_from(1_u64 << (bits & 0x3F)).ok()?;
+    let to = Location::try_from(1_u64 << ((bits >> 6) & 0x3F)).ok()?;
+    let move_ = Move::new(from, to);
+    let promotion_kind = match bits >> 12 {
+        0 => return Some(SelectedMove::Normal { move_ }),
+        1 => PieceKind::Knight,
+        2 => PieceKind::Bishop,
+        3 => PieceKind::Rook,
+        _ => PieceKind::Queen,
+    };
+
+    Some(SelectedMove::Promotion {
+        move_,
+        promotion_kind,
+    })
+}
+
+pub(crate) fn square_index(location: &Location) -> usize {
+    location.rank().as_index() * 8 + location.file().as_index()
+}
+
+#[cfg(test)]
+mod tests {
+    use chess_core::Board;
+
+    use super::{decode_move, encode_move, Bound, TranspositionTable};
+    use crate::search_algorithms::alpha_beta::score::MATE;
+
+    #[test]
+    fn moves_round_trip() {
+        let board = Board::default();
+        for move_ in board.possible_moves() {
+            let decoded = decode_move(encode_move(&move_)).unwrap();
+            assert_eq!(move_, decoded);
+        }
+    }
+
+    #[test]
+    fn stores_and_probes_entries() {
+        let table = TranspositionTable::new(1);
+        let move_ = Board::default().possible_moves().next().unwrap();
+        table.store(0xDEAD_BEEF, 5, 0, Bound::Lower, -123, Some(&move_));
+
+        let entry = table.probe(0xDEAD_BEEF, 0).unwrap();
+        assert_eq!(5, entry.depth);
+        assert_eq!(Bound::Lower, entry.bound);
+        assert_eq!(-123, entry.score);
+        assert_eq!(Some(move_), entry.best_move);
+        assert!(table.probe(0xBEEF_DEAD, 0).is_none());
+
+        table.clear();
+        assert!(table.probe(0xDEAD_BEEF, 0).is_none());
+    }
+
+    #[test]
+    fn mate_scores_are_relative_to_the_probing_ply() {
+        let table = TranspositionTable::new(1);
+        // A mate found 5 plies from the root, 3 plies below this node.
+        table.store(42, 3, 2, Bound::Exact, MATE - 5, None);
+        // Reached again 4 plies from the root, the mate is now 7 plies away.
+        assert_eq!(MATE - 7, table.probe(42, 4).unwrap().score);
+    }
+}
@@ -1,10 +1,13 @@
+mod alpha_beta;
 mod iterative_deepening;
 
 use chess_parsers::PieceMove;
+pub use alpha_beta::{Limits, PonderHandle, PvLine, Score, SearchFeatures, SearchInfo, SearchInfoKind, SearchResult, Searcher, Skill, StopHandle, TranspositionTable, MAX_SKILL_LEVEL};
 pub use iterative_deepening::IterativeDeepeningMovesIterator;
+pub(crate) use alpha_beta::{square_index, MATE_THRESHOLD, ORDERING_VALUES};
 use streaming_iterator::StreamingIterator;
 
 pub trait SearchAlgorithm: StreamingIterator {
     fn current_move_stack(&self) -> Vec<PieceMove>;
     fn current_depth(&self) -> usize;
-}
\ No newline at end of file
+}
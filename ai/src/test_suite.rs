@@ -0,0 +1,197 @@
+use std::{
+    error::Error,
+    fmt::{Debug, Display},
+    str::FromStr,
+};
+
+use chess_core::{AcnMoveErr, Board, SelectedMove};
+use chess_parsers::{ParsedEpd, PieceMove};
+
+use crate::{annotation::san_line, Evaluator, Limits, Score, Searcher};
+
+/// A position from a test suite such as WAC, ECM or STS, with the moves
+/// the engine should play (`bm`) or avoid (`am`).
+#[derive(Clone, Debug)]
+pub struct TestPosition {
+    pub id: Option<String>,
+    pub board: Board,
+    pub best_moves: Vec<SelectedMove>,
+    pub avoid_moves: Vec<SelectedMove>,
+}
+
+impl TestPosition {
+    /// Reads the position and its `bm`, `am` and `id` operations. Moves are
+    /// in standard algebraic notation, and any `!` or `?` suffixes are
+    /// ignored.
+    pub fn from_epd(epd: &ParsedEpd) -> Result<Self, TestSuiteErr> {
+        let board =
+            Board::from_str(&epd.fen).map_err(|_| TestSuiteErr::InvalidFen(epd.fen.clone()))?;
+        let moves = |opcode| -> Result<Vec<SelectedMove>, TestSuiteErr> {
+            epd.operation(opcode)
+                .map(|operation| operation.operands.as_slice())
+                .unwrap_or_default()
+                .iter()
+                .map(|notation| resolve_move(&board, notation))
+                .collect()
+        };
+        let best_moves = moves("bm")?;
+        let avoid_moves = moves("am")?;
+        if best_moves.is_empty() && avoid_moves.is_empty() {
+            return Err(TestSuiteErr::NoExpectedMoves);
+        }
+
+        Ok(Self {
+            id: epd
+                .operation("id")
+                .and_then(|operation| operation.operands.first().cloned()),
+            board,
+            best_moves,
+            avoid_moves,
+        })
+    }
+
+    /// The theme of the position, taken from its id with the trailing
+    /// position number removed, so that `STS(v1.0) Undermining.001` has the
+    /// theme `STS(v1.0) Undermining`.
+    pub fn theme(&self) -> Option<String> {
+        let theme = self
+            .id
+            .as_ref()?
+            .trim_end_matches(|c: char| c.is_ascii_digit())
+            .trim_end_matches(['.', ' ', '-', '_', '#']);
+        (!theme.is_empty()).then(|| theme.to_string())
+    }
+
+    /// Whether playing `move_` solves the position: it has to be one of the
+    /// best moves if there are any, and none of the moves to avoid.
+    pub fn is_solved_by(&self, move_: &SelectedMove) -> bool {
+        (self.best_moves.is_empty() || self.best_moves.contains(move_))
+            && !self.avoid_moves.contains(move_)
+    }
+}
+
+/// Finds the legal move written as `notation` in the position.
+fn resolve_move(board: &Board, notation: &str) -> Result<SelectedMove, TestSuiteErr> {
+    let mut board = board.clone();
+    match board.make_move_acn(notation.trim_end_matches(['!', '?'])) {
+        // A wrong check marker doesn't make the move ambiguous.
+        Ok(move_) | Err(AcnMoveErr::CheckStateMismatch(move_)) => Ok(move_),
+        Err(_) => Err(TestSuiteErr::IllegalMove(notation.to_string())),
+    }
+}
+
+/// How the engine did on one test position.
+#[derive(Clone, Debug)]
+pub struct TestOutcome {
+    /// The move the engine chose, or None if the position has no legal
+    /// moves.
+    pub move_: Option<SelectedMove>,
+    pub notation: Option<PieceMove>,
+    pub solved: bool,
+    pub score: Score,
+    pub depth: usize,
+    pub nodes: u64,
+}
+
+/// Searches the position with `limits` and checks the chosen move against
+/// the position's best moves and moves to avoid. Everything the searcher
+/// learned from earlier positions is forgotten first, so that each result
+/// doesn't depend on the order of the suite.
+pub fn run_test_position<E: Evaluator + Clone + Send>(
+    searcher: &mut Searcher<E>,
+    position: &TestPosition,
+    limits: Limits,
+) -> TestOutcome {
+    searcher.new_game();
+    let mut board = position.board.clone();
+    let result = searcher.search(&mut board, limits);
+    let notation = result
+        .best_move
+        .as_ref()
+        .and_then(|move_| san_line(&position.board, std::slice::from_ref(move_)).pop());
+
+    TestOutcome {
+        solved: result
+            .best_move
+            .as_ref()
+            .is_some_and(|move_| position.is_solved_by(move_)),
+        move_: result.best_move,
+        notation,
+        score: result.score,
+        depth: result.depth,
+        nodes: result.nodes,
+    }
+}
+
+pub enum TestSuiteErr {
+    InvalidFen(String),
+    /// A `bm` or `am` move isn't legal in the position.
+    IllegalMove(String),
+    /// The position has neither a `bm` nor an `am` operation.
+    NoExpectedMoves,
+}
+
+impl Debug for TestSuiteErr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Just defer to the Display impl
+        write!(f, "{}", self)
+    }
+}
+
+impl Display for TestSuiteErr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TestSuiteErr::InvalidFen(fen) => write!(f, "The FEN {fen:?} is invalid"),
+            TestSuiteErr::IllegalMove(notation) => {
+                write!(f, "The move {notation} is illegal in the position")
+            }
+            TestSuiteErr::NoExpectedMoves => write!(f, "Expected a bm or am operation"),
+        }
+    }
+}
+
+impl Error for TestSuiteErr {}
+
+#[cfg(test)]
+mod tests {
+    use chess_parsers::parse_epd;
+
+    use super::{run_test_position, TestPosition, TestSuiteErr};
+    use crate::{Limits, Searcher};
+
+    fn position(epd: &str) -> Result<TestPosition, TestSuiteErr> {
+        TestPosition::from_epd(&parse_epd(epd).unwrap())
+    }
+
+    #[test]
+    fn checks_best_and_avoid_moves() {
+        // WAC.001: only the queen sacrifice mates.
+        let wac = position(
+            "2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - bm Qg6; id \"WAC.001\";",
+        )
+        .unwrap();
+        assert_eq!(Some("WAC".to_string()), wac.theme());
+        let outcome = run_test_position(&mut Searcher::new(), &wac, Limits::depth(5));
+        assert!(outcome.solved, "{:?}", outcome.notation);
+        assert_eq!("Qg6", outcome.notation.unwrap().to_string());
+
+        // Ra3 hangs the rook to the knight.
+        let avoid = position("4k3/8/8/8/2n5/8/8/R3K3 w - - am Ra3; id \"STS Test.002\";").unwrap();
+        assert_eq!(Some("STS Test".to_string()), avoid.theme());
+        assert!(!avoid.is_solved_by(&avoid.avoid_moves[0]));
+        let outcome = run_test_position(&mut Searcher::new(), &avoid, Limits::depth(3));
+        assert!(outcome.solved);
+    }
+
+    #[test]
+    fn rejects_positions_without_legal_expected_moves() {
+        assert!(matches!(
+            position("4k3/8/8/8/8/8/8/R3K3 w - - id \"none\";"),
+            Err(TestSuiteErr::NoExpectedMoves)
+        ));
+        assert!(matches!(
+            position("4k3/8/8/8/8/8/8/R3K3 w - - bm Rh8!;"),
+            Err(TestSuiteErr::IllegalMove(notation)) if notation == "Rh8!"
+        ));
+    }
+}
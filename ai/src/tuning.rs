@@ -0,0 +1,308 @@
+use std::str::FromStr;
+
+use chess_core::Board;
+use chess_parsers::{GameResult, ParsedEpd, ParsedGame};
+
+use crate::{
+    annotation::{play_notation, starting_board},
+    EvalParams, EvalTerms, Searcher, TaperedEvaluator,
+};
+
+/// Opening positions are mostly decided by book knowledge rather than the
+/// evaluation, so this many plies are skipped at the start of each game.
+const SKIPPED_OPENING_PLIES: usize = 8;
+
+/// A position labelled with the result of the game it came from.
+#[derive(Clone, Debug)]
+pub struct TuningPosition {
+    pub board: Board,
+    /// The game's result from white's point of view: 1 for a win, 0.5 for
+    /// a draw and 0 for a loss.
+    pub result: f64,
+}
+
+/// Samples every position from a game, starting from its FEN tag if it has
+/// one, and skipping the opening and positions where the side to move is in
+/// check. Games without a result or with an invalid FEN, and the moves
+/// after any illegal move, are skipped.
+pub fn positions_from_game(game: &ParsedGame) -> Vec<TuningPosition> {
+    let result = match result_value(&game.result) {
+        None => return Vec::new(),
+        Some(result) => result,
+    };
+
+    let mut board = match starting_board(game) {
+        Err(_) => return Vec::new(),
+        Ok(board) => board,
+    };
+    let mut positions = Vec::new();
+    for (ply, move_) in game.moves.iter().enumerate() {
+        if play_notation(&mut board, &move_.to_string()).is_none() {
+            break;
+        }
+
+        if ply + 1 >= SKIPPED_OPENING_PLIES && !board.is_check() {
+            // Starting from the FEN drops the game's history, which tuning
+            // has no use for.
+            let board =
+                Board::from_str(&board.to_fen_string()).expect("the board's FEN to be valid");
+            positions.push(TuningPosition { board, result });
+        }
+    }
+    positions
+}
+
+/// Reads a position labelled with the `c9` operation, which holds the
+/// game result as `1-0`, `0-1` or `1/2-1/2`.
+pub fn position_from_epd(epd: &ParsedEpd) -> Option<TuningPosition> {
+    let result = match epd.operation("c9")?.operands.first()?.as_str() {
+        "1-0" => 1.0,
+        "0-1" => 0.0,
+        "1/2-1/2" => 0.5,
+        _ => return None,
+    };
+    let board = Board::from_str(&epd.fen).ok()?;
+    Some(TuningPosition { board, result })
+}
+
+fn result_value(result: &GameResult) -> Option<f64> {
+    match result {
+        GameResult::WhiteWin => Some(1.0),
+        GameResult::BlackWin => Some(0.0),
+        GameResult::Draw => Some(0.5),
+        GameResult::Inconclusive => None,
+    }
+}
+
+/// Tunes [`EvalParams`] with Texel's method: the evaluation of each
+/// position is mapped to an expected score with a logistic function, and
+/// parameters are adjusted to minimise the mean squared difference between
+/// the expected scores and the actual game results.
+pub struct Tuner {
+    /// Quiet positions and the results of the games they came from.
+    positions: Vec<(Board, f64)>,
+    terms: EvalTerms,
+    /// Scales evaluations before the logistic function, so that the
+    /// evaluation's units match how decisive positions really are.
+    scaling: f64,
+}
+
+impl Tuner {
+    /// Prepares the positions for tuning. Each position is replaced by the
+    /// quiet position at the end of its quiescence search line, so that
+    /// pending captures don't distort the static evaluation.
+    pub fn new(positions: Vec<TuningPosition>, params: &EvalParams, terms: EvalTerms) -> Self {
+        let mut evaluator = TaperedEvaluator::new(params.clone());
+        evaluator.set_terms(terms.clone());
+        let mut searcher = Searcher::with_evaluator(evaluator);
+        searcher.set_hash_size(1);
+
+        let positions = positions
+            .into_iter()
+            .map(|TuningPosition { mut board, result }| {
+                for move_ in searcher.quiet_line(&mut board) {
+                    board
+                        .make_move_unchecked(move_)
+                        .expect("quiescence moves to be legal");
+                }
+                (board, result)
+            })
+            .collect();
+
+        Self {
+            positions,
+            terms,
+            scaling: 1.0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    pub fn scaling(&self) -> f64 {
+        self.scaling
+    }
+
+    pub fn set_scaling(&mut self, scaling: f64) {
+        self.scaling = scaling;
+    }
+
+    /// Finds the scaling that gives the lowest error for `params`, and uses
+    /// it from then on. This should be done before tuning, so that tuning
+    /// doesn't just rescale every parameter.
+    pub fn fit_scaling(&mut self, params: &EvalParams) -> f64 {
+        let scores = self.scores(params);
+        let (mut low, mut high) = (0.1_f64, 3.0_f64);
+        for _ in 0..50 {
+            let third = (high - low) / 3.0;
+            if self.error_for(&scores, low + third) < self.error_for(&scores, high - third) {
+                high -= third;
+            } else {
+                low += third;
+            }
+        }
+        self.scaling = (low + high) / 2.0;
+        self.scaling
+    }
+
+    /// The mean squared error of the evaluation with `params`.
+    pub fn error(&self, params: &EvalParams) -> f64 {
+        self.error_for(&self.scores(params), self.scaling)
+    }
+
+    /// Adjusts every parameter by `step` in whichever direction lowers the
+    /// error, one parameter at a time, until a whole pass over the
+    /// parameters makes no improvement or `max_passes` passes are done.
+    /// The phase weights are left alone, since they only make sense as
+    /// a whole. `on_pass` is called with the pass number and the error
+    /// after each pass.
+    pub fn tune(
+        &self,
+        mut params: EvalParams,
+        step: i32,
+        max_passes: usize,
+        mut on_pass: impl FnMut(usize, f64),
+    ) -> EvalParams {
+        let shape = params
+            .parameters_mut()
+            .into_iter()
+            .map(|(name, values)| (name, values.len()))
+            .collect::<Vec<_>>();
+
+        let mut best_error = self.error(&params);
+        for pass in 1..=max_passes {
+            let mut improved = false;
+            for (parameter, (name, length)) in shape.iter().enumerate() {
+                if name == "phase_weights" {
+                    continue;
+                }
+
+                for value in 0..*length {
+                    for delta in [step, -step] {
+                        adjust(&mut params, parameter, value, delta);
+                        let error = self.error(&params);
+                        if error < best_error {
+                            best_error = error;
+                            improved = true;
+                            break;
+                        }
+                        adjust(&mut params, parameter, value, -delta);
+                    }
+                }
+            }
+
+            on_pass(pass, best_error);
+            if !improved {
+                break;
+            }
+        }
+
+        params
+    }
+
+    /// Evaluates every position from white's point of view.
+    fn scores(&self, params: &EvalParams) -> Vec<i32> {
+        // A fresh evaluator is needed each time, since its pawn table
+        // caches scores computed with the old parameters.
+        let mut evaluator = TaperedEvaluator::new(params.clone());
+        evaluator.set_terms(self.terms.clone());
+        self.positions
+            .iter()
+            .map(|(board, _)| evaluator.evaluate_for_white(board))
+            .collect()
+    }
+
+    fn error_for(&self, scores: &[i32], scaling: f64) -> f64 {
+        let total = self
+            .positions
+            .iter()
+            .zip(scores)
+            .map(|((_, result), score)| {
+                let expected = 1.0 / (1.0 + 10_f64.powf(-scaling * *score as f64 / 400.0));
+                (result - expected).powi(2)
+            })
+            .sum::<f64>();
+        total / self.positions.len().max(1) as f64
+    }
+}
+
+fn adjust(params: &mut EvalParams, parameter: usize, value: usize, delta: i32) {
+    params.parameters_mut()[parameter].1[value] += delta;
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use chess_core::Board;
+    use chess_parsers::{parse_epd, parse_pgn};
+
+    use super::{position_from_epd, positions_from_game, Tuner, TuningPosition};
+    use crate::{EvalParams, EvalTerms};
+
+    #[test]
+    fn samples_labelled_positions() {
+        let pgn = b"[Result \"0-1\"]\n\n1. f3 e5 2. g4 Qh4# 0-1\n";
+        let games = parse_pgn(pgn).unwrap();
+        // The game is too short to get past the skipped opening moves.
+        assert!(positions_from_game(&games[0]).is_empty());
+
+        // Only the positions from the eighth ply on are kept.
+        let pgn = b"[SetUp \"1\"]\n[FEN \"4k3/8/8/8/8/8/R7/4K3 w - - 0 1\"]\n\
+            [Result \"1-0\"]\n\n1. Ra7 Kd8 2. Ke2 Kc8 3. Ke3 Kb8 4. Rh7 Kc8 5. Ke4 Kd8 1-0\n";
+        let games = parse_pgn(pgn).unwrap();
+        let positions = positions_from_game(&games[0]);
+        assert_eq!(3, positions.len());
+        assert!(positions[0]
+            .board
+            .to_fen_string()
+            .starts_with("2k5/7R/8/8/8/4K3/8/8 w - -"));
+        assert!(positions.iter().all(|position| position.result == 1.0));
+
+        let epd = parse_epd("4k3/8/8/8/8/8/8/3QK3 w - - c9 \"1-0\";").unwrap();
+        let position = position_from_epd(&epd).unwrap();
+        assert_eq!(1.0, position.result);
+    }
+
+    #[test]
+    fn resolves_captures_before_evaluating() {
+        // The queen on d5 hangs, so the quiet position is after Rxd5.
+        let position = TuningPosition {
+            board: Board::from_str("4k3/8/8/3q4/8/8/3R4/4K3 w - - 0 1").unwrap(),
+            result: 1.0,
+        };
+        let tuner = Tuner::new(vec![position], &EvalParams::default(), EvalTerms::default());
+        let fen = tuner.positions[0].0.to_fen_string();
+        assert!(fen.starts_with("4k3/8/8/3R4/8/8/8/4K3 b - -"));
+    }
+
+    #[test]
+    fn tuning_lowers_the_error() {
+        let positions = [
+            ("4k3/8/8/8/8/8/4P3/3NK3 w - - 0 1", 1.0),
+            ("3nk3/4p3/8/8/8/8/8/4K3 w - - 0 1", 0.0),
+            ("4k3/4p3/8/8/8/8/4P3/4K3 w - - 0 1", 0.5),
+        ]
+        .into_iter()
+        .map(|(fen, result)| TuningPosition {
+            board: Board::from_str(fen).unwrap(),
+            result,
+        })
+        .collect();
+
+        let params = EvalParams::default();
+        let mut tuner = Tuner::new(positions, &params, EvalTerms::none());
+        tuner.set_scaling(1.0);
+        let error_before = tuner.error(&params);
+
+        let mut passes = 0;
+        let tuned = tuner.tune(params, 5, 1, |_, _| passes += 1);
+        assert_eq!(1, passes);
+        assert!(tuner.error(&tuned) < error_before);
+    }
+}
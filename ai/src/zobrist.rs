@@ -0,0 +1,330 @@
+use chess_common::{Piece, PieceKind, Player};
+use chess_core::Board;
+
+pub(crate) const PIECE_KINDS: [PieceKind; 6] = [
+    PieceKind::Pawn,
+    PieceKind::Knight,
+    PieceKind::Bishop,
+    PieceKind::Rook,
+    PieceKind::Queen,
+    PieceKind::King,
+];
+
+pub(crate) const PLAYERS: [Player; 2] = [Player::White, Player::Black];
+
+/// The random keys used to hash a position. These are generated at
+/// compile time from a fixed seed so that hashes are stable between runs.
+struct ZobristKeys {
+    /// Indexed by `[player][piece kind][square]`.
+    pieces: [[[u64; 64]; 6]; 2],
+    /// Indexed by `[player][kingside = 0, queenside = 1]`.
+    castling: [[u64; 2]; 2],
+    en_passant_file: [u64; 8],
+    black_to_move: u64,
+}
+
+const fn splitmix64(state: u64) -> (u64, u64) {
+    let state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    (state, z ^ (z >> 31))
+}
+
+const fn generate_keys() -> ZobristKeys {
+    let mut keys = ZobristKeys {
+        pieces: [[[0; 64]; 6]; 2],
+        castling: [[0; 2]; 2],
+        en_passant_file: [0; 8],
+        black_to_move: 0,
+    };
+
+    let mut state = 0x2545_F491_4F6C_DD1D;
+    let mut player = 0;
+    while player < 2 {
+        let mut kind = 0;
+        while kind < 6 {
+            let mut square = 0;
+            while square < 64 {
+                let (next_state, key) = splitmix64(state);
+                state = next_state;
+                keys.pieces[player][kind][square] = key;
+                square += 1;
+            }
+            kind += 1;
+        }
+        player += 1;
+    }
+
+    let mut player = 0;
+    while player < 2 {
+        let mut side = 0;
+        while side < 2 {
+            let (next_state, key) = splitmix64(state);
+            state = next_state;
+            keys.castling[player][side] = key;
+            side += 1;
+        }
+        player += 1;
+    }
+
+    let mut file = 0;
+    while file < 8 {
+        let (next_state, key) = splitmix64(state);
+        state = next_state;
+        keys.en_passant_file[file] = key;
+        file += 1;
+    }
+
+    let (_, key) = splitmix64(state);
+    keys.black_to_move = key;
+    keys
+}
+
+static KEYS: ZobristKeys = generate_keys();
+
+/// Computes the Zobrist hash of the board's current position. Two boards
+/// with the same pieces, side to move, castling rights and en-passant
+/// target square will always have the same hash.
+pub(crate) fn hash(board: &Board) -> u64 {
+    let mut hash = 0;
+    for player in PLAYERS {
+        for kind in PIECE_KINDS {
+            hash ^= hash_bitboard(
+                board.bitboard_for(&Piece::new(player, kind)),
+                &KEYS.pieces[player.as_index()][kind as usize],
+            );
+        }
+
+        let castling = &KEYS.castling[player.as_index()];
+        if board.player_can_castle_kingside(&player) {
+            hash ^= castling[0];
+        }
+        if board.player_can_castle_queenside(&player) {
+            hash ^= castling[1];
+        }
+    }
+
+    if let Some(en_passant) = board.en_passant_target_square() {
+        hash ^= KEYS.en_passant_file[en_passant.file().as_index()];
+    }
+
+    if board.player_to_move() == Player::Black {
+        hash ^= KEYS.black_to_move;
+    }
+
+    hash
+}
+
+/// The piece bitboards of a position, indexed by `[player][piece kind]`.
+pub(crate) type PieceBitboards = [[u64; 6]; 2];
+
+/// Gets the bitboard of every kind of piece for both players.
+pub(crate) fn piece_bitboards(board: &Board) -> PieceBitboards {
+    let mut bitboards = [[0; 6]; 2];
+    for player in PLAYERS {
+        for kind in PIECE_KINDS {
+            bitboards[player.as_index()][kind as usize] =
+                board.bitboard_for(&Piece::new(player, kind));
+        }
+    }
+    bitboards
+}
+
+/// The castling rights that are kept when a piece moves from or to each
+/// square, as bits of [`PositionKey::castling`]. Moving the king or a rook
+/// from its starting square, or capturing on it, loses the right for good.
+const CASTLING_KEPT: [u8; 64] = {
+    let mut kept = [0b1111; 64];
+    kept[4] = 0b1100;
+    kept[7] = 0b1110;
+    kept[0] = 0b1101;
+    kept[60] = 0b0011;
+    kept[63] = 0b1011;
+    kept[56] = 0b0111;
+    kept
+};
+
+/// A position's hash, along with what it takes to update it as moves are
+/// made rather than hashing every piece again.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct PositionKey {
+    pub(crate) key: u64,
+    /// The castling rights left, one bit per `[player][kingside, queenside]`.
+    castling: u8,
+    en_passant_file: Option<usize>,
+    /// The number of plies since the last capture or pawn move. No earlier
+    /// position can be repeated, since those moves can't be taken back.
+    pub(crate) reversible_plies: usize,
+}
+
+impl PositionKey {
+    pub(crate) fn new(board: &Board) -> Self {
+        let mut castling = 0;
+        for player in PLAYERS {
+            if board.player_can_castle_kingside(&player) {
+                castling |= 1 << (player.as_index() * 2);
+            }
+            if board.player_can_castle_queenside(&player) {
+                castling |= 1 << (player.as_index() * 2 + 1);
+            }
+        }
+
+        Self {
+            key: hash(board),
+            castling,
+            en_passant_file: board
+                .en_passant_target_square()
+                .map(|square| square.file().as_index()),
+            reversible_plies: 0,
+        }
+    }
+
+    /// Gets the key of the position on `board`, which was reached from this
+    /// one by moving from `from` to `to`. `before` holds the piece
+    /// bitboards from before the move, and `irreversible` whether it was a
+    /// capture or a pawn move.
+    pub(crate) fn after_move(
+        &self,
+        before: &PieceBitboards,
+        board: &Board,
+        from: usize,
+        to: usize,
+        irreversible: bool,
+    ) -> Self {
+        let mut key = self.key ^ KEYS.black_to_move;
+        let after = piece_bitboards(board);
+        for player in 0..2 {
+            for kind in 0..6 {
+                key ^= hash_bitboard(
+                    before[player][kind] ^ after[player][kind],
+                    &KEYS.pieces[player][kind],
+                );
+            }
+        }
+
+        let castling = self.castling & CASTLING_KEPT[from] & CASTLING_KEPT[to];
+        key ^= castling_hash(self.castling ^ castling);
+
+        let en_passant_file = board
+            .en_passant_target_square()
+            .map(|square| square.file().as_index());
+        key ^= en_passant_hash(self.en_passant_file) ^ en_passant_hash(en_passant_file);
+
+        Self {
+            key,
+            castling,
+            en_passant_file,
+            reversible_plies: if irreversible {
+                0
+            } else {
+                self.reversible_plies + 1
+            },
+        }
+    }
+
+    /// Gets the key of the position after passing the turn.
+    pub(crate) fn after_null_move(&self) -> Self {
+        Self {
+            key: self.key ^ KEYS.black_to_move ^ en_passant_hash(self.en_passant_file),
+            en_passant_file: None,
+            reversible_plies: self.reversible_plies + 1,
+            ..*self
+        }
+    }
+}
+
+fn castling_hash(castling: u8) -> u64 {
+    let mut hash = 0;
+    for (bit, key) in KEYS.castling.iter().flatten().enumerate() {
+        if castling & (1 << bit) != 0 {
+            hash ^= key;
+        }
+    }
+    hash
+}
+
+fn en_passant_hash(file: Option<usize>) -> u64 {
+    file.map_or(0, |file| KEYS.en_passant_file[file])
+}
+
+/// Computes a hash of only the pawns on the board, for caching anything
+/// that depends on nothing but the pawn structure.
+pub(crate) fn pawn_hash(board: &Board) -> u64 {
+    let mut hash = 0;
+    for player in PLAYERS {
+        hash ^= hash_bitboard(
+            board.bitboard_for(&Piece::new(player, PieceKind::Pawn)),
+            &KEYS.pieces[player.as_index()][PieceKind::Pawn as usize],
+        );
+    }
+    hash
+}
+
+fn hash_bitboard(mut bitboard: u64, keys: &[u64; 64]) -> u64 {
+    let mut hash = 0;
+    while bitboard != 0 {
+        hash ^= keys[bitboard.trailing_zeros() as usize];
+        bitboard &= bitboard - 1;
+    }
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use chess_core::Board;
+
+    use super::{piece_bitboards, PositionKey};
+    use crate::search_algorithms::square_index;
+
+    #[test]
+    fn transpositions_hash_equally() {
+        let mut board_1 = Board::default();
+        for move_ in ["Nf3", "Nf6", "Nc3", "Nc6"] {
+            board_1.make_move_acn(move_).unwrap();
+        }
+
+        let mut board_2 = Board::default();
+        for move_ in ["Nc3", "Nc6", "Nf3", "Nf6"] {
+            board_2.make_move_acn(move_).unwrap();
+        }
+
+        assert_eq!(super::hash(&board_1), super::hash(&board_2));
+        assert_ne!(super::hash(&board_1), super::hash(&Board::default()));
+    }
+
+    #[test]
+    fn keys_update_move_by_move() {
+        // An en-passant capture, a double pawn push, a rook capture that
+        // costs both sides a castling right, castling and a promotion.
+        let mut board = Board::from_str("r3k2r/1P4p1/8/3pP3/8/8/8/R3K2R w KQkq d6 0 1").unwrap();
+        let mut key = PositionKey::new(&board);
+        for uci in [
+            "e5d6", "g7g5", "a1a8", "e8d7", "e1g1", "d7d6", "b7b8q", "d6e6",
+        ] {
+            let move_ = board
+                .possible_moves()
+                .find(|move_| move_.to_uci() == uci)
+                .unwrap_or_else(|| panic!("{uci} to be legal"));
+            let before = piece_bitboards(&board);
+            let from = square_index(move_.move_().from());
+            let to = square_index(move_.move_().to());
+            board.make_move(move_).unwrap();
+            key = key.after_move(&before, &board, from, to, false);
+            assert_eq!(super::hash(&board), key.key, "after {uci}");
+        }
+
+        board.make_null_move().unwrap();
+        key = key.after_null_move();
+        assert_eq!(super::hash(&board), key.key);
+    }
+
+    #[test]
+    fn side_to_move_changes_hash() {
+        let white = Board::from_str("4k3/8/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        let black = Board::from_str("4k3/8/8/8/8/8/8/4K3 b - - 0 1").unwrap();
+        assert_ne!(super::hash(&white), super::hash(&black));
+    }
+}
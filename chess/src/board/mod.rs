@@ -3,6 +3,8 @@ pub use acn_move_err::AcnMoveErr;
 mod move_err;
 use iso_8859_1_encoder::Iso8859String;
 use move_err::MoveErr;
+mod null_move_err;
+pub use null_move_err::NullMoveErr;
 mod undoable_move;
 use streaming_iterator::StreamingIterator;
 use undoable_move::UndoableMove;
@@ -40,6 +42,9 @@ pub struct Board {
     pub(crate) mailbox: BitBoard,
     history: Vec<UndoableMove>,
     first_player_to_move: Player,
+    /// The length of the history when each null move still on the board
+    /// was made, oldest first.
+    null_moves: Vec<usize>,
 }
 
 impl Default for Board {
@@ -71,6 +76,7 @@ impl From<BoardLayout> for Board {
             mailbox: BitBoard::default(),
             history: Vec::new(),
             first_player_to_move: player_to_move,
+            null_moves: Vec::new(),
         };
 
         for location in Location::all_locations() {
@@ -91,7 +97,7 @@ impl Board {
 
     /// Calculates the material advantage of the current board position,
     /// assuming that pawns are worth 1 point, knights and bishops are worth 3 points,
-    /// rooks are worth 5 points, and queens are worth 8 points.
+    /// rooks are worth 5 points, and queens are worth 9 points.
     pub fn material_advantage(&self) -> i32 {
         let pawn_diff = self.pawns[white!()].bit_count() - self.pawns[black!()].bit_count();
         let knight_diff = self.knights[white!()].bit_count() - self.knights[black!()].bit_count();
@@ -103,8 +109,7 @@ impl Board {
             + knight_diff * 3
             + bishop_diff * 3
             + rook_diff * 5
-            + queen_diff * 5
-            + rook_diff * 8;
+            + queen_diff * 9;
     }
 
     /// Loops through the bitboards and updates the mailbox bitboard.
@@ -171,6 +176,20 @@ impl Board {
         );
     }
 
+    /// Gets the bitboard of the squares occupied by the specified piece.
+    /// Bit 0 is a1, bit 7 is h1, and bit 63 is h8.
+    pub fn bitboard_for(&self, piece: &Piece) -> u64 {
+        let player = piece.player().as_index();
+        match piece.kind() {
+            PieceKind::Pawn => self.pawns[player].0,
+            PieceKind::Knight => self.knights[player].0,
+            PieceKind::Bishop => self.bishops[player].0,
+            PieceKind::Rook => self.rooks[player].0,
+            PieceKind::Queen => self.queens[player].0,
+            PieceKind::King => self.kings[player].0,
+        }
+    }
+
     /// Gets the player whose turn it currently is.
     pub fn player_to_move(&self) -> Player {
         if self.history.len() % 2 == 0 {
@@ -181,7 +200,7 @@ impl Board {
     }
 
     /// Gets whether or not the specified player can castle kingside.
-    pub(crate) fn player_can_castle_kingside(&self, player: &Player) -> bool {
+    pub fn player_can_castle_kingside(&self, player: &Player) -> bool {
         match player {
             Player::White => {
                 if !self.starting_position.white_can_castle_kingside() {
@@ -217,7 +236,7 @@ impl Board {
     }
 
     /// Gets whether or not the specified player can castle queenside.
-    pub(crate) fn player_can_castle_queenside(&self, player: &Player) -> bool {
+    pub fn player_can_castle_queenside(&self, player: &Player) -> bool {
         match player {
             Player::White => {
                 if !self.starting_position.white_can_castle_queenside() {
@@ -312,7 +331,7 @@ impl Board {
 
     /// Gets whether the current position is a check for the player whose turn
     /// it is.
-    fn is_check(&self) -> bool {
+    pub fn is_check(&self) -> bool {
         let player_to_move = self.player_to_move();
         let king_position = self.kings[player_to_move.as_index()].0;
         LegalKingMovesIterator::is_check(self, player_to_move, king_position)
@@ -330,7 +349,7 @@ impl Board {
     }
 
     /// Gets the piece at the specified location.
-    fn at(&self, location: &Location) -> Option<Piece> {
+    pub fn at(&self, location: &Location) -> Option<Piece> {
         let location_bits = location.as_u64();
 
         if location_bits == 0 {
@@ -1035,9 +1054,46 @@ impl Board {
         }
     }
 
+    /// Passes the turn to the other player without moving a piece. This is
+    /// not a legal move in chess, but searches use it to find out whether
+    /// a position is so good that even doing nothing keeps it good.
+    ///
+    /// Null moves can't be made while in check, or while there is an
+    /// en-passant target square, since the target would wrongly carry over
+    /// to the other player. They must be taken back with
+    /// [`Board::undo_null_move`] before any earlier move can be undone.
+    pub fn make_null_move(&mut self) -> Result<(), NullMoveErr> {
+        if self.is_check() {
+            return Err(NullMoveErr::InCheck);
+        }
+        if self.en_passant_target_square().is_some() {
+            return Err(NullMoveErr::EnPassantPending);
+        }
+
+        self.null_moves.push(self.history.len());
+        self.first_player_to_move = self.first_player_to_move.other_player();
+        Ok(())
+    }
+
+    /// Takes back a null move. This operation will fail if the last move
+    /// made was not a null move.
+    pub fn undo_null_move(&mut self) -> Result<(), NullMoveErr> {
+        if self.null_moves.last() != Some(&self.history.len()) {
+            return Err(NullMoveErr::NotANullMove);
+        }
+
+        self.null_moves.pop();
+        self.first_player_to_move = self.first_player_to_move.other_player();
+        Ok(())
+    }
+
     /// Undoes the last move. This operation will fail if the
-    /// undo stack is empty.
+    /// undo stack is empty, or if the last move was a null move.
     pub fn undo(&mut self) -> Result<UndoableMove, ()> {
+        if self.null_moves.last() == Some(&self.history.len()) {
+            return Err(());
+        }
+
         match self.history.pop() {
             None => Err(()),
             Some(last_move) => {
@@ -1196,6 +1252,57 @@ impl Into<ParsedGame> for &Board {
     }
 }
 
+#[cfg(test)]
+mod null_move_tests {
+    use std::str::FromStr;
+
+    use chess_common::Player;
+
+    use crate::{Board, NullMoveErr};
+
+    #[test]
+    fn null_moves_pass_the_turn() {
+        let mut board = Board::default();
+        board.make_null_move().unwrap();
+        assert_eq!(Player::Black, board.player_to_move());
+
+        board.make_move_acn("e5").unwrap();
+        assert_eq!(Err(NullMoveErr::EnPassantPending), board.make_null_move());
+        board.undo().unwrap();
+
+        assert!(board.undo().is_err(), "the null move must be undone first");
+        board.undo_null_move().unwrap();
+        assert_eq!(Player::White, board.player_to_move());
+        assert_eq!(Err(NullMoveErr::NotANullMove), board.undo_null_move());
+    }
+
+    #[test]
+    fn null_moves_are_not_allowed_in_check() {
+        let mut board = Board::from_str("4k3/8/8/8/8/8/8/4K2r w - - 0 1").unwrap();
+        assert_eq!(Err(NullMoveErr::InCheck), board.make_null_move());
+    }
+}
+
+#[cfg(test)]
+mod material_tests {
+    use std::str::FromStr;
+
+    use crate::Board;
+
+    #[test]
+    fn material_advantage_counts_each_piece_once() {
+        assert_eq!(0, Board::default().material_advantage());
+
+        // White has a pawn, a knight, a rook and a queen against two pawns
+        // and a rook.
+        let board = Board::from_str("r3k3/pp6/8/8/8/8/P7/RN1QK3 w - - 0 1").unwrap();
+        assert_eq!(11, board.material_advantage());
+
+        let board = Board::from_str("rq2k3/8/8/8/8/8/8/R3K3 w - - 0 1").unwrap();
+        assert_eq!(-9, board.material_advantage());
+    }
+}
+
 pub mod perft_tests {
     use std::{collections::HashSet, io::Write, str::FromStr};
 
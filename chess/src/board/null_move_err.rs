@@ -0,0 +1,11 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NullMoveErr {
+    /// Signifies that a null move can't be made while in check.
+    InCheck,
+    /// Signifies that a null move can't be made while there is an
+    /// en-passant target square.
+    EnPassantPending,
+    /// Signifies that the last move made was not a null move, so there is
+    /// no null move to take back.
+    NotANullMove,
+}
@@ -5,7 +5,7 @@ mod legal_moves;
 mod moves;
 mod possible_moves;
 
-pub use board::{Board, AcnMoveErr};
+pub use board::{Board, AcnMoveErr, NullMoveErr};
 pub use iterative_deepening::IterativeDeepeningMovesIterator;
 pub use legal_moves::LegalMovesIterator;
 pub use moves::{Move, PossibleMove, SelectedMove};
@@ -42,6 +42,10 @@ impl Move {
         ),
     };
 
+    pub const fn new(from: Location, to: Location) -> Self {
+        Self { from, to }
+    }
+
     pub fn from(&self) -> &Location {
         &self.from
     }
@@ -3,7 +3,7 @@ use serde_derive::Deserialize;
 
 use super::move_::Move;
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize)]
 #[serde(tag = "type")]
 pub enum SelectedMove {
     Promotion {
@@ -36,4 +36,15 @@ impl SelectedMove {
             Self::Normal { .. } => None,
         }
     }
+
+    /// Gets the move in the long algebraic notation used by UCI, such as
+    /// `e2e4` or `e7e8q`. Castling is written as the king's move.
+    pub fn to_uci(&self) -> String {
+        let mut uci = self.move_().from().to_string();
+        uci.push_str(&self.move_().to().to_string());
+        if let Some(kind) = self.promotion_kind() {
+            uci.push(kind.as_char().to_ascii_lowercase());
+        }
+        uci
+    }
 }
@@ -0,0 +1,70 @@
+use std::fs;
+
+use chess_ai::{analyse_game, AnalysisSettings, MoveQuality, Searcher};
+use chess_common::Player;
+use iso_8859_1_encoder::Iso8859String;
+use log::{error, warn};
+
+use crate::shared::{read_pgn_games, search_limits, tag};
+
+/// Analyses every game in `file_name` and writes them to `output` with each
+/// move's evaluation, its inaccuracies, mistakes and blunders and the lines
+/// that should have been played. A summary of each game, with the average
+/// centipawn loss of each side, is printed as it's analysed.
+pub(crate) fn handle_annotate_subcommand(
+    file_name: &str,
+    output: &str,
+    depth: Option<usize>,
+    nodes: Option<u64>,
+    move_time: Option<u64>,
+) -> Result<(), ()> {
+    let mut games = read_pgn_games(file_name)?;
+
+    let mut settings = AnalysisSettings::default();
+    if let Some(limits) = search_limits(depth, nodes, move_time) {
+        settings.limits = limits;
+    }
+
+    let mut searcher = Searcher::new();
+    let mut annotated = Vec::new();
+    for (i, game) in games.iter_mut().enumerate() {
+        let title = format!(
+            "Game {} ({} vs {})",
+            i + 1,
+            tag(game, "White"),
+            tag(game, "Black")
+        );
+
+        let analysis = match analyse_game(game, &mut searcher, &settings) {
+            Err(err) => {
+                warn!("Skipping {title}: {err}");
+                continue;
+            }
+            Ok(analysis) => analysis,
+        };
+
+        println!("{title}");
+        for player in [Player::White, Player::Black] {
+            let loss = analysis
+                .average_centipawn_loss(player)
+                .map_or("-".to_string(), |loss| format!("{loss:.0}"));
+            println!(
+                "  {player:?}: {} inaccuracies, {} mistakes, {} blunders, average centipawn loss {loss}",
+                analysis.count(player, MoveQuality::Inaccuracy),
+                analysis.count(player, MoveQuality::Mistake),
+                analysis.count(player, MoveQuality::Blunder),
+            );
+        }
+
+        analysis.annotate(game);
+        let pgn: Iso8859String = (&*game).into();
+        annotated.extend_from_slice(pgn.as_bytes());
+        annotated.extend_from_slice(b"\n\n");
+    }
+
+    if let Err(err) = fs::write(output, annotated) {
+        error!("Failed to write {output}. Inner error: {err}");
+        return Err(());
+    }
+    Ok(())
+}
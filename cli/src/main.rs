@@ -1,4 +1,12 @@
-use std::{fs::OpenOptions, io::Read, process::ExitCode};
+mod annotate;
+mod matches;
+mod puzzles;
+mod shared;
+mod solve;
+mod suite;
+mod tune;
+
+use std::{fs::OpenOptions, io::Read, process::ExitCode, time::Duration};
 
 use chess_common::{File, PieceKind, Player, Rank};
 use chess_core::{AcnMoveErr, Board};
@@ -38,6 +46,96 @@ fn main() -> ExitCode {
                 return ExitCode::FAILURE;
             }
         }
+        Some(("tune", args)) => {
+            let output = args
+                .get_one::<String>("output params file")
+                .expect("'output params file' is required");
+            let starting_params = args.get_one::<String>("params");
+            let passes = *args
+                .get_one::<usize>("passes")
+                .expect("'passes' has a default");
+            let step = *args.get_one::<i32>("step").expect("'step' has a default");
+
+            let files = args
+                .get_many::<String>("position files")
+                .expect("'position files' is required")
+                .collect::<Vec<_>>();
+
+            match tune::handle_tune_subcommand(output, starting_params, passes, step, files) {
+                Ok(()) => ExitCode::SUCCESS,
+                Err(()) => ExitCode::FAILURE,
+            }
+        }
+        Some(("solve", args)) => {
+            let file = args
+                .get_one::<String>("problem file")
+                .expect("'problem file' is required");
+
+            match solve::handle_solve_subcommand(file) {
+                Ok(()) => ExitCode::SUCCESS,
+                Err(()) => ExitCode::FAILURE,
+            }
+        }
+        Some(("annotate", args)) => {
+            let file = args
+                .get_one::<String>("pgn file")
+                .expect("'pgn file' is required");
+            let output = args
+                .get_one::<String>("output pgn file")
+                .expect("'output pgn file' is required");
+            let depth = args.get_one::<usize>("depth").copied();
+            let nodes = args.get_one::<u64>("nodes").copied();
+            let move_time = args.get_one::<u64>("movetime").copied();
+
+            match annotate::handle_annotate_subcommand(file, output, depth, nodes, move_time) {
+                Ok(()) => ExitCode::SUCCESS,
+                Err(()) => ExitCode::FAILURE,
+            }
+        }
+        Some(("match", args)) => match matches::handle_match_subcommand(args) {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(()) => ExitCode::FAILURE,
+        },
+        Some(("suite", args)) => {
+            let files = args
+                .get_many::<String>("epd files")
+                .expect("'epd files' is required")
+                .collect::<Vec<_>>();
+            let depth = args.get_one::<usize>("depth").copied();
+            let nodes = args.get_one::<u64>("nodes").copied();
+            let move_time = args.get_one::<u64>("movetime").copied();
+            // Suites are usually run at a fixed time per position.
+            let limits = shared::search_limits(depth, nodes, move_time)
+                .unwrap_or_else(|| chess_ai::Limits::move_time(Duration::from_millis(1000)));
+            let threads = *args.get_one::<usize>("threads").expect("'threads' has a default");
+            let hash = args.get_one::<usize>("hash").copied();
+
+            match suite::handle_suite_subcommand(files, limits, threads, hash) {
+                Ok(()) => ExitCode::SUCCESS,
+                Err(()) => ExitCode::FAILURE,
+            }
+        }
+        Some(("puzzles", args)) => {
+            let output = args
+                .get_one::<String>("output epd file")
+                .expect("'output epd file' is required");
+            let files = args
+                .get_many::<String>("game files")
+                .expect("'game files' is required")
+                .collect::<Vec<_>>();
+            let settings = puzzles::settings(
+                args.get_one::<usize>("depth").copied(),
+                args.get_one::<u64>("nodes").copied(),
+                args.get_one::<u64>("movetime").copied(),
+                *args.get_one::<usize>("max moves").expect("'max moves' has a default"),
+                *args.get_one::<usize>("skip plies").expect("'skip plies' has a default"),
+            );
+
+            match puzzles::handle_puzzles_subcommand(files, output, settings) {
+                Ok(()) => ExitCode::SUCCESS,
+                Err(()) => ExitCode::FAILURE,
+            }
+        }
         Some((_, _)) => {
             unreachable!("clap should prevent coming to this branch");
         }
@@ -63,6 +161,302 @@ fn create_command() -> Command {
                     .help("the pgn files to be loaded into the sqlite"),
             ),
     )
+    .subcommand(
+        Command::new("tune")
+            .about("Tunes the evaluation parameters against positions with known game results")
+            .arg(
+                Arg::new("output params file")
+                    .required(true)
+                    .help("the file where the tuned parameters should be written"),
+            )
+            .arg(
+                Arg::new("params")
+                    .long("params")
+                    .help("a parameter file to start from instead of the defaults"),
+            )
+            .arg(
+                Arg::new("passes")
+                    .long("passes")
+                    .value_parser(clap::value_parser!(usize))
+                    .default_value("10")
+                    .help("the most passes to make over the parameters"),
+            )
+            .arg(
+                Arg::new("step")
+                    .long("step")
+                    .value_parser(clap::value_parser!(i32))
+                    .default_value("1")
+                    .help("how much to adjust a parameter by at a time"),
+            )
+            .arg(
+                Arg::last(Arg::new("position files"), true)
+                    .num_args(1..)
+                    .required(true)
+                    .help("pgn files, or epd files with results in a c9 operation"),
+            ),
+    )
+    .subcommand(
+        Command::new("solve")
+            .about("Solves chess problems such as direct mates, helpmates, selfmates and reflexmates")
+            .arg(
+                Arg::new("problem file")
+                    .required(true)
+                    .help("a file with a FEN followed by a stipulation such as #2, h#3, s#2 or r#2 on each line"),
+            ),
+    )
+    .subcommand(
+        Command::new("annotate")
+            .about("Annotates games with evaluations, inaccuracies, mistakes, blunders and better lines")
+            .arg(
+                Arg::new("pgn file")
+                    .required(true)
+                    .help("the games to annotate"),
+            )
+            .arg(
+                Arg::new("output pgn file")
+                    .required(true)
+                    .help("the file where the annotated games should be written"),
+            )
+            .arg(
+                Arg::new("depth")
+                    .long("depth")
+                    .value_parser(clap::value_parser!(usize))
+                    .help("the depth to search each position to, 10 if no limit is given"),
+            )
+            .arg(
+                Arg::new("nodes")
+                    .long("nodes")
+                    .value_parser(clap::value_parser!(u64))
+                    .help("the number of nodes to search in each position"),
+            )
+            .arg(
+                Arg::new("movetime")
+                    .long("movetime")
+                    .value_parser(clap::value_parser!(u64))
+                    .help("the milliseconds to search each position for"),
+            ),
+    )
+    .subcommand(
+        Command::new("match")
+            .about("Plays a match between two engines and reports the Elo difference and SPRT result")
+            .arg(
+                Arg::new("engine1")
+                    .long("engine1")
+                    .required(true)
+                    .help("the first engine, such as name=new,params=new.params or cmd=./engine,option.Hash=64"),
+            )
+            .arg(
+                Arg::new("engine2")
+                    .long("engine2")
+                    .required(true)
+                    .help("the second engine, which the first is measured against"),
+            )
+            .arg(
+                Arg::new("openings")
+                    .long("openings")
+                    .help("a pgn or epd file, or a file with a FEN on each line, to start the games from"),
+            )
+            .arg(
+                Arg::new("plies")
+                    .long("plies")
+                    .value_parser(clap::value_parser!(usize))
+                    .help("the most moves to play from each game of a pgn opening book"),
+            )
+            .arg(
+                Arg::new("games")
+                    .long("games")
+                    .value_parser(clap::value_parser!(usize))
+                    .default_value("100")
+                    .help("the number of games to play, with each opening played with both colours"),
+            )
+            .arg(
+                Arg::new("concurrency")
+                    .long("concurrency")
+                    .value_parser(clap::value_parser!(usize))
+                    .default_value("1")
+                    .help("the number of games to play at once"),
+            )
+            .arg(
+                Arg::new("tc")
+                    .long("tc")
+                    .help("the clock in seconds plus increment, such as 10+0.1"),
+            )
+            .arg(
+                Arg::new("movetime")
+                    .long("movetime")
+                    .value_parser(clap::value_parser!(u64))
+                    .help("the milliseconds to think per move"),
+            )
+            .arg(
+                Arg::new("depth")
+                    .long("depth")
+                    .value_parser(clap::value_parser!(usize))
+                    .help("the depth to search each move to"),
+            )
+            .arg(
+                Arg::new("nodes")
+                    .long("nodes")
+                    .value_parser(clap::value_parser!(u64))
+                    .help("the number of nodes to search per move"),
+            )
+            .arg(
+                Arg::new("resign score")
+                    .long("resign-score")
+                    .value_parser(clap::value_parser!(i32))
+                    .help("adjudicate a win once both engines agree one side is this many centipawns ahead"),
+            )
+            .arg(
+                Arg::new("resign moves")
+                    .long("resign-moves")
+                    .value_parser(clap::value_parser!(usize))
+                    .default_value("3")
+                    .help("how many moves in a row both engines have to agree for a win"),
+            )
+            .arg(
+                Arg::new("draw score")
+                    .long("draw-score")
+                    .value_parser(clap::value_parser!(i32))
+                    .help("adjudicate a draw once both engines score the game within this many centipawns"),
+            )
+            .arg(
+                Arg::new("draw moves")
+                    .long("draw-moves")
+                    .value_parser(clap::value_parser!(usize))
+                    .default_value("8")
+                    .help("how many moves in a row both engines have to agree for a draw"),
+            )
+            .arg(
+                Arg::new("draw after")
+                    .long("draw-after")
+                    .value_parser(clap::value_parser!(usize))
+                    .default_value("40")
+                    .help("the first move number at which a draw can be adjudicated"),
+            )
+            .arg(
+                Arg::new("max moves")
+                    .long("max-moves")
+                    .value_parser(clap::value_parser!(usize))
+                    .help("adjudicate a draw after this many moves by each side"),
+            )
+            .arg(
+                Arg::new("sprt")
+                    .long("sprt")
+                    .help("stop once an SPRT decides between elo0 and elo1, given as elo0,elo1"),
+            )
+            .arg(
+                Arg::new("alpha")
+                    .long("alpha")
+                    .value_parser(clap::value_parser!(f64))
+                    .default_value("0.05")
+                    .help("the SPRT's chance of passing a change that isn't elo1 better"),
+            )
+            .arg(
+                Arg::new("beta")
+                    .long("beta")
+                    .value_parser(clap::value_parser!(f64))
+                    .default_value("0.05")
+                    .help("the SPRT's chance of failing a change that is elo1 better"),
+            )
+            .arg(
+                Arg::new("pgn output")
+                    .long("pgnout")
+                    .help("the file where the games should be written as pgn"),
+            )
+            .arg(
+                Arg::new("event")
+                    .long("event")
+                    .default_value("Engine match")
+                    .help("the event name to put in each game's tags"),
+            ),
+    )
+    .subcommand(
+        Command::new("suite")
+            .about("Runs EPD test suites such as WAC, ECM and STS and reports how many positions were solved")
+            .arg(
+                Arg::new("movetime")
+                    .long("movetime")
+                    .value_parser(clap::value_parser!(u64))
+                    .help("the milliseconds to search each position for, 1000 if no limit is given"),
+            )
+            .arg(
+                Arg::new("nodes")
+                    .long("nodes")
+                    .value_parser(clap::value_parser!(u64))
+                    .help("the number of nodes to search in each position"),
+            )
+            .arg(
+                Arg::new("depth")
+                    .long("depth")
+                    .value_parser(clap::value_parser!(usize))
+                    .help("the depth to search each position to"),
+            )
+            .arg(
+                Arg::new("threads")
+                    .long("threads")
+                    .value_parser(clap::value_parser!(usize))
+                    .default_value("1")
+                    .help("the number of threads to search with"),
+            )
+            .arg(
+                Arg::new("hash")
+                    .long("hash")
+                    .value_parser(clap::value_parser!(usize))
+                    .help("the size of the transposition table in MB"),
+            )
+            .arg(
+                Arg::last(Arg::new("epd files"), true)
+                    .num_args(1..)
+                    .required(true)
+                    .help("epd files with bm or am operations, and optionally id operations"),
+            ),
+    )
+    .subcommand(
+        Command::new("puzzles")
+            .about("Finds positions in games where only one move wins or saves the game and writes them as EPD")
+            .arg(
+                Arg::new("output epd file")
+                    .required(true)
+                    .help("the file where the puzzles should be written"),
+            )
+            .arg(
+                Arg::new("depth")
+                    .long("depth")
+                    .value_parser(clap::value_parser!(usize))
+                    .help("the depth to search each position to, 10 if no limit is given"),
+            )
+            .arg(
+                Arg::new("nodes")
+                    .long("nodes")
+                    .value_parser(clap::value_parser!(u64))
+                    .help("the number of nodes to search in each position"),
+            )
+            .arg(
+                Arg::new("movetime")
+                    .long("movetime")
+                    .value_parser(clap::value_parser!(u64))
+                    .help("the milliseconds to search each position for"),
+            )
+            .arg(
+                Arg::new("max moves")
+                    .long("max-moves")
+                    .value_parser(clap::value_parser!(usize))
+                    .default_value("3")
+                    .help("the most moves a solution that doesn't end in mate may have"),
+            )
+            .arg(
+                Arg::new("skip plies")
+                    .long("skip-plies")
+                    .value_parser(clap::value_parser!(usize))
+                    .default_value("10")
+                    .help("the number of plies at the start of each game not to search"),
+            )
+            .arg(
+                Arg::last(Arg::new("game files"), true)
+                    .num_args(1..)
+                    .required(true)
+                    .help("pgn files, or sqlite databases created by the load subcommand"),
+            ),
+    )
 }
 
 fn handle_load_subcommand(sqlite_db: &str, files: Vec<&String>) -> Result<(), ()> {
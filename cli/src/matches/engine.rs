@@ -0,0 +1,537 @@
+use std::{
+    error::Error,
+    fmt::{Debug, Display},
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    process::{Child, ChildStdin, ChildStdout, Command, Stdio},
+    str::FromStr,
+};
+
+use chess_ai::{
+    EvalParams, EvalTerms, Limits, Score, SearchFeatures, Searcher, Skill, TaperedEvaluator,
+};
+use chess_core::{Board, SelectedMove};
+
+/// A player in a match, either a configuration of our own searcher or an
+/// external engine that speaks UCI.
+pub(crate) trait Engine {
+    fn name(&self) -> &str;
+
+    /// Gets ready to play a new game, forgetting anything learned in the
+    /// last one.
+    fn new_game(&mut self) -> Result<(), EngineErr>;
+
+    /// Chooses a move for the side to move in `position`.
+    fn choose_move(&mut self, position: &Position, limits: &Limits) -> Result<Thought, EngineErr>;
+}
+
+/// The position an engine is asked to move in, along with how the game got
+/// there for engines that need the history.
+pub(crate) struct Position<'a> {
+    pub(crate) start_fen: &'a str,
+    pub(crate) moves: &'a [SelectedMove],
+    pub(crate) board: &'a Board,
+}
+
+/// The move an engine chose, and its score if it reported one, from the
+/// perspective of the side that moved.
+pub(crate) struct Thought {
+    pub(crate) move_: SelectedMove,
+    pub(crate) score: Option<Score>,
+}
+
+/// How to create one of the engines in a match, parsed from a comma
+/// separated list of `key=value` settings.
+///
+/// Our own searcher accepts `name`, `params` (a parameter file), `hash` (in
+/// MB), `threads`, `skill` and `disable`, a `+` separated list of search
+/// features and evaluation terms to turn off, such as
+/// `disable=null_move_pruning+mobility`.
+///
+/// An external engine is given with `cmd`, the program to run, and accepts
+/// `name`, `arg` (repeatable) and `option.<Name>=<value>` for the UCI
+/// options to set.
+#[derive(Clone, Debug)]
+pub(crate) enum EngineSpec {
+    Internal {
+        name: String,
+        params: Option<String>,
+        hash: Option<usize>,
+        threads: Option<usize>,
+        skill: Option<u8>,
+        disabled: Vec<String>,
+    },
+    Uci {
+        name: Option<String>,
+        command: String,
+        args: Vec<String>,
+        options: Vec<(String, String)>,
+    },
+}
+
+impl FromStr for EngineSpec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut settings = Vec::new();
+        for setting in s.split(',').filter(|setting| !setting.trim().is_empty()) {
+            match setting.split_once('=') {
+                None => return Err(format!("'{setting}' should be written as key=value")),
+                Some((key, value)) => settings.push((key.trim(), value.trim())),
+            }
+        }
+
+        let name = settings
+            .iter()
+            .find(|(key, _)| *key == "name")
+            .map(|(_, value)| value.to_string());
+        if let Some((_, command)) = settings.iter().find(|(key, _)| *key == "cmd") {
+            let mut args = Vec::new();
+            let mut options = Vec::new();
+            for (key, value) in settings.iter() {
+                match *key {
+                    "name" | "cmd" => {}
+                    "arg" => args.push(value.to_string()),
+                    _ => match key.strip_prefix("option.") {
+                        Some(option) => options.push((option.to_string(), value.to_string())),
+                        None => return Err(format!("unknown setting '{key}' for a UCI engine")),
+                    },
+                }
+            }
+            return Ok(EngineSpec::Uci {
+                name,
+                command: command.to_string(),
+                args,
+                options,
+            });
+        }
+
+        let mut params = None;
+        let mut hash = None;
+        let mut threads = None;
+        let mut skill = None;
+        let mut disabled = Vec::new();
+        for (key, value) in settings {
+            match key {
+                "name" => {}
+                "params" => params = Some(value.to_string()),
+                "hash" => hash = Some(parse_value(key, value)?),
+                "threads" => threads = Some(parse_value(key, value)?),
+                "skill" => skill = Some(parse_value(key, value)?),
+                "disable" => {
+                    for switch in value.split('+') {
+                        let mut features = SearchFeatures::default();
+                        let mut terms = EvalTerms::default();
+                        if !disable(&mut features, &mut terms, switch) {
+                            return Err(format!("unknown feature or term '{switch}'"));
+                        }
+                        disabled.push(switch.to_string());
+                    }
+                }
+                _ => return Err(format!("unknown setting '{key}' for chess_ai")),
+            }
+        }
+
+        Ok(EngineSpec::Internal {
+            name: name.unwrap_or_else(|| "chess_ai".to_string()),
+            params,
+            hash,
+            threads,
+            skill,
+            disabled,
+        })
+    }
+}
+
+fn parse_value<T: FromStr>(key: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("'{value}' is not a valid value for {key}"))
+}
+
+impl EngineSpec {
+    pub(crate) fn create(&self) -> Result<Box<dyn Engine>, EngineErr> {
+        match self {
+            EngineSpec::Internal {
+                name,
+                params,
+                hash,
+                threads,
+                skill,
+                disabled,
+            } => {
+                let params = match params {
+                    None => EvalParams::default(),
+                    Some(path) => EvalParams::load(path)
+                        .map_err(|err| EngineErr::Setup(format!("failed to load {path}: {err}")))?,
+                };
+
+                let mut features = SearchFeatures::default();
+                let mut terms = EvalTerms::default();
+                for switch in disabled {
+                    disable(&mut features, &mut terms, switch);
+                }
+
+                let mut evaluator = TaperedEvaluator::new(params);
+                evaluator.set_terms(terms);
+                let mut searcher = Searcher::with_evaluator(evaluator);
+                searcher.set_features(features);
+                if let Some(hash) = hash {
+                    searcher.set_hash_size(*hash);
+                }
+                if let Some(threads) = threads {
+                    searcher.set_threads(*threads);
+                }
+
+                Ok(Box::new(InternalEngine {
+                    name: name.clone(),
+                    searcher,
+                    skill: skill.map(Skill::new),
+                    games: 0,
+                }))
+            }
+            EngineSpec::Uci {
+                name,
+                command,
+                args,
+                options,
+            } => Ok(Box::new(UciEngine::start(
+                name.clone(),
+                command,
+                args,
+                options,
+            )?)),
+        }
+    }
+}
+
+/// Turns off the search feature or evaluation term called `name`, returning
+/// whether there is one.
+fn disable(features: &mut SearchFeatures, terms: &mut EvalTerms, name: &str) -> bool {
+    let switch = match name {
+        "principal_variation_search" => &mut features.principal_variation_search,
+        "aspiration_windows" => &mut features.aspiration_windows,
+        "null_move_pruning" => &mut features.null_move_pruning,
+        "late_move_reductions" => &mut features.late_move_reductions,
+        "futility_pruning" => &mut features.futility_pruning,
+        "razoring" => &mut features.razoring,
+        "check_extensions" => &mut features.check_extensions,
+        "singular_extensions" => &mut features.singular_extensions,
+        "pawn_structure" => &mut terms.pawn_structure,
+        "pawn_shield" => &mut terms.pawn_shield,
+        "king_open_files" => &mut terms.king_open_files,
+        "king_attackers" => &mut terms.king_attackers,
+        "mobility" => &mut terms.mobility,
+        "bishop_pair" => &mut terms.bishop_pair,
+        "rook_files" => &mut terms.rook_files,
+        "rook_seventh_rank" => &mut terms.rook_seventh_rank,
+        "knight_outposts" => &mut terms.knight_outposts,
+        "endgames" => &mut terms.endgames,
+        _ => return false,
+    };
+    *switch = false;
+    true
+}
+
+struct InternalEngine {
+    name: String,
+    searcher: Searcher<TaperedEvaluator>,
+    skill: Option<Skill>,
+    games: u64,
+}
+
+impl Engine for InternalEngine {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn new_game(&mut self) -> Result<(), EngineErr> {
+        self.searcher.new_game();
+        // Reseed every game, or a weakened engine would make the same
+        // choices from the same opening each time.
+        self.games += 1;
+        self.searcher
+            .set_skill(self.skill.clone().map(|skill| skill.with_seed(self.games)));
+        Ok(())
+    }
+
+    fn choose_move(&mut self, position: &Position, limits: &Limits) -> Result<Thought, EngineErr> {
+        let mut board = position.board.clone();
+        let result = self.searcher.search(&mut board, limits.clone());
+        match result.best_move {
+            None => Err(EngineErr::Protocol(
+                "no move in a position with legal moves".to_string(),
+            )),
+            Some(move_) => Ok(Thought {
+                move_,
+                score: Some(result.score),
+            }),
+        }
+    }
+}
+
+/// An external engine run as a child process and spoken to over UCI.
+struct UciEngine {
+    name: String,
+    process: Child,
+    input: BufWriter<ChildStdin>,
+    output: BufReader<ChildStdout>,
+}
+
+impl UciEngine {
+    fn start(
+        name: Option<String>,
+        command: &str,
+        args: &[String],
+        options: &[(String, String)],
+    ) -> Result<Self, EngineErr> {
+        let mut process = Command::new(command)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|err| EngineErr::Setup(format!("failed to start {command}: {err}")))?;
+        let input = BufWriter::new(process.stdin.take().expect("stdin is piped"));
+        let output = BufReader::new(process.stdout.take().expect("stdout is piped"));
+
+        let mut engine = Self {
+            name: name.clone().unwrap_or_else(|| command.to_string()),
+            process,
+            input,
+            output,
+        };
+
+        engine.send("uci")?;
+        loop {
+            let line = engine.receive()?;
+            if line == "uciok" {
+                break;
+            }
+            if let (None, Some(id)) = (&name, line.strip_prefix("id name ")) {
+                engine.name = id.trim().to_string();
+            }
+        }
+        for (option, value) in options {
+            engine.send(&format!("setoption name {option} value {value}"))?;
+        }
+        engine.wait_until_ready()?;
+        Ok(engine)
+    }
+
+    fn send(&mut self, command: &str) -> Result<(), EngineErr> {
+        writeln!(self.input, "{command}")?;
+        self.input.flush()?;
+        Ok(())
+    }
+
+    fn receive(&mut self) -> Result<String, EngineErr> {
+        let mut line = String::new();
+        if self.output.read_line(&mut line)? == 0 {
+            return Err(EngineErr::Protocol("the engine exited".to_string()));
+        }
+        Ok(line.trim_end().to_string())
+    }
+
+    fn wait_until_ready(&mut self) -> Result<(), EngineErr> {
+        self.send("isready")?;
+        while self.receive()? != "readyok" {}
+        Ok(())
+    }
+}
+
+impl Engine for UciEngine {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn new_game(&mut self) -> Result<(), EngineErr> {
+        self.send("ucinewgame")?;
+        self.wait_until_ready()
+    }
+
+    fn choose_move(&mut self, position: &Position, limits: &Limits) -> Result<Thought, EngineErr> {
+        let mut command = format!("position fen {}", position.start_fen);
+        if !position.moves.is_empty() {
+            command.push_str(" moves");
+            for move_ in position.moves {
+                command.push(' ');
+                command.push_str(&move_.to_uci());
+            }
+        }
+        self.send(&command)?;
+        self.send(&go_command(limits))?;
+
+        let mut score = None;
+        loop {
+            let line = self.receive()?;
+            let mut words = line.split_whitespace();
+            match words.next() {
+                Some("info") => {
+                    if let Some(info_score) = parse_score(words) {
+                        score = Some(info_score);
+                    }
+                }
+                Some("bestmove") => {
+                    let uci = words.next().unwrap_or_default();
+                    return match position
+                        .board
+                        .possible_moves()
+                        .find(|move_| move_.to_uci() == uci)
+                    {
+                        None => Err(EngineErr::IllegalMove(uci.to_string())),
+                        Some(move_) => Ok(Thought { move_, score }),
+                    };
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+impl Drop for UciEngine {
+    fn drop(&mut self) {
+        if self.send("quit").is_err() || self.process.wait().is_err() {
+            self.process.kill().ok();
+        }
+    }
+}
+
+fn go_command(limits: &Limits) -> String {
+    let mut command = "go".to_string();
+    let mut add = |name: &str, value: Option<u128>| {
+        if let Some(value) = value {
+            command.push_str(&format!(" {name} {value}"));
+        }
+    };
+    add("wtime", limits.white_time.map(|time| time.as_millis()));
+    add("btime", limits.black_time.map(|time| time.as_millis()));
+    add("winc", limits.white_increment.map(|time| time.as_millis()));
+    add("binc", limits.black_increment.map(|time| time.as_millis()));
+    add("movetime", limits.move_time.map(|time| time.as_millis()));
+    add("depth", limits.depth.map(|depth| depth as u128));
+    add("nodes", limits.nodes.map(|nodes| nodes as u128));
+    command
+}
+
+/// Reads the score from the rest of an `info` line, if it has one. Bounds
+/// are ignored, since only the last score before the move is used.
+fn parse_score<'a>(mut words: impl Iterator<Item = &'a str>) -> Option<Score> {
+    while let Some(word) = words.next() {
+        if word == "score" {
+            let kind = words.next()?;
+            let value = words.next()?.parse().ok()?;
+            return match kind {
+                "cp" => Some(Score::Centipawns(value)),
+                "mate" => Some(Score::Mate(value)),
+                _ => None,
+            };
+        }
+    }
+    None
+}
+
+pub(crate) enum EngineErr {
+    /// The engine couldn't be created.
+    Setup(String),
+    Io(io::Error),
+    /// The engine said something that doesn't make sense.
+    Protocol(String),
+    IllegalMove(String),
+}
+
+impl From<io::Error> for EngineErr {
+    fn from(err: io::Error) -> Self {
+        EngineErr::Io(err)
+    }
+}
+
+impl Display for EngineErr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EngineErr::Setup(message) => write!(f, "{message}"),
+            EngineErr::Io(err) => write!(f, "failed to talk to the engine: {err}"),
+            EngineErr::Protocol(message) => write!(f, "{message}"),
+            EngineErr::IllegalMove(move_) => write!(f, "illegal move '{move_}'"),
+        }
+    }
+}
+
+impl Debug for EngineErr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Just defer to the Display impl
+        write!(f, "{self}")
+    }
+}
+
+impl Error for EngineErr {}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use chess_ai::{Limits, Score};
+
+    use super::{go_command, parse_score, EngineSpec};
+
+    #[test]
+    fn parses_engine_specs() {
+        match "name=new,hash=32,disable=null_move_pruning+mobility".parse() {
+            Ok(EngineSpec::Internal {
+                name,
+                hash,
+                disabled,
+                ..
+            }) => {
+                assert_eq!("new", name);
+                assert_eq!(Some(32), hash);
+                assert_eq!(vec!["null_move_pruning", "mobility"], disabled);
+            }
+            other => panic!("{other:?}"),
+        }
+
+        match "cmd=./engine,arg=--uci,option.Hash=64".parse() {
+            Ok(EngineSpec::Uci {
+                name,
+                command,
+                args,
+                options,
+            }) => {
+                assert_eq!(None, name);
+                assert_eq!("./engine", command);
+                assert_eq!(vec!["--uci"], args);
+                assert_eq!(vec![("Hash".to_string(), "64".to_string())], options);
+            }
+            other => panic!("{other:?}"),
+        }
+
+        assert!("disable=nothing".parse::<EngineSpec>().is_err());
+        assert!("hash".parse::<EngineSpec>().is_err());
+    }
+
+    #[test]
+    fn speaks_uci_limits_and_scores() {
+        let limits = Limits {
+            white_time: Some(Duration::from_secs(10)),
+            black_time: Some(Duration::from_millis(9500)),
+            white_increment: Some(Duration::from_millis(100)),
+            black_increment: Some(Duration::from_millis(100)),
+            ..Default::default()
+        };
+        assert_eq!(
+            "go wtime 10000 btime 9500 winc 100 binc 100",
+            go_command(&limits)
+        );
+        assert_eq!("go depth 6", go_command(&Limits::depth(6)));
+
+        let info = "depth 12 seldepth 18 score cp -35 nodes 1000 pv e2e4";
+        assert_eq!(
+            Some(Score::Centipawns(-35)),
+            parse_score(info.split_whitespace())
+        );
+        assert_eq!(
+            Some(Score::Mate(3)),
+            parse_score("score mate 3 pv a1a8".split_whitespace())
+        );
+        assert_eq!(None, parse_score("depth 3 nodes 10".split_whitespace()));
+    }
+}
@@ -0,0 +1,556 @@
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    str::FromStr,
+    time::{Duration, Instant},
+};
+
+use chess_ai::{Limits, Score};
+use chess_common::{Piece, PieceKind, Player};
+use chess_core::{Board, SelectedMove};
+use chess_parsers::GameResult;
+
+use super::engine::{Engine, Position};
+
+/// How much longer than its clock allows an engine may think before losing
+/// on time, to allow for the overhead of talking to it.
+const TIME_MARGIN: Duration = Duration::from_millis(50);
+
+/// Scores at least this large are treated as mates when adjudicating.
+const MATE_SCORE: i32 = 100_000;
+
+/// The position a game starts from: a FEN and the moves of the opening that
+/// were played from it.
+#[derive(Clone, Debug)]
+pub(crate) struct Opening {
+    pub(crate) fen: String,
+    pub(crate) moves: Vec<String>,
+}
+
+impl Default for Opening {
+    fn default() -> Self {
+        Self {
+            fen: Board::default().to_fen_string(),
+            moves: Vec::new(),
+        }
+    }
+}
+
+/// The limits each engine searches with. A clock is kept for each engine if
+/// `base` is set, and anything else set applies to every move.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct TimeControl {
+    pub(crate) base: Option<Duration>,
+    pub(crate) increment: Duration,
+    pub(crate) move_time: Option<Duration>,
+    pub(crate) depth: Option<usize>,
+    pub(crate) nodes: Option<u64>,
+}
+
+impl TimeControl {
+    pub(crate) fn is_limited(&self) -> bool {
+        self.base.is_some()
+            || self.move_time.is_some()
+            || self.depth.is_some()
+            || self.nodes.is_some()
+    }
+
+    /// Parses a clock such as `60+0.5`, in seconds, into `base` and
+    /// `increment`.
+    pub(crate) fn set_clock(&mut self, clock: &str) -> Result<(), String> {
+        let seconds = |text: &str| {
+            text.parse::<f64>()
+                .ok()
+                .filter(|seconds| seconds.is_finite() && *seconds >= 0.0)
+                .map(Duration::from_secs_f64)
+                .ok_or_else(|| format!("'{clock}' should be written as seconds+increment"))
+        };
+        let (base, increment) = clock.split_once('+').unwrap_or((clock, "0"));
+        self.base = Some(seconds(base)?);
+        self.increment = seconds(increment)?;
+        Ok(())
+    }
+
+    fn limits(&self, clocks: &[Duration; 2]) -> Limits {
+        let mut limits = Limits {
+            move_time: self.move_time,
+            depth: self.depth,
+            nodes: self.nodes,
+            ..Default::default()
+        };
+        if self.base.is_some() {
+            limits.white_time = Some(clocks[Player::White.as_index()]);
+            limits.black_time = Some(clocks[Player::Black.as_index()]);
+            limits.white_increment = Some(self.increment);
+            limits.black_increment = Some(self.increment);
+        }
+        limits
+    }
+}
+
+impl Display for TimeControl {
+    /// Writes the time control the way the PGN `TimeControl` tag expects,
+    /// or `-` if there is no clock.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (self.base, self.move_time) {
+            (Some(base), _) if self.increment.is_zero() => write!(f, "{}", base.as_secs_f64()),
+            (Some(base), _) => write!(f, "{}+{}", base.as_secs_f64(), self.increment.as_secs_f64()),
+            (None, Some(move_time)) => write!(f, "{}/move", move_time.as_secs_f64()),
+            (None, None) => write!(f, "-"),
+        }
+    }
+}
+
+/// When to end a game early from the scores the engines report.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Adjudication {
+    /// A side loses once it has scored itself at least this many centipawns
+    /// down for `resign_moves` moves in a row, and its opponent has scored
+    /// itself as many up for as long.
+    pub(crate) resign_score: Option<i32>,
+    pub(crate) resign_moves: usize,
+    /// The game is drawn once both sides score it within this many
+    /// centipawns of equal for `draw_moves` moves each in a row, from move
+    /// `draw_after` on.
+    pub(crate) draw_score: Option<i32>,
+    pub(crate) draw_moves: usize,
+    pub(crate) draw_after: usize,
+    /// The game is drawn after this many moves by each side.
+    pub(crate) max_moves: Option<usize>,
+}
+
+/// Why a game ended.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) enum Termination {
+    Checkmate,
+    Stalemate,
+    Repetition,
+    FiftyMoves,
+    InsufficientMaterial,
+    TimeForfeit,
+    /// The engine to move played an illegal move, crashed or otherwise
+    /// failed to come up with a move.
+    EngineFailure(String),
+    Resignation,
+    DrawAdjudication,
+    MaxMoves,
+}
+
+impl Termination {
+    /// The value of the PGN `Termination` tag.
+    pub(crate) fn pgn_tag(&self) -> &'static str {
+        match self {
+            Termination::Checkmate
+            | Termination::Stalemate
+            | Termination::Repetition
+            | Termination::FiftyMoves
+            | Termination::InsufficientMaterial => "normal",
+            Termination::TimeForfeit => "time forfeit",
+            Termination::EngineFailure(_) => "rules infraction",
+            Termination::Resignation | Termination::DrawAdjudication | Termination::MaxMoves => {
+                "adjudication"
+            }
+        }
+    }
+}
+
+impl Display for Termination {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Termination::Checkmate => write!(f, "checkmate"),
+            Termination::Stalemate => write!(f, "stalemate"),
+            Termination::Repetition => write!(f, "threefold repetition"),
+            Termination::FiftyMoves => write!(f, "fifty move rule"),
+            Termination::InsufficientMaterial => write!(f, "insufficient material"),
+            Termination::TimeForfeit => write!(f, "loss on time"),
+            Termination::EngineFailure(reason) => write!(f, "engine failure: {reason}"),
+            Termination::Resignation => write!(f, "adjudicated as a win"),
+            Termination::DrawAdjudication => write!(f, "adjudicated as a draw"),
+            Termination::MaxMoves => write!(f, "move limit"),
+        }
+    }
+}
+
+/// A finished game.
+pub(crate) struct PlayedGame {
+    pub(crate) board: Board,
+    pub(crate) result: GameResult,
+    pub(crate) termination: Termination,
+}
+
+/// Plays a game between `white` and `black` from `opening`.
+pub(crate) fn play_game(
+    white: &mut dyn Engine,
+    black: &mut dyn Engine,
+    opening: &Opening,
+    time_control: &TimeControl,
+    adjudication: &Adjudication,
+) -> Result<PlayedGame, String> {
+    let mut game = Game::new(opening)?;
+
+    for engine in [&mut *white as &mut dyn Engine, &mut *black] {
+        if let Err(err) = engine.new_game() {
+            return Err(format!("{} failed to start a game: {err}", engine.name()));
+        }
+    }
+
+    let base = time_control.base.unwrap_or_default();
+    let mut clocks = [base, base];
+    let mut losing = [0, 0];
+    let mut winning = [0, 0];
+    let mut drawish = 0;
+    let opening_plies = game.moves.len();
+
+    loop {
+        if let Some((result, termination)) = game.outcome() {
+            return Ok(game.finish(result, termination));
+        }
+
+        let mover = game.board.player_to_move();
+        let moves_played = (game.moves.len() - opening_plies) / 2;
+        if adjudication
+            .max_moves
+            .is_some_and(|max_moves| moves_played >= max_moves)
+        {
+            return Ok(game.finish(GameResult::Draw, Termination::MaxMoves));
+        }
+
+        let engine: &mut dyn Engine = match mover {
+            Player::White => &mut *white,
+            Player::Black => &mut *black,
+        };
+        let position = Position {
+            start_fen: &opening.fen,
+            moves: &game.moves,
+            board: &game.board,
+        };
+        let started = Instant::now();
+        let thought = match engine.choose_move(&position, &time_control.limits(&clocks)) {
+            Err(err) => {
+                return Ok(game.finish(loss_for(mover), Termination::EngineFailure(err.to_string())))
+            }
+            Ok(thought) => thought,
+        };
+
+        if time_control.base.is_some() {
+            let clock = &mut clocks[mover.as_index()];
+            let elapsed = started.elapsed();
+            if elapsed > *clock + TIME_MARGIN {
+                return Ok(game.finish(loss_for(mover), Termination::TimeForfeit));
+            }
+            *clock = clock.saturating_sub(elapsed) + time_control.increment;
+        }
+        game.play(thought.move_);
+
+        // Resigning needs both engines to agree that one side is lost.
+        let score = thought.score.map(centipawns);
+        if let Some(resign_score) = adjudication.resign_score {
+            let own = mover.as_index();
+            losing[own] = match score {
+                Some(score) if score <= -resign_score => losing[own] + 1,
+                _ => 0,
+            };
+            winning[own] = match score {
+                Some(score) if score >= resign_score => winning[own] + 1,
+                _ => 0,
+            };
+
+            let other = mover.other_player().as_index();
+            for (loser, winner) in [(own, other), (other, own)] {
+                if losing[loser] >= adjudication.resign_moves
+                    && winning[winner] >= adjudication.resign_moves
+                {
+                    let loser = Player::try_from(loser).expect("a player index");
+                    return Ok(game.finish(loss_for(loser), Termination::Resignation));
+                }
+            }
+        }
+
+        if let Some(draw_score) = adjudication.draw_score {
+            let in_range = score.is_some_and(|score| score.abs() <= draw_score);
+            if in_range && game.move_number() >= adjudication.draw_after {
+                drawish += 1;
+            } else {
+                drawish = 0;
+            }
+            if drawish >= 2 * adjudication.draw_moves {
+                return Ok(game.finish(GameResult::Draw, Termination::DrawAdjudication));
+            }
+        }
+    }
+}
+
+fn loss_for(player: Player) -> GameResult {
+    match player {
+        Player::White => GameResult::BlackWin,
+        Player::Black => GameResult::WhiteWin,
+    }
+}
+
+fn centipawns(score: Score) -> i32 {
+    match score {
+        Score::Centipawns(centipawns) => centipawns,
+        Score::Mate(moves) if moves > 0 => MATE_SCORE,
+        Score::Mate(_) => -MATE_SCORE,
+    }
+}
+
+/// The state of a game in progress, with what's needed to apply the draw
+/// rules that the board doesn't know about.
+struct Game {
+    board: Board,
+    moves: Vec<SelectedMove>,
+    /// How often each position has occurred, keyed by the parts of its FEN
+    /// that matter for repetitions.
+    positions: HashMap<String, u32>,
+    /// The number of plies since the last capture or pawn move.
+    fifty_move_clock: u32,
+    /// The move number of the first move, and whether black played it.
+    first_move: usize,
+    black_first: bool,
+}
+
+impl Game {
+    fn new(opening: &Opening) -> Result<Self, String> {
+        let board = Board::from_str(&opening.fen)
+            .map_err(|_| format!("the opening '{}' has an invalid FEN", opening.fen))?;
+        let fields = opening.fen.split_whitespace().collect::<Vec<_>>();
+        let counter = |index: usize, default| {
+            fields
+                .get(index)
+                .and_then(|counter| counter.parse().ok())
+                .unwrap_or(default)
+        };
+
+        let black_first = board.player_to_move() == Player::Black;
+        let mut game = Self {
+            board,
+            moves: Vec::new(),
+            positions: HashMap::new(),
+            fifty_move_clock: counter(4, 0) as u32,
+            first_move: counter(5, 1).max(1),
+            black_first,
+        };
+        game.record_position();
+
+        for move_ in opening.moves.iter() {
+            let selected = {
+                let mut board = game.board.clone();
+                board
+                    .make_move_acn(move_)
+                    .map_err(|err| format!("the opening move {move_} is illegal: {err:?}"))?
+            };
+            game.play(selected);
+        }
+        Ok(game)
+    }
+
+    fn play(&mut self, move_: SelectedMove) {
+        let is_pawn_move = self
+            .board
+            .at(move_.move_().from())
+            .is_some_and(|piece| piece.kind() == PieceKind::Pawn);
+        let is_capture = self.board.at(move_.move_().to()).is_some();
+        if is_pawn_move || is_capture {
+            self.fifty_move_clock = 0;
+        } else {
+            self.fifty_move_clock += 1;
+        }
+
+        self.board
+            .make_move(move_.clone())
+            .expect("engines' moves to be checked for legality");
+        self.moves.push(move_);
+        self.record_position();
+    }
+
+    /// The number of the move being played now.
+    fn move_number(&self) -> usize {
+        self.first_move + (self.moves.len() + self.black_first as usize) / 2
+    }
+
+    fn record_position(&mut self) {
+        let fen = self.board.to_fen_string();
+        let key = fen.split_whitespace().take(4).collect::<Vec<_>>().join(" ");
+        *self.positions.entry(key).or_default() += 1;
+    }
+
+    /// Gets the result if the game is over by the rules.
+    fn outcome(&self) -> Option<(GameResult, Termination)> {
+        if self.board.possible_moves().next().is_none() {
+            return Some(if self.board.is_check() {
+                (
+                    loss_for(self.board.player_to_move()),
+                    Termination::Checkmate,
+                )
+            } else {
+                (GameResult::Draw, Termination::Stalemate)
+            });
+        }
+
+        let termination = if self.positions.values().any(|count| *count >= 3) {
+            Termination::Repetition
+        } else if self.fifty_move_clock >= 100 {
+            Termination::FiftyMoves
+        } else if is_insufficient_material(&self.board) {
+            Termination::InsufficientMaterial
+        } else {
+            return None;
+        };
+        Some((GameResult::Draw, termination))
+    }
+
+    fn finish(self, result: GameResult, termination: Termination) -> PlayedGame {
+        PlayedGame {
+            board: self.board,
+            result,
+            termination,
+        }
+    }
+}
+
+/// Gets whether neither side can possibly mate: kings with at most one
+/// minor piece between them, or only bishops that are all on squares of the
+/// same colour.
+fn is_insufficient_material(board: &Board) -> bool {
+    let pieces = |kind| {
+        board.bitboard_for(&Piece::new(Player::White, kind))
+            | board.bitboard_for(&Piece::new(Player::Black, kind))
+    };
+    if [PieceKind::Pawn, PieceKind::Rook, PieceKind::Queen]
+        .into_iter()
+        .any(|kind| pieces(kind) != 0)
+    {
+        return false;
+    }
+
+    const LIGHT_SQUARES: u64 = 0x55AA_55AA_55AA_55AA;
+    let knights = pieces(PieceKind::Knight);
+    let bishops = pieces(PieceKind::Bishop);
+    (knights | bishops).count_ones() <= 1
+        || (knights == 0 && (bishops & LIGHT_SQUARES == 0 || bishops & !LIGHT_SQUARES == 0))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use chess_ai::Limits;
+    use chess_core::Board;
+    use chess_parsers::GameResult;
+
+    use super::{
+        is_insufficient_material, play_game, Adjudication, Opening, Termination, TimeControl,
+    };
+    use crate::matches::engine::{Engine, EngineErr, Position, Thought};
+
+    /// Plays the first legal move, which makes games easy to predict.
+    struct FirstMove;
+
+    impl Engine for FirstMove {
+        fn name(&self) -> &str {
+            "first move"
+        }
+
+        fn new_game(&mut self) -> Result<(), EngineErr> {
+            Ok(())
+        }
+
+        fn choose_move(&mut self, position: &Position, _: &Limits) -> Result<Thought, EngineErr> {
+            Ok(Thought {
+                move_: position.board.possible_moves().next().unwrap(),
+                score: None,
+            })
+        }
+    }
+
+    fn opening(fen: &str, moves: &[&str]) -> Opening {
+        Opening {
+            fen: fen.to_string(),
+            moves: moves.iter().map(|move_| move_.to_string()).collect(),
+        }
+    }
+
+    fn play(opening: &Opening, adjudication: &Adjudication) -> (GameResult, Termination) {
+        let game = play_game(
+            &mut FirstMove,
+            &mut FirstMove,
+            opening,
+            &TimeControl::default(),
+            adjudication,
+        )
+        .unwrap();
+        (game.result, game.termination)
+    }
+
+    #[test]
+    fn applies_the_rules_of_the_game() {
+        let mate = opening("k7/8/1K6/8/8/8/8/7R w - - 0 1", &["Rh8#"]);
+        let (result, termination) = play(&mate, &Adjudication::default());
+        assert!(matches!(result, GameResult::WhiteWin));
+        assert_eq!(Termination::Checkmate, termination);
+
+        // Kings shuffling back and forth repeat the position.
+        let kings = opening("k7/8/8/8/8/8/8/K6R w - - 0 1", &[]);
+        let (result, termination) = play(&kings, &Adjudication::default());
+        assert!(matches!(result, GameResult::Draw));
+        assert!(
+            matches!(
+                termination,
+                Termination::Repetition | Termination::FiftyMoves
+            ),
+            "{termination:?}"
+        );
+
+        let fifty = opening("k7/8/8/8/8/8/8/K6R w - - 99 80", &["Rh2"]);
+        let (_, termination) = play(&fifty, &Adjudication::default());
+        assert_eq!(Termination::FiftyMoves, termination);
+    }
+
+    #[test]
+    fn adjudicates_long_games() {
+        let adjudication = Adjudication {
+            max_moves: Some(2),
+            ..Default::default()
+        };
+        let (result, termination) = play(&Opening::default(), &adjudication);
+        assert!(matches!(result, GameResult::Draw));
+        assert_eq!(Termination::MaxMoves, termination);
+    }
+
+    #[test]
+    fn rejects_illegal_openings() {
+        let illegal = opening(&Opening::default().fen, &["e5"]);
+        assert!(play_game(
+            &mut FirstMove,
+            &mut FirstMove,
+            &illegal,
+            &TimeControl::default(),
+            &Adjudication::default(),
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn knows_insufficient_material() {
+        let insufficient = |fen| is_insufficient_material(&Board::from_str(fen).unwrap());
+        assert!(insufficient("k7/8/8/8/8/8/8/K7 w - - 0 1"));
+        assert!(insufficient("k7/8/8/8/8/8/8/K5N1 w - - 0 1"));
+        // Bishops on e5 and g1 are both on dark squares.
+        assert!(insufficient("k7/8/8/4b3/8/8/8/K5B1 w - - 0 1"));
+        assert!(!insufficient("k7/8/8/3b4/8/8/8/K5B1 w - - 0 1"));
+        assert!(!insufficient("k7/8/8/8/8/8/8/K4NN1 w - - 0 1"));
+        assert!(!insufficient("k7/p7/8/8/8/8/8/K7 w - - 0 1"));
+    }
+
+    #[test]
+    fn writes_pgn_time_controls() {
+        let mut time_control = TimeControl::default();
+        assert_eq!("-", time_control.to_string());
+        time_control.set_clock("60+0.5").unwrap();
+        assert_eq!("60+0.5", time_control.to_string());
+        time_control.set_clock("10").unwrap();
+        assert_eq!("10", time_control.to_string());
+        assert!(time_control.set_clock("ten").is_err());
+    }
+}
@@ -0,0 +1,471 @@
+mod engine;
+mod game;
+mod stats;
+
+use std::{
+    fs::{self, File},
+    io::Write,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        mpsc::{self, Sender},
+    },
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use chess_core::Board;
+use chess_parsers::{GameResult, ParsedGame};
+use clap::ArgMatches;
+use engine::EngineSpec;
+use game::{play_game, Adjudication, Opening, PlayedGame, TimeControl};
+use iso_8859_1_encoder::Iso8859String;
+use log::{error, info, warn};
+use stats::{MatchScore, Sprt, SprtStatus};
+
+use crate::shared::{read_pgn_games, tag};
+
+/// Everything needed to run a match, read from the `match` subcommand's
+/// arguments.
+struct MatchSettings {
+    engines: [EngineSpec; 2],
+    openings: Vec<Opening>,
+    games: usize,
+    concurrency: usize,
+    time_control: TimeControl,
+    adjudication: Adjudication,
+    sprt: Option<Sprt>,
+    pgn_output: Option<String>,
+    event: String,
+}
+
+/// A game one of the workers finished, or the reason it couldn't be played.
+struct FinishedGame {
+    index: usize,
+    names: [String; 2],
+    /// Whether the first engine had white.
+    first_is_white: bool,
+    opening: Opening,
+    game: Result<PlayedGame, String>,
+}
+
+/// Plays a match between the two engines given to the `match` subcommand,
+/// printing the score and Elo difference as it goes and writing the games
+/// as PGN. Each opening is played twice, with each engine getting white
+/// once. If an SPRT is given, the match stops as soon as it passes or
+/// fails.
+pub(crate) fn handle_match_subcommand(args: &ArgMatches) -> Result<(), ()> {
+    let settings = MatchSettings::from_args(args)?;
+
+    let mut pgn_output = match &settings.pgn_output {
+        None => None,
+        Some(file_name) => match File::create(file_name) {
+            Err(err) => {
+                error!("Failed to create {file_name}. Inner error: {err}");
+                return Err(());
+            }
+            Ok(file) => Some(file),
+        },
+    };
+
+    let next_game = AtomicUsize::new(0);
+    let stop = AtomicBool::new(false);
+    let (sender, receiver) = mpsc::channel();
+    let mut score = MatchScore::default();
+    let mut names = None;
+    let mut failed = false;
+
+    thread::scope(|scope| {
+        for _ in 0..settings.concurrency {
+            let sender = sender.clone();
+            let (settings, next_game, stop) = (&settings, &next_game, &stop);
+            scope.spawn(move || play_games(settings, next_game, stop, sender));
+        }
+        drop(sender);
+
+        for finished in receiver {
+            let played = match finished.game {
+                Err(err) => {
+                    error!("Game {} couldn't be played: {err}", finished.index + 1);
+                    stop.store(true, Ordering::Relaxed);
+                    failed = true;
+                    continue;
+                }
+                Ok(played) => played,
+            };
+
+            let [first, second] = &finished.names;
+            let (white, black) = if finished.first_is_white {
+                (first, second)
+            } else {
+                (second, first)
+            };
+            println!(
+                "Finished game {} ({white} vs {black}): {} {{{}}}",
+                finished.index + 1,
+                played.result.as_ref(),
+                played.termination
+            );
+
+            match (&played.result, finished.first_is_white) {
+                (GameResult::WhiteWin, true) | (GameResult::BlackWin, false) => score.wins += 1,
+                (GameResult::WhiteWin, false) | (GameResult::BlackWin, true) => score.losses += 1,
+                _ => score.draws += 1,
+            }
+            println!("Score of {first} vs {second}: {score}");
+            names.get_or_insert_with(|| finished.names.clone());
+
+            if let Some(output) = pgn_output.as_mut() {
+                let pgn = game_to_pgn(
+                    &settings,
+                    &finished.opening,
+                    finished.index,
+                    white,
+                    black,
+                    played,
+                );
+                if let Err(err) = output.write_all(pgn.as_bytes()) {
+                    error!("Failed to write a game. Inner error: {err}");
+                    stop.store(true, Ordering::Relaxed);
+                    failed = true;
+                }
+            }
+
+            if settings
+                .sprt
+                .is_some_and(|sprt| sprt.status(&score) != SprtStatus::Continue)
+            {
+                stop.store(true, Ordering::Relaxed);
+            }
+        }
+    });
+
+    if let Some([first, second]) = names {
+        print_summary(&settings, &first, &second, &score);
+    }
+    if failed {
+        Err(())
+    } else {
+        Ok(())
+    }
+}
+
+/// Plays games with a pair of engines of its own until every game has been
+/// started or the match is stopped.
+fn play_games(
+    settings: &MatchSettings,
+    next_game: &AtomicUsize,
+    stop: &AtomicBool,
+    sender: Sender<FinishedGame>,
+) {
+    let engines = settings
+        .engines
+        .iter()
+        .map(|spec| spec.create())
+        .collect::<Result<Vec<_>, _>>();
+    let mut engines = match engines {
+        Err(err) => {
+            let index = next_game.load(Ordering::Relaxed);
+            sender
+                .send(FinishedGame {
+                    index,
+                    names: Default::default(),
+                    first_is_white: true,
+                    opening: Opening::default(),
+                    game: Err(err.to_string()),
+                })
+                .ok();
+            return;
+        }
+        Ok(engines) => engines,
+    };
+
+    while !stop.load(Ordering::Relaxed) {
+        let index = next_game.fetch_add(1, Ordering::Relaxed);
+        if index >= settings.games {
+            break;
+        }
+
+        let opening = settings.openings[index / 2 % settings.openings.len()].clone();
+        let first_is_white = index.is_multiple_of(2);
+        let names = [engines[0].name().to_string(), engines[1].name().to_string()];
+        let (first, second) = engines.split_at_mut(1);
+        let (white, black) = if first_is_white {
+            (&mut first[0], &mut second[0])
+        } else {
+            (&mut second[0], &mut first[0])
+        };
+
+        let game = play_game(
+            white.as_mut(),
+            black.as_mut(),
+            &opening,
+            &settings.time_control,
+            &settings.adjudication,
+        );
+        let finished = FinishedGame {
+            index,
+            names,
+            first_is_white,
+            opening,
+            game,
+        };
+        if sender.send(finished).is_err() {
+            break;
+        }
+    }
+}
+
+fn game_to_pgn(
+    settings: &MatchSettings,
+    opening: &Opening,
+    index: usize,
+    white: &str,
+    black: &str,
+    played: PlayedGame,
+) -> Iso8859String {
+    let moves = played.board.get_move_history_acn();
+    let mut tags = vec![
+        ("Event".to_string(), settings.event.clone()),
+        ("Site".to_string(), "?".to_string()),
+        ("Date".to_string(), today()),
+        ("Round".to_string(), (index + 1).to_string()),
+        ("White".to_string(), white.to_string()),
+        ("Black".to_string(), black.to_string()),
+        ("Result".to_string(), played.result.as_ref().to_string()),
+    ];
+    if opening.fen != Board::default().to_fen_string() {
+        tags.push(("SetUp".to_string(), "1".to_string()));
+        tags.push(("FEN".to_string(), opening.fen.clone()));
+    }
+    tags.push(("TimeControl".to_string(), settings.time_control.to_string()));
+    tags.push(("PlyCount".to_string(), moves.len().to_string()));
+    tags.push((
+        "Termination".to_string(),
+        played.termination.pgn_tag().to_string(),
+    ));
+
+    // Engine names that can't be written in ISO 8859-1 lose the characters
+    // that can't be, since the game itself is still worth keeping.
+    let tags = tags
+        .into_iter()
+        .map(|(name, value)| (name, value.replace(|c: char| c as u32 > 0xFF, "?")))
+        .collect();
+    let game = ParsedGame::new(tags, moves, played.result).expect("the tags to be ISO 8859-1");
+    let pgn: Iso8859String = (&game).into();
+    let mut bytes = pgn.as_bytes().to_vec();
+    bytes.extend_from_slice(b"\n\n");
+    Iso8859String::from_bytes(bytes)
+}
+
+fn print_summary(settings: &MatchSettings, first: &str, second: &str, score: &MatchScore) {
+    println!();
+    println!("Score of {first} vs {second}: {score}");
+    match score.elo() {
+        None => println!("Elo difference: unknown"),
+        Some(elo) => println!("Elo difference: {elo}"),
+    }
+    if let Some(sprt) = settings.sprt {
+        let (lower, upper) = sprt.bounds();
+        let verdict = match sprt.status(score) {
+            SprtStatus::Continue => "inconclusive",
+            SprtStatus::AcceptH1 => "H1 accepted, passed",
+            SprtStatus::AcceptH0 => "H0 accepted, failed",
+        };
+        println!(
+            "SPRT: llr {:.2} ({lower:.2}, {upper:.2}), elo0 {}, elo1 {}: {verdict}",
+            sprt.llr(score),
+            sprt.elo0,
+            sprt.elo1
+        );
+    }
+}
+
+impl MatchSettings {
+    fn from_args(args: &ArgMatches) -> Result<Self, ()> {
+        let spec = |name: &str| {
+            let spec = args
+                .get_one::<String>(name)
+                .unwrap_or_else(|| panic!("'{name}' is required"));
+            spec.parse::<EngineSpec>().map_err(|err| {
+                error!("Invalid {name} '{spec}': {err}");
+            })
+        };
+        let engines = [spec("engine1")?, spec("engine2")?];
+
+        let openings = match args.get_one::<String>("openings") {
+            None => vec![Opening::default()],
+            Some(file_name) => load_openings(file_name, args.get_one::<usize>("plies").copied())?,
+        };
+
+        let mut time_control = TimeControl {
+            move_time: args
+                .get_one::<u64>("movetime")
+                .map(|millis| Duration::from_millis(*millis)),
+            depth: args.get_one::<usize>("depth").copied(),
+            nodes: args.get_one::<u64>("nodes").copied(),
+            ..Default::default()
+        };
+        if let Some(clock) = args.get_one::<String>("tc") {
+            if let Err(err) = time_control.set_clock(clock) {
+                error!("Invalid time control: {err}");
+                return Err(());
+            }
+        }
+        if !time_control.is_limited() {
+            error!("Set a time control with --tc, --movetime, --depth or --nodes");
+            return Err(());
+        }
+
+        let count = |name: &str| *args.get_one::<usize>(name).expect("it has a default");
+        let adjudication = Adjudication {
+            resign_score: args.get_one::<i32>("resign score").copied(),
+            resign_moves: count("resign moves"),
+            draw_score: args.get_one::<i32>("draw score").copied(),
+            draw_moves: count("draw moves"),
+            draw_after: count("draw after"),
+            max_moves: args.get_one::<usize>("max moves").copied(),
+        };
+
+        let sprt = match args.get_one::<String>("sprt") {
+            None => None,
+            Some(bounds) => {
+                let elos = bounds
+                    .split_once(',')
+                    .and_then(|(elo0, elo1)| Some((elo0.parse().ok()?, elo1.parse().ok()?)));
+                let (elo0, elo1) = match elos {
+                    Some((elo0, elo1)) if elo0 < elo1 => (elo0, elo1),
+                    _ => {
+                        error!(
+                            "Invalid SPRT bounds '{bounds}', expected elo0,elo1 with elo0 < elo1"
+                        );
+                        return Err(());
+                    }
+                };
+                Some(Sprt {
+                    elo0,
+                    elo1,
+                    alpha: *args.get_one::<f64>("alpha").expect("it has a default"),
+                    beta: *args.get_one::<f64>("beta").expect("it has a default"),
+                })
+            }
+        };
+
+        Ok(Self {
+            engines,
+            openings,
+            games: count("games"),
+            concurrency: count("concurrency").max(1),
+            time_control,
+            adjudication,
+            sprt,
+            pgn_output: args.get_one::<String>("pgn output").cloned(),
+            event: args
+                .get_one::<String>("event")
+                .expect("it has a default")
+                .clone(),
+        })
+    }
+}
+
+/// Reads the openings from `file_name`. Files ending in `.pgn` are read as
+/// games, with at most `plies` of each game's moves played, files ending in
+/// `.epd` are read as EPD, and anything else is read as one FEN per line.
+fn load_openings(file_name: &str, plies: Option<usize>) -> Result<Vec<Opening>, ()> {
+    let mut openings = Vec::new();
+    if file_name.ends_with(".pgn") {
+        for game in read_pgn_games(file_name)? {
+            let fen = match tag(&game, "FEN") {
+                fen if fen == "?" => Board::default().to_fen_string(),
+                fen => fen,
+            };
+            openings.push(Opening {
+                fen,
+                moves: game
+                    .moves
+                    .iter()
+                    .take(plies.unwrap_or(usize::MAX))
+                    .map(|move_| move_.to_string())
+                    .collect(),
+            });
+        }
+    } else {
+        let bytes = match fs::read(file_name) {
+            Err(err) => {
+                error!("Failed to read {file_name}. Inner error: {err}");
+                return Err(());
+            }
+            Ok(bytes) => bytes,
+        };
+        let text = String::from_utf8_lossy(&bytes);
+        for (line_number, line) in text.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let fen = if file_name.ends_with(".epd") {
+                match chess_parsers::parse_epd(line) {
+                    Err(err) => {
+                        warn!("Skipping {file_name}:{}: {err}", line_number + 1);
+                        continue;
+                    }
+                    Ok(epd) => epd.fen,
+                }
+            } else {
+                line.trim().to_string()
+            };
+            openings.push(Opening {
+                fen,
+                moves: Vec::new(),
+            });
+        }
+    }
+
+    if openings.is_empty() {
+        error!("No openings in {file_name}");
+        return Err(());
+    }
+    info!("Loaded {} openings from {file_name}", openings.len());
+    Ok(openings)
+}
+
+/// Gets today's date in UTC the way the PGN `Date` tag expects.
+fn today() -> String {
+    let days = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|since| since.as_secs() / 86_400)
+        .unwrap_or_default() as i64;
+    let (year, month, day) = civil_from_days(days);
+    format!("{year:04}.{month:02}.{day:02}")
+}
+
+/// Converts a number of days since 1970-01-01 into a year, month and day of
+/// the Gregorian calendar.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    } as u32;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::civil_from_days;
+
+    #[test]
+    fn converts_days_to_dates() {
+        assert_eq!((1970, 1, 1), civil_from_days(0));
+        assert_eq!((2000, 2, 29), civil_from_days(11_016));
+        assert_eq!((2024, 12, 31), civil_from_days(20_088));
+    }
+}
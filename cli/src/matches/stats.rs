@@ -0,0 +1,199 @@
+use std::fmt::Display;
+
+/// The results of a match so far, from the first engine's point of view.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub(crate) struct MatchScore {
+    pub(crate) wins: u32,
+    pub(crate) losses: u32,
+    pub(crate) draws: u32,
+}
+
+impl MatchScore {
+    pub(crate) fn games(&self) -> u32 {
+        self.wins + self.losses + self.draws
+    }
+
+    /// The fraction of the points the first engine scored, counting a draw
+    /// as half a point.
+    pub(crate) fn score(&self) -> f64 {
+        (self.wins as f64 + self.draws as f64 / 2.0) / self.games() as f64
+    }
+
+    /// The variance of the score of a single game.
+    fn variance(&self) -> f64 {
+        let games = self.games() as f64;
+        let score = self.score();
+        (self.wins as f64 * (1.0 - score).powi(2)
+            + self.draws as f64 * (0.5 - score).powi(2)
+            + self.losses as f64 * score.powi(2))
+            / games
+    }
+
+    /// Estimates the Elo difference between the engines, with the margin
+    /// of a 95% confidence interval. There's no estimate until both engines
+    /// have scored at least some points, since a perfect score means an
+    /// infinite difference.
+    pub(crate) fn elo(&self) -> Option<EloEstimate> {
+        let score = self.score();
+        if self.games() == 0 || score <= 0.0 || score >= 1.0 {
+            return None;
+        }
+
+        let margin = 1.96 * (self.variance() / self.games() as f64).sqrt();
+        let low = elo_from_score((score - margin).max(f64::EPSILON));
+        let high = elo_from_score((score + margin).min(1.0 - f64::EPSILON));
+        Some(EloEstimate {
+            elo: elo_from_score(score),
+            margin: (high - low) / 2.0,
+        })
+    }
+}
+
+impl Display for MatchScore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} - {} - {}", self.wins, self.losses, self.draws)?;
+        if self.games() > 0 {
+            write!(f, " [{:.3}]", self.score())?;
+        }
+        write!(f, " {}", self.games())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct EloEstimate {
+    pub(crate) elo: f64,
+    pub(crate) margin: f64,
+}
+
+impl Display for EloEstimate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:.1} +/- {:.1}", self.elo, self.margin)
+    }
+}
+
+fn elo_from_score(score: f64) -> f64 {
+    400.0 * (score / (1.0 - score)).log10()
+}
+
+fn score_from_elo(elo: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-elo / 400.0))
+}
+
+/// A sequential probability ratio test of whether the first engine is
+/// `elo1` stronger than the second (H1) rather than only `elo0` (H0), with
+/// `alpha` the chance of wrongly accepting H1 and `beta` the chance of
+/// wrongly accepting H0.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct Sprt {
+    pub(crate) elo0: f64,
+    pub(crate) elo1: f64,
+    pub(crate) alpha: f64,
+    pub(crate) beta: f64,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum SprtStatus {
+    /// The test needs more games.
+    Continue,
+    /// The first engine is at least `elo1` stronger, so the test passed.
+    AcceptH1,
+    /// The first engine is at most `elo0` stronger, so the test failed.
+    AcceptH0,
+}
+
+impl Sprt {
+    /// The log-likelihood ratio of H1 against H0, using a normal
+    /// approximation of the trinomial distribution of game results. It is
+    /// 0 while every game has had the same result, since the approximation
+    /// needs the results to vary.
+    pub(crate) fn llr(&self, score: &MatchScore) -> f64 {
+        if score.games() == 0 || score.variance() <= 0.0 {
+            return 0.0;
+        }
+
+        let score0 = score_from_elo(self.elo0);
+        let score1 = score_from_elo(self.elo1);
+        (score1 - score0) * (2.0 * score.score() - score0 - score1) * score.games() as f64
+            / (2.0 * score.variance())
+    }
+
+    /// The log-likelihood ratios at which H0 and H1 are accepted.
+    pub(crate) fn bounds(&self) -> (f64, f64) {
+        (
+            (self.beta / (1.0 - self.alpha)).ln(),
+            ((1.0 - self.beta) / self.alpha).ln(),
+        )
+    }
+
+    pub(crate) fn status(&self, score: &MatchScore) -> SprtStatus {
+        let llr = self.llr(score);
+        let (lower, upper) = self.bounds();
+        if llr >= upper {
+            SprtStatus::AcceptH1
+        } else if llr <= lower {
+            SprtStatus::AcceptH0
+        } else {
+            SprtStatus::Continue
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MatchScore, Sprt, SprtStatus};
+
+    fn score(wins: u32, losses: u32, draws: u32) -> MatchScore {
+        MatchScore {
+            wins,
+            losses,
+            draws,
+        }
+    }
+
+    #[test]
+    fn estimates_elo_with_error_bars() {
+        assert_eq!(None, score(10, 0, 0).elo());
+        assert_eq!(None, score(0, 0, 0).elo());
+
+        let even = score(30, 30, 40).elo().unwrap();
+        assert!(even.elo.abs() < 1e-9);
+        // A score of 0.5 with a standard error of 0.0387 per 100 games.
+        assert!((even.margin - 53.2).abs() < 0.1, "{even:?}");
+
+        let ahead = score(40, 20, 40).elo().unwrap();
+        // A score of 0.6 is worth about 70 Elo.
+        assert!((ahead.elo - 70.4).abs() < 0.1, "{ahead:?}");
+        assert!(score(400, 200, 400).elo().unwrap().margin < ahead.margin);
+    }
+
+    #[test]
+    fn sprt_accepts_the_hypothesis_the_results_favour() {
+        let sprt = Sprt {
+            elo0: 0.0,
+            elo1: 10.0,
+            alpha: 0.05,
+            beta: 0.05,
+        };
+        let (lower, upper) = sprt.bounds();
+        assert!((upper - 2.944).abs() < 0.001);
+        assert!((lower + 2.944).abs() < 0.001);
+
+        assert_eq!(SprtStatus::Continue, sprt.status(&score(12, 10, 20)));
+        assert_eq!(0.0, sprt.llr(&score(100, 0, 0)));
+        assert_eq!(SprtStatus::AcceptH1, sprt.status(&score(1200, 1000, 2000)));
+        assert_eq!(SprtStatus::AcceptH0, sprt.status(&score(1000, 1100, 2000)));
+    }
+
+    #[test]
+    fn sprt_decides_matches_without_draws() {
+        let sprt = Sprt {
+            elo0: 0.0,
+            elo1: 10.0,
+            alpha: 0.05,
+            beta: 0.05,
+        };
+        assert_eq!(SprtStatus::AcceptH1, sprt.status(&score(600, 400, 0)));
+        assert_eq!(SprtStatus::AcceptH0, sprt.status(&score(400, 600, 0)));
+        assert_eq!(0.0, sprt.llr(&score(0, 0, 0)));
+    }
+}
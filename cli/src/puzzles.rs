@@ -0,0 +1,241 @@
+use std::fs;
+
+use chess_ai::{find_puzzles, Puzzle, PuzzleSettings, Searcher};
+use chess_parsers::{parse_algebraic_notation, GameResult, ParsedGame};
+use log::{error, warn};
+use rusqlite::Connection;
+
+use crate::shared::{read_pgn_games, search_limits, tag};
+
+/// A game to look for puzzles in, along with a description of where it
+/// came from.
+struct SourceGame {
+    source: String,
+    game: ParsedGame,
+}
+
+/// Searches the games in `files` for tactical puzzles and writes them to
+/// `output` as EPD, one puzzle per line. Files ending in `.sqlite` or `.db`
+/// are read as databases created by the load subcommand, anything else as
+/// PGN.
+pub(crate) fn handle_puzzles_subcommand(
+    files: Vec<&String>,
+    output: &str,
+    settings: PuzzleSettings,
+) -> Result<(), ()> {
+    let mut games = Vec::new();
+    for file_name in files {
+        if file_name.ends_with(".sqlite") || file_name.ends_with(".db") {
+            games.extend(load_sqlite_games(file_name)?);
+        } else {
+            games.extend(load_pgn_games(file_name)?);
+        }
+    }
+
+    let mut searcher = Searcher::new();
+    let mut lines = String::new();
+    let mut found = 0;
+    for SourceGame { source, game } in games.iter() {
+        let puzzles = match find_puzzles(game, &mut searcher, &settings) {
+            Err(err) => {
+                warn!("Skipping {source}: {err}");
+                continue;
+            }
+            Ok(puzzles) => puzzles,
+        };
+
+        println!("{source}: {} puzzles", puzzles.len());
+        found += puzzles.len();
+        for puzzle in puzzles.iter() {
+            lines.push_str(&puzzle_to_epd(puzzle, source));
+            lines.push('\n');
+        }
+    }
+    println!("Found {found} puzzles in {} games", games.len());
+
+    if let Err(err) = fs::write(output, lines) {
+        error!("Failed to write {output}. Inner error: {err}");
+        return Err(());
+    }
+    Ok(())
+}
+
+/// Builds the settings from the command line limits, searching to the
+/// default depth if no limit is given.
+pub(crate) fn settings(
+    depth: Option<usize>,
+    nodes: Option<u64>,
+    move_time: Option<u64>,
+    max_moves: usize,
+    skipped_plies: usize,
+) -> PuzzleSettings {
+    let mut settings = PuzzleSettings {
+        max_moves,
+        skipped_plies,
+        ..Default::default()
+    };
+    if let Some(limits) = search_limits(depth, nodes, move_time) {
+        settings.limits = limits;
+    }
+    settings
+}
+
+fn load_pgn_games(file_name: &str) -> Result<Vec<SourceGame>, ()> {
+    Ok(read_pgn_games(file_name)?
+        .into_iter()
+        .enumerate()
+        .map(|(i, game)| {
+            let source = format!(
+                "{file_name} game {} ({} vs {}, {} {})",
+                i + 1,
+                tag(&game, "White"),
+                tag(&game, "Black"),
+                tag(&game, "Event"),
+                tag(&game, "Date"),
+            );
+            SourceGame { source, game }
+        })
+        .collect())
+}
+
+/// Reads every game back out of a database created by the load subcommand.
+/// Games are always loaded from the starting position, so only their moves
+/// and the names of the players are needed.
+fn load_sqlite_games(file_name: &str) -> Result<Vec<SourceGame>, ()> {
+    let connection = match Connection::open(file_name) {
+        Err(err) => {
+            error!("Failed to open {file_name}. Inner error: {err}");
+            return Err(());
+        }
+        Ok(connection) => connection,
+    };
+
+    let rows = connection
+        .prepare(
+            "SELECT games.id, games.white, games.black, games.event, games.date, moves.acn \
+             FROM games JOIN moves ON moves.game_id = games.id \
+             ORDER BY games.id, moves.id;",
+        )
+        .and_then(|mut statement| {
+            statement
+                .query_map([], |row| {
+                    Ok((
+                        row.get::<_, i64>(0)?,
+                        [
+                            row.get::<_, Option<String>>(1)?,
+                            row.get::<_, Option<String>>(2)?,
+                            row.get::<_, Option<String>>(3)?,
+                            row.get::<_, Option<String>>(4)?,
+                        ],
+                        row.get::<_, String>(5)?,
+                    ))
+                })?
+                .collect::<Result<Vec<_>, _>>()
+        });
+    let rows = match rows {
+        Err(err) => {
+            error!("Failed to read the games in {file_name}. Inner error: {err}");
+            return Err(());
+        }
+        Ok(rows) => rows,
+    };
+
+    // Each game's id, whether all of its moves could be read, and the game.
+    let mut games: Vec<(i64, bool, SourceGame)> = Vec::new();
+    for (id, [white, black, event, date], acn) in rows {
+        if games.last().map(|(last, ..)| *last) != Some(id) {
+            let or_unknown = |value: Option<String>| value.unwrap_or_else(|| "?".to_string());
+            let source = format!(
+                "{file_name} game {id} ({} vs {}, {} {})",
+                or_unknown(white),
+                or_unknown(black),
+                or_unknown(event),
+                or_unknown(date),
+            );
+            let game = ParsedGame {
+                tag_pairs: Vec::new(),
+                moves: Vec::new(),
+                annotations: Vec::new(),
+                result: GameResult::Inconclusive,
+            };
+            games.push((id, true, SourceGame { source, game }));
+        }
+
+        let (_, readable, SourceGame { source, game }) =
+            games.last_mut().expect("a game was just added");
+        match parse_algebraic_notation(&acn) {
+            Some(move_) => game.moves.push(move_),
+            None if *readable => {
+                warn!("Skipping {source}: {acn} is not a move");
+                *readable = false;
+            }
+            None => {}
+        }
+    }
+
+    Ok(games
+        .into_iter()
+        .filter(|(_, readable, _)| *readable)
+        .map(|(.., game)| game)
+        .collect())
+}
+
+/// Writes the puzzle as an EPD line with the first solution move as the
+/// best move, the whole solution as the predicted variation, the themes as
+/// a comment and the game it came from as the id.
+fn puzzle_to_epd(puzzle: &Puzzle, source: &str) -> String {
+    let solution = puzzle
+        .notation
+        .iter()
+        .map(|move_| move_.to_string())
+        .collect::<Vec<_>>();
+    let mut themes = vec![puzzle.kind.to_string()];
+    themes.extend(puzzle.themes.iter().map(|theme| theme.to_string()));
+
+    // EPD leaves out the move counters.
+    let mut epd = puzzle
+        .fen
+        .split_whitespace()
+        .take(4)
+        .collect::<Vec<_>>()
+        .join(" ");
+    if let Some(best) = solution.first() {
+        epd.push_str(&format!(" bm {best};"));
+    }
+    epd.push_str(&format!(
+        " pv {}; c0 \"{}\"; id \"{} ply {}\";",
+        solution.join(" "),
+        themes.join(" "),
+        source.replace('"', "'"),
+        puzzle.ply + 1,
+    ));
+    epd
+}
+
+#[cfg(test)]
+mod tests {
+    use chess_ai::{find_puzzles, Limits, PuzzleSettings, Searcher};
+
+    use super::puzzle_to_epd;
+
+    #[test]
+    fn writes_puzzles_as_epd() {
+        let pgn = b"[FEN \"2r3k1/5ppp/8/8/8/8/5PPP/4R1K1 b - - 0 1\"]\n\n1... Rc7 2. Re8# 1-0";
+        let game = chess_parsers::parse_pgn(pgn).unwrap().remove(0);
+        let settings = PuzzleSettings {
+            limits: Limits::depth(4),
+            skipped_plies: 1,
+            ..Default::default()
+        };
+        let puzzles = find_puzzles(&game, &mut Searcher::new(), &settings).unwrap();
+
+        let epd = puzzle_to_epd(&puzzles[0], "games.pgn game 1");
+        assert_eq!(
+            "6k1/2r2ppp/8/8/8/8/5PPP/4R1K1 w - - bm Re8#; pv Re8#; \
+             c0 \"winning mateIn1 backRankMate\"; id \"games.pgn game 1 ply 2\";",
+            epd,
+        );
+        let parsed = chess_parsers::parse_epd(&epd).unwrap();
+        assert_eq!(vec!["Re8#"], parsed.operation("bm").unwrap().operands);
+    }
+}
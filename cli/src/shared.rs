@@ -0,0 +1,53 @@
+//! Helpers shared by the subcommands that search the games in a PGN file.
+
+use std::{fs, time::Duration};
+
+use chess_ai::Limits;
+use chess_parsers::ParsedGame;
+use iso_8859_1_encoder::Iso8859String;
+use log::error;
+
+/// Reads and parses every game in the PGN file, logging why if it can't.
+pub(crate) fn read_pgn_games(file_name: &str) -> Result<Vec<ParsedGame>, ()> {
+    let pgn = match fs::read(file_name) {
+        Err(err) => {
+            error!("Failed to read {file_name}. Inner error: {err}");
+            return Err(());
+        }
+        Ok(bytes) => Iso8859String::from_bytes(bytes),
+    };
+    match chess_parsers::parse_pgn(pgn.as_bytes()) {
+        Err(err) => {
+            error!("Failed to parse {file_name}. Inner error: {err:?}");
+            Err(())
+        }
+        Ok(games) => Ok(games),
+    }
+}
+
+/// Gets the value of the game's tag, or "?" if the game doesn't have it.
+pub(crate) fn tag(game: &ParsedGame, name: &str) -> String {
+    game.tag_pairs
+        .iter()
+        .find(|(tag, _)| tag.to_string() == name)
+        .map(|(_, value)| value.to_string())
+        .unwrap_or_else(|| "?".to_string())
+}
+
+/// Builds limits from the command line, or None if no limit was given so
+/// that the subcommand's default applies.
+pub(crate) fn search_limits(
+    depth: Option<usize>,
+    nodes: Option<u64>,
+    move_time: Option<u64>,
+) -> Option<Limits> {
+    if depth.is_none() && nodes.is_none() && move_time.is_none() {
+        return None;
+    }
+    Some(Limits {
+        depth,
+        nodes,
+        move_time: move_time.map(Duration::from_millis),
+        ..Default::default()
+    })
+}
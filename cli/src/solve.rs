@@ -0,0 +1,48 @@
+use std::fs;
+
+use chess_ai::{parse_problems, solve};
+use log::error;
+
+/// Solves every problem in `file`, which has one FEN and stipulation per
+/// line, and prints each solution along with any cooks and duals.
+pub(crate) fn handle_solve_subcommand(file_name: &str) -> Result<(), ()> {
+    let text = match fs::read_to_string(file_name) {
+        Err(err) => {
+            error!("Failed to read {file_name}. Inner error: {err}");
+            return Err(());
+        }
+        Ok(text) => text,
+    };
+
+    let problems = match parse_problems(&text) {
+        Err(err) => {
+            error!("Failed to parse {file_name}. Inner error: {err}");
+            return Err(());
+        }
+        Ok(problems) => problems,
+    };
+
+    for problem in problems.iter() {
+        let solution = solve(problem);
+        println!("{} {}", problem.board.to_fen_string(), problem.stipulation);
+        if !solution.is_solved() {
+            println!("No solution");
+        }
+        for tree in solution.trees.iter() {
+            print!("{tree}");
+        }
+        if solution.is_cooked() {
+            println!("Cooked: {} solutions", solution.solution_count());
+        }
+        for dual in solution.duals() {
+            println!(
+                "Dual after {}: {}",
+                dual.line.join(" "),
+                dual.continuations.join(", ")
+            );
+        }
+        println!();
+    }
+
+    Ok(())
+}
@@ -0,0 +1,167 @@
+use std::{fmt::Display, fs};
+
+use chess_ai::{run_test_position, Limits, Searcher, TestPosition};
+use log::{error, warn};
+
+/// How many of a group of positions were solved.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+struct Tally {
+    solved: usize,
+    total: usize,
+}
+
+impl Tally {
+    fn add(&mut self, solved: bool) {
+        self.total += 1;
+        if solved {
+            self.solved += 1;
+        }
+    }
+}
+
+impl Display for Tally {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.solved, self.total)?;
+        if self.total > 0 {
+            write!(
+                f,
+                " ({:.1}%)",
+                100.0 * self.solved as f64 / self.total as f64
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// The results of one suite, in total and for each theme in the order the
+/// themes first appear.
+#[derive(Clone, Debug, Default)]
+struct SuiteReport {
+    overall: Tally,
+    themes: Vec<(String, Tally)>,
+}
+
+impl SuiteReport {
+    fn add(&mut self, theme: Option<String>, solved: bool) {
+        self.overall.add(solved);
+        let theme = match theme {
+            None => return,
+            Some(theme) => theme,
+        };
+        match self.themes.iter_mut().find(|(name, _)| *name == theme) {
+            Some((_, tally)) => tally.add(solved),
+            None => {
+                let mut tally = Tally::default();
+                tally.add(solved);
+                self.themes.push((theme, tally));
+            }
+        }
+    }
+}
+
+/// Runs every EPD test suite in `files`, searching each position with
+/// `limits` and checking the move chosen against its `bm` and `am`
+/// operations. Each position's result is printed as it finishes, followed
+/// by the number solved in each suite and each theme within it.
+pub(crate) fn handle_suite_subcommand(
+    files: Vec<&String>,
+    limits: Limits,
+    threads: usize,
+    hash: Option<usize>,
+) -> Result<(), ()> {
+    let mut searcher = Searcher::new();
+    searcher.set_threads(threads);
+    if let Some(hash) = hash {
+        searcher.set_hash_size(hash);
+    }
+
+    let mut reports = Vec::new();
+    for file_name in files {
+        let text = match fs::read(file_name) {
+            Err(err) => {
+                error!("Failed to read {file_name}. Inner error: {err}");
+                return Err(());
+            }
+            Ok(bytes) => String::from_utf8_lossy(&bytes).into_owned(),
+        };
+
+        let mut report = SuiteReport::default();
+        for (line_number, line) in text.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let position = match chess_parsers::parse_epd(line)
+                .map_err(|err| err.to_string())
+                .and_then(|epd| TestPosition::from_epd(&epd).map_err(|err| err.to_string()))
+            {
+                Err(err) => {
+                    warn!("Skipping {file_name}:{}: {err}", line_number + 1);
+                    continue;
+                }
+                Ok(position) => position,
+            };
+
+            let outcome = run_test_position(&mut searcher, &position, limits.clone());
+            let name = position
+                .id
+                .clone()
+                .unwrap_or_else(|| format!("{file_name}:{}", line_number + 1));
+            let played = outcome
+                .notation
+                .map_or("(none)".to_string(), |move_| move_.to_string());
+            println!(
+                "{name}: {played} {} (depth {}, {} nodes)",
+                if outcome.solved { "solved" } else { "failed" },
+                outcome.depth,
+                outcome.nodes,
+            );
+            report.add(position.theme(), outcome.solved);
+        }
+        reports.push((file_name, report));
+    }
+
+    println!();
+    let mut total = Tally::default();
+    for (file_name, report) in reports.iter() {
+        println!("{file_name}: {}", report.overall);
+        // A suite with a single theme is already summed up by its total.
+        if report.themes.len() > 1 {
+            for (theme, tally) in report.themes.iter() {
+                println!("  {theme}: {tally}");
+            }
+        }
+        total.solved += report.overall.solved;
+        total.total += report.overall.total;
+    }
+    if reports.len() > 1 {
+        println!("Total: {total}");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SuiteReport, Tally};
+
+    #[test]
+    fn counts_solved_positions_by_theme() {
+        let mut report = SuiteReport::default();
+        report.add(Some("STS Undermining".to_string()), true);
+        report.add(Some("STS Open Files".to_string()), false);
+        report.add(Some("STS Undermining".to_string()), false);
+        report.add(None, true);
+
+        assert_eq!(
+            Tally {
+                solved: 2,
+                total: 4
+            },
+            report.overall
+        );
+        assert_eq!("2/4 (50.0%)", report.overall.to_string());
+        assert_eq!("STS Undermining", report.themes[0].0);
+        assert_eq!("1/2 (50.0%)", report.themes[0].1.to_string());
+        assert_eq!("0/1 (0.0%)", report.themes[1].1.to_string());
+        assert_eq!("0/0", Tally::default().to_string());
+    }
+}
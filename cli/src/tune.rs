@@ -0,0 +1,91 @@
+use std::fs;
+
+use chess_ai::{position_from_epd, positions_from_game, EvalParams, EvalTerms, Tuner};
+use iso_8859_1_encoder::Iso8859String;
+use log::{error, info, warn};
+
+/// Tunes the evaluation parameters against the positions in `files` and
+/// writes the result to `output`. Files ending in `.epd` are read as EPD
+/// with the game result in the `c9` operation, and anything else is read as
+/// PGN with every position of each decided game used.
+pub(crate) fn handle_tune_subcommand(
+    output: &str,
+    starting_params: Option<&String>,
+    passes: usize,
+    step: i32,
+    files: Vec<&String>,
+) -> Result<(), ()> {
+    let params = match starting_params {
+        None => EvalParams::default(),
+        Some(path) => match EvalParams::load(path) {
+            Err(err) => {
+                error!("Failed to load {path}. Inner error: {err}");
+                return Err(());
+            }
+            Ok(params) => params,
+        },
+    };
+
+    let mut positions = Vec::new();
+    for file_name in files {
+        let bytes = match fs::read(file_name) {
+            Err(err) => {
+                error!("Failed to read {file_name}. Inner error: {err}");
+                return Err(());
+            }
+            Ok(bytes) => bytes,
+        };
+
+        if file_name.ends_with(".epd") {
+            let text = String::from_utf8_lossy(&bytes);
+            for (line_number, line) in text.lines().enumerate() {
+                if line.trim().is_empty() {
+                    continue;
+                }
+
+                match chess_parsers::parse_epd(line).map(|epd| position_from_epd(&epd)) {
+                    Ok(Some(position)) => positions.push(position),
+                    Ok(None) => warn!(
+                        "Skipping {file_name}:{}, which has no valid position and result",
+                        line_number + 1
+                    ),
+                    Err(err) => warn!("Skipping {file_name}:{}: {err}", line_number + 1),
+                }
+            }
+        } else {
+            let pgn = Iso8859String::from_bytes(bytes);
+            match chess_parsers::parse_pgn(pgn.as_bytes()) {
+                Err(err) => {
+                    error!("Failed to parse {file_name}. Inner error: {err:?}");
+                    return Err(());
+                }
+                Ok(games) => positions.extend(games.iter().flat_map(positions_from_game)),
+            }
+        }
+    }
+
+    if positions.is_empty() {
+        error!("No positions to tune with");
+        return Err(());
+    }
+
+    let terms = EvalTerms::default();
+    let mut tuner = Tuner::new(positions, &params, terms);
+    let scaling = tuner.fit_scaling(&params);
+    info!(
+        "Tuning with {} positions, scaling {scaling:.3}, starting error {:.6}",
+        tuner.len(),
+        tuner.error(&params)
+    );
+
+    let params = tuner.tune(params, step, passes, |pass, error| {
+        info!("Pass {pass} finished with error {error:.6}");
+    });
+
+    if let Err(err) = params.save(output) {
+        error!("Failed to write {output}. Inner error: {err}");
+        return Err(());
+    }
+
+    Ok(())
+}
@@ -0,0 +1,185 @@
+use std::{
+    error::Error,
+    fmt::{Debug, Display},
+};
+
+/// A position in Extended Position Description format: the first four
+/// fields of a FEN string followed by operations such as `bm Nf3;` or
+/// `c9 "1-0";`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ParsedEpd {
+    /// The position as a complete FEN string. EPD leaves out the move
+    /// counters, so they are taken from the `hmvc` and `fmvn` operations
+    /// if present, and default to `0` and `1` otherwise.
+    pub fen: String,
+    pub operations: Vec<EpdOperation>,
+}
+
+impl ParsedEpd {
+    /// Gets the first operation with the given opcode.
+    pub fn operation(&self, opcode: &str) -> Option<&EpdOperation> {
+        self.operations
+            .iter()
+            .find(|operation| operation.opcode == opcode)
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct EpdOperation {
+    pub opcode: String,
+    /// The operands with any quotes around them removed.
+    pub operands: Vec<String>,
+}
+
+pub enum EpdErr {
+    /// The line has fewer than the four fields that describe a position.
+    MissingFields,
+    /// A quoted operand is missing its closing quote.
+    UnterminatedString,
+}
+
+impl Debug for EpdErr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Just defer to the Display impl
+        write!(f, "{}", self)
+    }
+}
+
+impl Display for EpdErr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EpdErr::MissingFields => write!(f, "Expected at least four position fields"),
+            EpdErr::UnterminatedString => write!(f, "Missing closing quote"),
+        }
+    }
+}
+
+impl Error for EpdErr {}
+
+pub(crate) fn parse_epd(line: &str) -> Result<ParsedEpd, EpdErr> {
+    let line = line.trim();
+    let mut fields = Vec::with_capacity(4);
+    let mut rest = line;
+    for _ in 0..4 {
+        rest = rest.trim_start();
+        let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        if end == 0 {
+            return Err(EpdErr::MissingFields);
+        }
+        fields.push(&rest[..end]);
+        rest = &rest[end..];
+    }
+
+    let operations = parse_operations(rest)?;
+    let counter = |opcode, default: &str| {
+        operations
+            .iter()
+            .find(|operation: &&EpdOperation| operation.opcode == opcode)
+            .and_then(|operation| operation.operands.first().cloned())
+            .unwrap_or(default.to_string())
+    };
+    let fen = format!(
+        "{} {} {}",
+        fields.join(" "),
+        counter("hmvc", "0"),
+        counter("fmvn", "1")
+    );
+
+    Ok(ParsedEpd { fen, operations })
+}
+
+fn parse_operations(text: &str) -> Result<Vec<EpdOperation>, EpdErr> {
+    let mut operations = Vec::new();
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            ';' => {
+                if let Some(operation) = to_operation(&mut tokens) {
+                    operations.push(operation);
+                }
+            }
+            '"' => {
+                let mut token = String::new();
+                loop {
+                    match chars.next() {
+                        None => return Err(EpdErr::UnterminatedString),
+                        Some('"') => break,
+                        Some(c) => token.push(c),
+                    }
+                }
+                tokens.push(token);
+            }
+            c if c.is_whitespace() => {}
+            c => {
+                let mut token = c.to_string();
+                while let Some(&next) = chars.peek() {
+                    if next.is_whitespace() || next == ';' || next == '"' {
+                        break;
+                    }
+                    token.push(next);
+                    chars.next();
+                }
+                tokens.push(token);
+            }
+        }
+    }
+
+    // The last operation's semicolon is sometimes left out.
+    if let Some(operation) = to_operation(&mut tokens) {
+        operations.push(operation);
+    }
+
+    Ok(operations)
+}
+
+fn to_operation(tokens: &mut Vec<String>) -> Option<EpdOperation> {
+    if tokens.is_empty() {
+        return None;
+    }
+
+    let mut operands = std::mem::take(tokens);
+    let opcode = operands.remove(0);
+    Some(EpdOperation { opcode, operands })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{parse_epd, EpdErr};
+
+    #[test]
+    fn parses_position_and_operations() {
+        let epd = parse_epd(
+            "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - bm Bb5 Bc4; id \"Open game\"; c9 \"1-0\";",
+        )
+        .unwrap();
+
+        assert_eq!(
+            "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 0 1",
+            epd.fen
+        );
+        assert_eq!(3, epd.operations.len());
+        assert_eq!(vec!["Bb5", "Bc4"], epd.operation("bm").unwrap().operands);
+        assert_eq!(vec!["Open game"], epd.operation("id").unwrap().operands);
+        assert_eq!(vec!["1-0"], epd.operation("c9").unwrap().operands);
+    }
+
+    #[test]
+    fn uses_move_counter_operations() {
+        let epd = parse_epd("8/8/8/8/8/8/8/K6k b - - hmvc 12; fmvn 40").unwrap();
+        assert_eq!("8/8/8/8/8/8/8/K6k b - - 12 40", epd.fen);
+    }
+
+    #[test]
+    fn rejects_malformed_lines() {
+        assert!(matches!(
+            parse_epd("8/8/8/8 w -"),
+            Err(EpdErr::MissingFields)
+        ));
+        assert!(matches!(
+            parse_epd("8/8/8/8/8/8/8/K6k w - - id \"oops;"),
+            Err(EpdErr::UnterminatedString)
+        ));
+    }
+}
@@ -1,11 +1,13 @@
 mod pgn_parser;
 use pgn_parser::PgnParser;
-pub use pgn_parser::{GameResult, ParsedGame, PgnErr};
+pub use pgn_parser::{GameResult, MoveAnnotation, ParsedGame, PgnErr};
 mod fen;
 use fen::FenParser;
 pub use fen::{BoardLayout, FenErr, PieceLocations};
 mod acn_parser;
 pub use acn_parser::{parse_algebraic_notation, Check, NormalMove, PieceMove, PieceMoveKind};
+mod epd;
+pub use epd::{EpdErr, EpdOperation, ParsedEpd};
 
 pub fn parse_pgn(pgn: &[u8]) -> Result<Vec<ParsedGame>, PgnErr> {
     PgnParser::parse_pgn(pgn)
@@ -14,3 +16,7 @@ pub fn parse_pgn(pgn: &[u8]) -> Result<Vec<ParsedGame>, PgnErr> {
 pub fn parse_fen(fen: &str) -> Result<BoardLayout, FenErr> {
     FenParser::parse_fen(fen)
 }
+
+pub fn parse_epd(epd: &str) -> Result<ParsedEpd, EpdErr> {
+    epd::parse_epd(epd)
+}
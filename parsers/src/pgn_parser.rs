@@ -12,9 +12,24 @@ use crate::acn_parser::{parse_algebraic_notation, PieceMove};
 pub struct ParsedGame {
     pub tag_pairs: Vec<(Iso8859String, Iso8859String)>,
     pub moves: Vec<PieceMove>,
+    /// The annotations written after each move, in the same order as
+    /// `moves`. Moves past the end of the list have none.
+    pub annotations: Vec<MoveAnnotation>,
     pub result: GameResult,
 }
 
+/// What's written after a move in a PGN: Numeric Annotation Glyphs, a
+/// comment and variations that could have been played instead of it.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct MoveAnnotation {
+    /// NAG numbers, such as 2 for a mistake (`?`) or 4 for a blunder (`??`).
+    pub nags: Vec<u8>,
+    pub comment: Option<String>,
+    /// Alternatives to the move, each starting with a move that could have
+    /// been played in its place.
+    pub variations: Vec<Vec<PieceMove>>,
+}
+
 impl Debug for ParsedGame {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("ParsedGame")
@@ -34,6 +49,7 @@ impl Debug for ParsedGame {
                     .map(|move_| move_.to_string())
                     .collect::<Vec<_>>(),
             )
+            .field("annotations", &self.annotations)
             .field("result", &format!("{:?}", self.result))
             .finish()
     }
@@ -63,11 +79,90 @@ impl ParsedGame {
         Some(Self {
             tag_pairs,
             moves,
+            annotations: Vec::new(),
             result,
         })
     }
 }
 
+impl ParsedGame {
+    /// Gets the number of the first move and whether black makes it, from
+    /// the game's FEN tag if it has one.
+    fn first_move(&self) -> (u32, bool) {
+        let fen = self
+            .tag_pairs
+            .iter()
+            .find(|(name, _)| name.to_string() == "FEN")
+            .map(|(_, value)| value.to_string());
+        let mut fields = match fen.as_deref() {
+            None => return (1, false),
+            Some(fen) => fen.split_whitespace().skip(1),
+        };
+
+        let black_first = fields.next() == Some("b");
+        let move_num = fields
+            .nth(3)
+            .and_then(|number| number.parse().ok())
+            .filter(|number| *number > 0)
+            .unwrap_or(1);
+        (move_num, black_first)
+    }
+}
+
+/// Writes `moves` with their annotations, starting at the half move `ply`
+/// of the game counted from white's first move.
+fn write_moves(
+    result: &mut Vec<u8>,
+    moves: &[PieceMove],
+    annotations: &[MoveAnnotation],
+    mut ply: usize,
+) {
+    let mut needs_number = true;
+    for (i, move_) in moves.iter().enumerate() {
+        if i != 0 {
+            result.push(b' ');
+        }
+        if ply.is_multiple_of(2) {
+            result.extend_from_slice(format!("{}. ", ply / 2 + 1).as_bytes());
+        } else if needs_number {
+            result.extend_from_slice(format!("{}... ", ply / 2 + 1).as_bytes());
+        }
+        result.extend_from_slice(move_.to_string().as_bytes());
+        needs_number = false;
+
+        if let Some(annotation) = annotations.get(i) {
+            for nag in annotation.nags.iter() {
+                result.extend_from_slice(format!(" ${nag}").as_bytes());
+            }
+
+            if let Some(comment) = &annotation.comment {
+                result.extend_from_slice(b" {");
+                // A comment ends at the first closing brace, and anything
+                // outside ISO 8859-1 can't be written at all.
+                for ch in comment.chars() {
+                    result.push(match ch {
+                        '}' => b')',
+                        ch if (ch as u32) < 0x100 => ch as u8,
+                        _ => b'?',
+                    });
+                }
+                result.push(b'}');
+                needs_number = true;
+            }
+
+            // A variation replaces the move it follows, so it starts at the
+            // same ply.
+            for variation in annotation.variations.iter() {
+                result.extend_from_slice(b" (");
+                write_moves(result, variation, &[], ply);
+                result.push(b')');
+                needs_number = true;
+            }
+        }
+        ply += 1;
+    }
+}
+
 impl Into<Iso8859String> for &ParsedGame {
     fn into(self) -> Iso8859String {
         let mut result = Vec::new();
@@ -96,37 +191,11 @@ impl Into<Iso8859String> for &ParsedGame {
         result.push(b'\n');
         result.push(b'\n');
 
-        let mut moves_iter = self.moves.iter();
-        let mut move_num = 0;
-        loop {
-            let white_move = match moves_iter.next() {
-                None => break,
-                Some(move_) => move_,
-            };
-
-            if move_num != 0 {
-                result.push(b' ');
-            }
-            move_num += 1;
-            for byte in move_num.to_string().as_bytes() {
-                result.push(*byte);
-            }
-            result.push(b'.');
-            result.push(b' ');
-            for byte in white_move.to_string().as_bytes() {
-                result.push(*byte);
-            }
-
-            let black_move = match moves_iter.next() {
-                None => break,
-                Some(move_) => move_,
-            };
-
-            result.push(b' ');
-            for byte in black_move.to_string().as_bytes() {
-                result.push(*byte);
-            }
-        }
+        // Games set up from a FEN tag start at its move number, and with a
+        // black move written as `12...` if black is to move.
+        let (move_num, black_first) = self.first_move();
+        let ply = 2 * (move_num as usize - 1) + black_first as usize;
+        write_moves(&mut result, &self.moves, &self.annotations, ply);
 
         result.push(b' ');
         for byte in Iso8859String::try_from(self.result.as_ref())
@@ -294,11 +363,13 @@ impl<'pgn> PgnParser<'pgn> {
                     tag_pairs,
                     result: GameResult::Inconclusive,
                     moves: Vec::with_capacity(0),
+                    annotations: Vec::new(),
                 },
                 Some((result, moves)) => ParsedGame {
                     tag_pairs,
                     result,
                     moves,
+                    annotations: Vec::new(),
                 },
             });
         }
@@ -1179,7 +1250,9 @@ where
 
 #[cfg(test)]
 mod tests {
-    use crate::{parse_pgn, pgn_parser::PgnTokenizer};
+    use iso_8859_1_encoder::Iso8859String;
+
+    use crate::{parse_pgn, pgn_parser::PgnTokenizer, MoveAnnotation};
 
     use super::PgnParser;
 
@@ -1204,6 +1277,39 @@ mod tests {
         println!("{:#?}", parsed);
     }
 
+    #[test]
+    fn writes_move_numbers_from_the_fen_tag() {
+        let pgn = br#"[FEN "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 5"]
+
+5... e5 6. Nf3 Nc6 *"#;
+
+        let game = &PgnParser::parse_pgn(pgn).unwrap()[0];
+        let written: Iso8859String = game.into();
+        let written = written.to_string();
+        assert!(written.ends_with("\n\n5... e5 6. Nf3 Nc6 *"), "{written}");
+    }
+
+    #[test]
+    fn writes_annotations() {
+        let pgn = b"1. e4 e5 2. Qh5 Nc6 3. Bc4 Nf6 4. Qxf7# 1-0";
+        let mut game = PgnParser::parse_pgn(pgn).unwrap().remove(0);
+        let alternative = PgnParser::parse_pgn(b"3... g6 4. Qf3 *").unwrap().remove(0);
+        game.annotations = vec![MoveAnnotation::default(); 6];
+        game.annotations[1].comment = Some("[%eval 0.3]".to_string());
+        game.annotations[5] = MoveAnnotation {
+            nags: vec![4],
+            comment: Some("Blunder. g6 was best.".to_string()),
+            variations: vec![alternative.moves],
+        };
+
+        let written: Iso8859String = (&game).into();
+        assert_eq!(
+            "\n\n1. e4 e5 {[%eval 0.3]} 2. Qh5 Nc6 3. Bc4 Nf6 $4 {Blunder. g6 was best.} \
+             (3... g6 4. Qf3) 4. Qxf7# 1-0",
+            written.to_string()
+        );
+    }
+
     #[test]
     fn parses_real_pgn() {
         let pgn = include_bytes!("../Bucharest2023.pgn");
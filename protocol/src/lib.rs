@@ -0,0 +1,13 @@
+//! The plumbing shared by the engine's text protocol front ends: reading
+//! commands from the GUI, writing replies from more than one thread, and
+//! running searches in the background.
+
+mod output;
+mod protocol;
+mod search_thread;
+#[cfg(feature = "testing")]
+pub mod testing;
+
+pub use output::Output;
+pub use protocol::{run, Protocol};
+pub use search_thread::SearchThread;
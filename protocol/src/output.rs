@@ -0,0 +1,30 @@
+use std::{
+    io::Write,
+    sync::{Arc, Mutex},
+};
+
+/// The output shared between a session and its search thread, so that
+/// search reports and the engine's move can be written while the session
+/// carries on reading commands.
+pub struct Output<W: Write>(Arc<Mutex<W>>);
+
+impl<W: Write> Output<W> {
+    pub fn new(writer: W) -> Self {
+        Self(Arc::new(Mutex::new(writer)))
+    }
+
+    /// Writes the line and flushes it straight away, since the GUI is
+    /// waiting on the other end of a pipe. There's nobody to report a
+    /// failed write to, so it's ignored.
+    pub fn send(&self, line: &str) {
+        let mut writer = self.0.lock().expect("no writer to panic while writing");
+        writeln!(writer, "{line}").ok();
+        writer.flush().ok();
+    }
+}
+
+impl<W: Write> Clone for Output<W> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
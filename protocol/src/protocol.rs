@@ -0,0 +1,36 @@
+use std::io::{BufRead, Write};
+
+use crate::Output;
+
+/// A conversation with a GUI in one of the text protocols.
+pub trait Protocol {
+    /// Handles one line from the GUI, returning false once the GUI has
+    /// asked the engine to quit.
+    fn handle_line(&mut self, line: &str) -> bool;
+
+    /// Stops any search that is still running, as if the GUI had asked the
+    /// engine to quit.
+    fn quit(&mut self);
+}
+
+/// Reads commands from `input` until the session is told to quit or the
+/// input ends, writing the engine's replies to `output`.
+pub fn run<W: Write, P: Protocol>(
+    input: impl BufRead,
+    output: W,
+    new_session: impl FnOnce(Output<W>) -> P,
+) {
+    let mut session = new_session(Output::new(output));
+    for line in input.lines() {
+        let line = match line {
+            Err(_) => break,
+            Ok(line) => line,
+        };
+        if !session.handle_line(&line) {
+            return;
+        }
+    }
+    // The GUI went away without saying quit, so stop any search that is
+    // still running rather than leaving it to carry on by itself.
+    session.quit();
+}
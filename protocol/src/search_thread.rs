@@ -0,0 +1,78 @@
+use std::thread::{self, JoinHandle};
+
+use chess_ai::{PonderHandle, Searcher, StopHandle};
+
+/// Runs searches on a thread of their own, so that the session can carry
+/// on reading commands while the engine thinks. The searcher is handed to
+/// the search thread for the length of the search, and handed back when
+/// the search is done.
+pub struct SearchThread {
+    /// None while a search is running, since the search thread owns it.
+    searcher: Option<Searcher>,
+    thread: Option<JoinHandle<Searcher>>,
+    stop_handle: StopHandle,
+    ponder_handle: PonderHandle,
+}
+
+impl SearchThread {
+    pub fn new(searcher: Searcher) -> Self {
+        Self {
+            stop_handle: searcher.stop_handle(),
+            ponder_handle: searcher.ponder_handle(),
+            searcher: Some(searcher),
+            thread: None,
+        }
+    }
+
+    /// Gets the searcher, first waiting for any search in progress to
+    /// finish so that it can't change options under a running search.
+    pub fn searcher(&mut self) -> &mut Searcher {
+        if let Some(thread) = self.thread.take() {
+            let searcher = thread.join().expect("the search not to panic");
+            self.searcher = Some(searcher);
+        }
+        self.searcher
+            .as_mut()
+            .expect("the searcher to be back once the search is done")
+    }
+
+    /// Gets whether a search has been started and not yet waited for.
+    pub fn is_running(&self) -> bool {
+        self.thread.is_some()
+    }
+
+    /// Starts `search` on its own thread with the searcher, first waiting
+    /// for any search in progress to finish. A stop or ponder hit sent
+    /// from now on applies to the new search, even if it lands before the
+    /// search has started.
+    pub fn spawn(&mut self, search: impl FnOnce(&mut Searcher) + Send + 'static) {
+        self.searcher();
+        // Whatever arrived after the last search returned was meant for it.
+        self.stop_handle.reset();
+        self.ponder_handle.reset();
+        let mut searcher = self
+            .searcher
+            .take()
+            .expect("the searcher to be back once the search is done");
+        self.thread = Some(thread::spawn(move || {
+            search(&mut searcher);
+            searcher
+        }));
+    }
+
+    /// Stops the search in progress, if there is one, and waits for it.
+    pub fn stop(&mut self) {
+        if self.thread.is_some() {
+            self.stop_handle.stop();
+        }
+        self.searcher();
+    }
+
+    /// Tells the running search that the opponent played the move it is
+    /// pondering on.
+    pub fn ponder_hit(&self) {
+        if self.thread.is_some() {
+            self.ponder_handle.ponder_hit();
+        }
+    }
+}
@@ -0,0 +1,55 @@
+//! A harness for testing a front end the way a GUI talks to it.
+
+use std::{
+    io::{self, BufRead, BufReader, PipeReader, PipeWriter, Write},
+    thread::{self, JoinHandle},
+};
+
+use crate::{run, Output, Protocol};
+
+/// The engine running on its own thread, talked to through pipes the way
+/// a GUI would.
+pub struct Engine {
+    input: PipeWriter,
+    output: BufReader<PipeReader>,
+    thread: JoinHandle<()>,
+}
+
+impl Engine {
+    pub fn start<P: Protocol + 'static>(new_session: fn(Output<PipeWriter>) -> P) -> Self {
+        let (command_reader, input) = io::pipe().unwrap();
+        let (output, reply_writer) = io::pipe().unwrap();
+        let thread =
+            thread::spawn(move || run(BufReader::new(command_reader), reply_writer, new_session));
+        Self {
+            input,
+            output: BufReader::new(output),
+            thread,
+        }
+    }
+
+    pub fn send(&mut self, command: &str) {
+        writeln!(self.input, "{command}").unwrap();
+    }
+
+    /// Reads lines until one starts with `prefix`, returning every line
+    /// read.
+    pub fn read_until(&mut self, prefix: &str) -> Vec<String> {
+        let mut lines = Vec::new();
+        loop {
+            let mut line = String::new();
+            assert!(self.output.read_line(&mut line).unwrap() > 0, "{lines:?}");
+            let line = line.trim_end().to_string();
+            let done = line.starts_with(prefix);
+            lines.push(line);
+            if done {
+                return lines;
+            }
+        }
+    }
+
+    pub fn quit(mut self) {
+        self.send("quit");
+        self.thread.join().unwrap();
+    }
+}
@@ -0,0 +1,244 @@
+use std::time::Duration;
+
+use chess_ai::Limits;
+
+/// A command sent by the GUI. Anything that isn't understood is kept so
+/// that it can be reported, since the protocol says unknown commands
+/// should be ignored rather than treated as errors.
+#[derive(Clone, Debug)]
+pub(crate) enum UciCommand {
+    Uci,
+    Debug(bool),
+    IsReady,
+    SetOption {
+        name: String,
+        value: Option<String>,
+    },
+    UciNewGame,
+    /// The position to search next, as a FEN (or the starting position if
+    /// None) and the moves played from it in long algebraic notation.
+    Position {
+        fen: Option<String>,
+        moves: Vec<String>,
+    },
+    /// The limits of the search, and the moves in long algebraic notation
+    /// that the search is restricted to, if any.
+    Go {
+        limits: Limits,
+        search_moves: Vec<String>,
+    },
+    Stop,
+    PonderHit,
+    Quit,
+    Unknown(String),
+}
+
+impl UciCommand {
+    pub(crate) fn parse(line: &str) -> Self {
+        let mut words = line.split_whitespace();
+        match words.next() {
+            Some("uci") => UciCommand::Uci,
+            Some("debug") => UciCommand::Debug(words.next() == Some("on")),
+            Some("isready") => UciCommand::IsReady,
+            Some("setoption") => parse_set_option(words).unwrap_or_else(|| unknown(line)),
+            Some("ucinewgame") => UciCommand::UciNewGame,
+            Some("position") => parse_position(words).unwrap_or_else(|| unknown(line)),
+            Some("go") => parse_go(words),
+            Some("stop") => UciCommand::Stop,
+            Some("ponderhit") => UciCommand::PonderHit,
+            Some("quit") => UciCommand::Quit,
+            _ => unknown(line),
+        }
+    }
+}
+
+fn unknown(line: &str) -> UciCommand {
+    UciCommand::Unknown(line.trim().to_string())
+}
+
+/// Reads `name <name> [value <value>]`, where both the name and the value
+/// may contain spaces.
+fn parse_set_option<'a>(mut words: impl Iterator<Item = &'a str>) -> Option<UciCommand> {
+    if words.next() != Some("name") {
+        return None;
+    }
+
+    let mut name = Vec::new();
+    let mut value = None::<Vec<&str>>;
+    for word in words {
+        match &mut value {
+            None if word == "value" => value = Some(Vec::new()),
+            None => name.push(word),
+            Some(value) => value.push(word),
+        }
+    }
+    if name.is_empty() {
+        return None;
+    }
+
+    Some(UciCommand::SetOption {
+        name: name.join(" "),
+        value: value.map(|value| value.join(" ")),
+    })
+}
+
+/// Reads `startpos` or `fen <six fields>`, optionally followed by `moves`
+/// and the moves played since.
+fn parse_position<'a>(mut words: impl Iterator<Item = &'a str>) -> Option<UciCommand> {
+    let fen = match words.next()? {
+        "startpos" => None,
+        "fen" => {
+            let fields = words
+                .by_ref()
+                .take_while(|word| *word != "moves")
+                .collect::<Vec<_>>();
+            if fields.is_empty() {
+                return None;
+            }
+            return Some(UciCommand::Position {
+                fen: Some(fields.join(" ")),
+                moves: words.map(str::to_string).collect(),
+            });
+        }
+        _ => return None,
+    };
+
+    let moves = match words.next() {
+        Some("moves") => words.map(str::to_string).collect(),
+        _ => Vec::new(),
+    };
+    Some(UciCommand::Position { fen, moves })
+}
+
+/// Reads the limits of a `go` command. Values that are missing or can't
+/// be read are skipped. The moves after `searchmoves` are kept as they
+/// are, since they can only be checked against the position.
+fn parse_go<'a>(words: impl Iterator<Item = &'a str>) -> UciCommand {
+    let mut limits = Limits::default();
+    let mut search_moves = Vec::new();
+    let mut words = words.peekable();
+    while let Some(word) = words.next() {
+        let mut value = || words.next().and_then(|value| value.parse::<u64>().ok());
+        match word {
+            "wtime" => limits.white_time = milliseconds(words.next()),
+            "btime" => limits.black_time = milliseconds(words.next()),
+            "winc" => limits.white_increment = milliseconds(words.next()),
+            "binc" => limits.black_increment = milliseconds(words.next()),
+            "movestogo" => limits.moves_to_go = value().map(|moves| moves as u32),
+            "depth" => limits.depth = value().map(|depth| depth as usize),
+            "nodes" => limits.nodes = value(),
+            "mate" => limits.mate = value().map(|moves| moves as u32),
+            "movetime" => limits.move_time = value().map(Duration::from_millis),
+            "infinite" => limits.infinite = true,
+            "ponder" => limits.ponder = true,
+            "searchmoves" => {
+                while let Some(move_) = words.next_if(|word| is_move(word)) {
+                    search_moves.push(move_.to_string());
+                }
+            }
+            _ => {}
+        }
+    }
+    UciCommand::Go {
+        limits,
+        search_moves,
+    }
+}
+
+/// Reads a clock time or increment in milliseconds. Some GUIs send a
+/// negative time once a side has overrun its clock, which is read as no
+/// time left rather than as no clock at all.
+fn milliseconds(word: Option<&str>) -> Option<Duration> {
+    let milliseconds = word?.parse::<i64>().ok()?;
+    Some(Duration::from_millis(milliseconds.max(0) as u64))
+}
+
+/// Whether the word looks like a move in long algebraic notation, such as
+/// `e2e4` or `e7e8q`.
+fn is_move(word: &str) -> bool {
+    let bytes = word.as_bytes();
+    let square =
+        |file: u8, rank: u8| (b'a'..=b'h').contains(&file) && (b'1'..=b'8').contains(&rank);
+    (bytes.len() == 4 || bytes.len() == 5)
+        && square(bytes[0], bytes[1])
+        && square(bytes[2], bytes[3])
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::UciCommand;
+
+    #[test]
+    fn parses_positions_and_options() {
+        match UciCommand::parse("position startpos moves e2e4 e7e5") {
+            UciCommand::Position { fen: None, moves } => assert_eq!(vec!["e2e4", "e7e5"], moves),
+            other => panic!("{other:?}"),
+        }
+        match UciCommand::parse("position fen 8/8/8/8/8/8/8/K1k5 w - - 0 1") {
+            UciCommand::Position {
+                fen: Some(fen),
+                moves,
+            } => {
+                assert_eq!("8/8/8/8/8/8/8/K1k5 w - - 0 1", fen);
+                assert!(moves.is_empty());
+            }
+            other => panic!("{other:?}"),
+        }
+        match UciCommand::parse("setoption name Skill Level value 5") {
+            UciCommand::SetOption { name, value } => {
+                assert_eq!("Skill Level", name);
+                assert_eq!(Some("5".to_string()), value);
+            }
+            other => panic!("{other:?}"),
+        }
+        assert!(matches!(
+            UciCommand::parse("position"),
+            UciCommand::Unknown(line) if line == "position"
+        ));
+    }
+
+    #[test]
+    fn parses_every_go_limit() {
+        let (limits, search_moves) = match UciCommand::parse(
+            "go searchmoves e2e4 d2d4 wtime 1000 btime 2000 winc 10 binc 20 movestogo 5 \
+             depth 6 nodes 7000 mate 3 movetime 500 ponder",
+        ) {
+            UciCommand::Go {
+                limits,
+                search_moves,
+            } => (limits, search_moves),
+            other => panic!("{other:?}"),
+        };
+        assert_eq!(vec!["e2e4", "d2d4"], search_moves);
+        assert_eq!(Some(Duration::from_millis(1000)), limits.white_time);
+        assert_eq!(Some(Duration::from_millis(2000)), limits.black_time);
+        assert_eq!(Some(Duration::from_millis(10)), limits.white_increment);
+        assert_eq!(Some(Duration::from_millis(20)), limits.black_increment);
+        assert_eq!(Some(5), limits.moves_to_go);
+        assert_eq!(Some(6), limits.depth);
+        assert_eq!(Some(7000), limits.nodes);
+        assert_eq!(Some(3), limits.mate);
+        assert_eq!(Some(Duration::from_millis(500)), limits.move_time);
+        assert!(limits.ponder);
+        assert!(!limits.infinite);
+
+        match UciCommand::parse("go infinite") {
+            UciCommand::Go { limits, .. } => assert!(limits.infinite),
+            other => panic!("{other:?}"),
+        }
+    }
+
+    #[test]
+    fn reads_negative_clocks_as_no_time_left() {
+        match UciCommand::parse("go wtime -50 btime 3000 winc -10") {
+            UciCommand::Go { limits, .. } => {
+                assert_eq!(Some(Duration::ZERO), limits.white_time);
+                assert_eq!(Some(Duration::from_millis(3000)), limits.black_time);
+                assert_eq!(Some(Duration::ZERO), limits.white_increment);
+            }
+            other => panic!("{other:?}"),
+        }
+    }
+}
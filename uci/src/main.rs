@@ -0,0 +1,90 @@
+mod command;
+mod session;
+
+use std::io;
+
+use session::Session;
+
+fn main() {
+    chess_protocol::run(io::stdin().lock(), io::stdout(), Session::new);
+}
+
+#[cfg(test)]
+mod tests {
+    use chess_protocol::testing::Engine;
+
+    use crate::session::Session;
+
+    #[test]
+    fn handshakes_and_plays_a_move() {
+        let mut engine = Engine::start(Session::new);
+        engine.send("uci");
+        let lines = engine.read_until("uciok");
+        assert!(lines[0].starts_with("id name chess_ai"));
+        for option in ["Hash", "Threads", "MultiPV", "Skill Level"] {
+            let declaration = format!("option name {option} type spin");
+            assert!(
+                lines.iter().any(|line| line.starts_with(&declaration)),
+                "{lines:?}"
+            );
+        }
+
+        engine.send("setoption name Hash value 1");
+        engine.send("setoption name MultiPV value 2");
+        engine.send("ucinewgame");
+        engine.send("isready");
+        assert_eq!(vec!["readyok"], engine.read_until("readyok"));
+
+        // After 1. f3 e5 2. g4 black mates with Qh4.
+        engine.send("position startpos moves f2f3 e7e5 g2g4");
+        engine.send("go depth 3");
+        let lines = engine.read_until("bestmove");
+        assert_eq!("bestmove d8h4", lines.last().unwrap());
+        assert!(lines
+            .iter()
+            .any(|line| line.contains("multipv 1 score mate 1") && line.ends_with("pv d8h4")));
+        assert!(lines.iter().any(|line| line.contains("multipv 2")));
+
+        // Restricted to a knight move, the engine misses the mate.
+        engine.send("go searchmoves b8c6 depth 3");
+        let lines = engine.read_until("bestmove");
+        assert!(
+            lines.last().unwrap().starts_with("bestmove b8c6"),
+            "{lines:?}"
+        );
+        engine.quit();
+    }
+
+    #[test]
+    fn waits_for_stop_and_ponderhit() {
+        let mut engine = Engine::start(Session::new);
+        engine.send("position fen 4k3/8/8/8/8/8/8/4K2R w K - 0 1 moves e1g1");
+        engine.send("go infinite");
+        engine.send("isready");
+        engine.read_until("readyok");
+        engine.send("stop");
+        let lines = engine.read_until("bestmove");
+        assert!(
+            lines.iter().any(|line| line.starts_with("info depth")),
+            "{lines:?}"
+        );
+
+        // Changing options stops the search rather than waiting forever
+        // for it to finish.
+        engine.send("go infinite");
+        engine.send("setoption name Hash value 1");
+        engine.read_until("bestmove");
+        engine.send("go ponder");
+        engine.send("ucinewgame");
+        engine.read_until("bestmove");
+        engine.send("isready");
+        engine.read_until("readyok");
+
+        engine.send("position startpos moves e2e4");
+        engine.send("go ponder wtime 100 btime 100");
+        engine.send("ponderhit");
+        let bestmove = engine.read_until("bestmove").pop().unwrap();
+        assert!(bestmove.starts_with("bestmove "), "{bestmove}");
+        engine.quit();
+    }
+}
@@ -0,0 +1,252 @@
+use std::{
+    io::Write,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+
+use chess_ai::{Limits, Score, SearchInfo, Searcher, Skill, MAX_SKILL_LEVEL};
+use chess_core::Board;
+use chess_protocol::{Output, Protocol, SearchThread};
+
+use crate::command::UciCommand;
+
+const DEFAULT_HASH_MB: usize = 16;
+const MAX_HASH_MB: usize = 4096;
+const MAX_THREADS: usize = 256;
+const MAX_MULTI_PV: usize = 256;
+
+/// How often a finished infinite search checks whether it has been told
+/// to stop.
+const POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+/// The state of a conversation with a GUI: the current position, the
+/// searcher and its options, and the search in progress if there is one.
+pub(crate) struct Session<W: Write + Send + 'static> {
+    output: Output<W>,
+    board: Board,
+    search: SearchThread,
+    /// Set when the GUI sends `stop`. Unlike the searcher's own flag, it
+    /// isn't set by the search stopping its helper threads, so an infinite
+    /// search can wait on it before sending its best move.
+    stop_requested: Arc<AtomicBool>,
+    debug: bool,
+}
+
+impl<W: Write + Send + 'static> Session<W> {
+    pub(crate) fn new(output: Output<W>) -> Self {
+        let mut searcher = Searcher::new();
+        let info_output = output.clone();
+        searcher.set_info_callback(move |info| info_output.send(&info_line(info)));
+
+        Self {
+            output,
+            board: Board::default(),
+            search: SearchThread::new(searcher),
+            stop_requested: Arc::new(AtomicBool::new(false)),
+            debug: false,
+        }
+    }
+
+    /// Handles one command, returning false once the GUI has asked the
+    /// engine to quit.
+    pub(crate) fn handle(&mut self, command: UciCommand) -> bool {
+        match command {
+            UciCommand::Uci => {
+                self.output
+                    .send(&format!("id name chess_ai {}", env!("CARGO_PKG_VERSION")));
+                self.output.send("id author the chess_ai authors");
+                for option in [
+                    format!(
+                        "option name Hash type spin default {DEFAULT_HASH_MB} min 1 max {MAX_HASH_MB}"
+                    ),
+                    format!("option name Threads type spin default 1 min 1 max {MAX_THREADS}"),
+                    format!("option name MultiPV type spin default 1 min 1 max {MAX_MULTI_PV}"),
+                    format!(
+                        "option name Skill Level type spin default {MAX_SKILL_LEVEL} min 0 max {MAX_SKILL_LEVEL}"
+                    ),
+                    "option name Ponder type check default false".to_string(),
+                ] {
+                    self.output.send(&option);
+                }
+                self.output.send("uciok");
+            }
+            UciCommand::Debug(debug) => self.debug = debug,
+            // The search runs on its own thread, so the engine is always
+            // ready to read the next command.
+            UciCommand::IsReady => self.output.send("readyok"),
+            // The searcher can't be changed while it's searching, and a
+            // search that is infinite or pondering only ends when it's told
+            // to, so finish it first.
+            UciCommand::SetOption { name, value } => {
+                self.stop();
+                self.set_option(&name, value.as_deref());
+            }
+            UciCommand::UciNewGame => {
+                self.stop();
+                self.search.searcher().new_game();
+            }
+            UciCommand::Position { fen, moves } => self.set_position(fen, &moves),
+            UciCommand::Go {
+                mut limits,
+                search_moves,
+            } => {
+                limits.search_moves = self
+                    .board
+                    .possible_moves()
+                    .filter(|move_| search_moves.contains(&move_.to_uci()))
+                    .collect();
+                self.go(limits);
+            }
+            UciCommand::Stop => self.stop(),
+            UciCommand::PonderHit => self.search.ponder_hit(),
+            UciCommand::Quit => {
+                self.stop();
+                return false;
+            }
+            UciCommand::Unknown(line) => {
+                if self.debug && !line.is_empty() {
+                    self.output
+                        .send(&format!("info string unknown command '{line}'"));
+                }
+            }
+        }
+        true
+    }
+
+    fn set_option(&mut self, name: &str, value: Option<&str>) {
+        let number = value.and_then(|value| value.trim().parse::<usize>().ok());
+        match (name.to_lowercase().as_str(), number) {
+            ("hash", Some(size)) => self
+                .search
+                .searcher()
+                .set_hash_size(size.clamp(1, MAX_HASH_MB)),
+            ("threads", Some(threads)) => self
+                .search
+                .searcher()
+                .set_threads(threads.clamp(1, MAX_THREADS)),
+            ("multipv", Some(lines)) => self
+                .search
+                .searcher()
+                .set_multi_pv(lines.clamp(1, MAX_MULTI_PV)),
+            ("skill level", Some(level)) => {
+                let level = level.min(MAX_SKILL_LEVEL as usize) as u8;
+                let skill = (level < MAX_SKILL_LEVEL).then(|| Skill::new(level));
+                self.search.searcher().set_skill(skill);
+            }
+            // Pondering is up to the GUI, which sends `go ponder` when it
+            // wants the engine to ponder.
+            ("ponder", _) => {}
+            _ => self.output.send(&format!(
+                "info string ignoring option '{name}' with value '{}'",
+                value.unwrap_or_default()
+            )),
+        }
+    }
+
+    /// Sets up the position, stopping at the first move that can't be
+    /// played.
+    fn set_position(&mut self, fen: Option<String>, moves: &[String]) {
+        let mut board = match fen {
+            None => Board::default(),
+            Some(fen) => match Board::from_str(&fen) {
+                Ok(board) => board,
+                Err(_) => {
+                    self.output
+                        .send(&format!("info string invalid fen '{fen}'"));
+                    return;
+                }
+            },
+        };
+
+        for uci in moves {
+            let move_ = board.possible_moves().find(|move_| move_.to_uci() == *uci);
+            match move_ {
+                Some(move_) => board
+                    .make_move(move_)
+                    .expect("a move from the list of possible moves to be legal"),
+                None => {
+                    self.output
+                        .send(&format!("info string illegal move '{uci}'"));
+                    break;
+                }
+            }
+        }
+        self.board = board;
+    }
+
+    /// Starts searching the current position on another thread, which
+    /// sends the best move when it's done. A search that is infinite or
+    /// pondering doesn't send its move until it's told to stop, or until
+    /// the ponder hit for a pondering search.
+    fn go(&mut self, limits: Limits) {
+        self.stop();
+        let mut board = self.board.clone();
+        let output = self.output.clone();
+        self.stop_requested.store(false, Ordering::Relaxed);
+        let stop_requested = self.stop_requested.clone();
+
+        self.search.spawn(move |searcher| {
+            let infinite = limits.infinite;
+            let result = searcher.search(&mut board, limits);
+            while infinite && !stop_requested.load(Ordering::Relaxed) {
+                thread::sleep(POLL_INTERVAL);
+            }
+
+            let best_move = result
+                .best_move
+                .as_ref()
+                .map_or("0000".to_string(), |move_| move_.to_uci());
+            match result.ponder_move() {
+                Some(ponder) => {
+                    output.send(&format!("bestmove {best_move} ponder {}", ponder.to_uci()))
+                }
+                None => output.send(&format!("bestmove {best_move}")),
+            }
+        });
+    }
+
+    /// Stops the search in progress, if there is one, and waits for it to
+    /// send its best move.
+    fn stop(&mut self) {
+        self.stop_requested.store(true, Ordering::Relaxed);
+        self.search.stop();
+    }
+}
+
+impl<W: Write + Send + 'static> Protocol for Session<W> {
+    fn handle_line(&mut self, line: &str) -> bool {
+        self.handle(UciCommand::parse(line))
+    }
+
+    fn quit(&mut self) {
+        self.handle(UciCommand::Quit);
+    }
+}
+
+/// Formats a search report as an `info` line.
+fn info_line(info: &SearchInfo) -> String {
+    let score = match info.score {
+        Score::Centipawns(centipawns) => format!("cp {centipawns}"),
+        Score::Mate(moves) => format!("mate {moves}"),
+    };
+    let mut line = format!(
+        "info depth {} seldepth {} multipv {} score {score} nodes {} nps {} hashfull {} time {}",
+        info.depth,
+        info.seldepth,
+        info.multi_pv,
+        info.nodes,
+        info.nps,
+        info.hashfull,
+        info.elapsed.as_millis(),
+    );
+    if !info.pv_uci.is_empty() {
+        line.push_str(" pv ");
+        line.push_str(&info.pv_uci.join(" "));
+    }
+    line
+}
@@ -0,0 +1,193 @@
+use std::time::Duration;
+
+/// A command sent by the GUI. Commands the engine has no use for, such as
+/// `hard`, `random` or `accepted`, are read as [`XboardCommand::Ignored`],
+/// and anything else it doesn't know as [`XboardCommand::Unknown`] so that
+/// the GUI can be told about it.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum XboardCommand {
+    Xboard,
+    Protover(u32),
+    New,
+    Quit,
+    Force,
+    Go,
+    /// `?`: move now.
+    MoveNow,
+    Ping(String),
+    /// A move in coordinate notation, either after `usermove` or on its own
+    /// for GUIs that weren't told about the `usermove` feature.
+    UserMove(String),
+    Level(TimeControl),
+    /// `st`: the exact time to spend on each move.
+    SearchTime(Duration),
+    /// `sd`: the deepest the engine may search.
+    SearchDepth(usize),
+    /// `time`: the engine's clock.
+    Time(Duration),
+    /// `otim`: the opponent's clock.
+    OpponentTime(Duration),
+    Undo,
+    Remove,
+    SetBoard(String),
+    Post,
+    NoPost,
+    Result,
+    Ignored,
+    Unknown(String),
+}
+
+/// A conventional time control set with `level`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct TimeControl {
+    /// The number of moves in each period, or 0 if the base time has to
+    /// last the whole game.
+    pub(crate) moves_per_period: u32,
+    pub(crate) base: Duration,
+    pub(crate) increment: Duration,
+}
+
+impl XboardCommand {
+    pub(crate) fn parse(line: &str) -> Self {
+        let line = line.trim();
+        let (command, rest) = line.split_once(' ').unwrap_or((line, ""));
+        let rest = rest.trim();
+        let parsed = match command {
+            "xboard" => Some(XboardCommand::Xboard),
+            "protover" => rest.parse().ok().map(XboardCommand::Protover),
+            "new" => Some(XboardCommand::New),
+            "quit" => Some(XboardCommand::Quit),
+            "force" => Some(XboardCommand::Force),
+            "go" => Some(XboardCommand::Go),
+            "?" => Some(XboardCommand::MoveNow),
+            "ping" => Some(XboardCommand::Ping(rest.to_string())),
+            "usermove" if !rest.is_empty() => Some(XboardCommand::UserMove(rest.to_string())),
+            "level" => parse_level(rest).map(XboardCommand::Level),
+            "st" => rest
+                .parse::<f64>()
+                .ok()
+                .filter(|seconds| *seconds > 0.0)
+                .map(|seconds| XboardCommand::SearchTime(Duration::from_secs_f64(seconds))),
+            "sd" => rest.parse().ok().map(XboardCommand::SearchDepth),
+            "time" => centiseconds(rest).map(XboardCommand::Time),
+            "otim" => centiseconds(rest).map(XboardCommand::OpponentTime),
+            "undo" => Some(XboardCommand::Undo),
+            "remove" => Some(XboardCommand::Remove),
+            "setboard" if !rest.is_empty() => Some(XboardCommand::SetBoard(rest.to_string())),
+            "post" => Some(XboardCommand::Post),
+            "nopost" => Some(XboardCommand::NoPost),
+            "result" => Some(XboardCommand::Result),
+            "accepted" | "rejected" | "random" | "hard" | "easy" | "computer" | "name"
+            | "rating" | "ics" | "white" | "black" | "draw" | "" => Some(XboardCommand::Ignored),
+            _ if is_move(command) && rest.is_empty() => {
+                Some(XboardCommand::UserMove(command.to_string()))
+            }
+            _ => None,
+        };
+        parsed.unwrap_or_else(|| XboardCommand::Unknown(line.to_string()))
+    }
+}
+
+/// Reads `MPS BASE INC`, where the base time is in minutes, optionally
+/// followed by `:` and seconds, and the increment is in seconds.
+fn parse_level(rest: &str) -> Option<TimeControl> {
+    let mut fields = rest.split_whitespace();
+    let moves_per_period = fields.next()?.parse().ok()?;
+    let base = fields.next()?;
+    let base = match base.split_once(':') {
+        None => base.parse::<f64>().ok()? * 60.0,
+        Some((minutes, seconds)) => {
+            minutes.parse::<f64>().ok()? * 60.0 + seconds.parse::<f64>().ok()?
+        }
+    };
+    let increment = fields.next()?.parse::<f64>().ok()?;
+    if base < 0.0 || increment < 0.0 {
+        return None;
+    }
+
+    Some(TimeControl {
+        moves_per_period,
+        base: Duration::from_secs_f64(base),
+        increment: Duration::from_secs_f64(increment),
+    })
+}
+
+fn centiseconds(rest: &str) -> Option<Duration> {
+    let centiseconds = rest.parse::<i64>().ok()?;
+    Some(Duration::from_millis(centiseconds.max(0) as u64 * 10))
+}
+
+/// Whether the word looks like a move in coordinate notation, such as
+/// `e2e4` or `e7e8q`.
+fn is_move(word: &str) -> bool {
+    let bytes = word.as_bytes();
+    let square =
+        |file: u8, rank: u8| (b'a'..=b'h').contains(&file) && (b'1'..=b'8').contains(&rank);
+    (bytes.len() == 4 || bytes.len() == 5)
+        && square(bytes[0], bytes[1])
+        && square(bytes[2], bytes[3])
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{TimeControl, XboardCommand};
+
+    #[test]
+    fn parses_time_controls() {
+        assert_eq!(
+            XboardCommand::Level(TimeControl {
+                moves_per_period: 40,
+                base: Duration::from_secs(300),
+                increment: Duration::ZERO,
+            }),
+            XboardCommand::parse("level 40 5 0")
+        );
+        assert_eq!(
+            XboardCommand::Level(TimeControl {
+                moves_per_period: 0,
+                base: Duration::from_secs(90),
+                increment: Duration::from_secs(2),
+            }),
+            XboardCommand::parse("level 0 1:30 2")
+        );
+        assert_eq!(
+            XboardCommand::SearchTime(Duration::from_millis(1500)),
+            XboardCommand::parse("st 1.5")
+        );
+        assert_eq!(
+            XboardCommand::Time(Duration::from_secs(30)),
+            XboardCommand::parse("time 3000")
+        );
+        assert_eq!(XboardCommand::SearchDepth(7), XboardCommand::parse("sd 7"));
+    }
+
+    #[test]
+    fn parses_moves_and_positions() {
+        assert_eq!(
+            XboardCommand::UserMove("e7e8q".to_string()),
+            XboardCommand::parse("usermove e7e8q")
+        );
+        assert_eq!(
+            XboardCommand::UserMove("e2e4".to_string()),
+            XboardCommand::parse("e2e4")
+        );
+        assert_eq!(
+            XboardCommand::SetBoard("8/8/8/8/8/8/8/K1k5 w - - 0 1".to_string()),
+            XboardCommand::parse("setboard 8/8/8/8/8/8/8/K1k5 w - - 0 1")
+        );
+        assert_eq!(
+            XboardCommand::Protover(2),
+            XboardCommand::parse("protover 2")
+        );
+        assert_eq!(
+            XboardCommand::Ignored,
+            XboardCommand::parse("accepted setboard")
+        );
+        assert_eq!(
+            XboardCommand::Unknown("bogus 1".to_string()),
+            XboardCommand::parse("bogus 1")
+        );
+    }
+}
@@ -0,0 +1,91 @@
+mod command;
+mod session;
+
+use std::io;
+
+use session::Session;
+
+fn main() {
+    chess_protocol::run(io::stdin().lock(), io::stdout(), Session::new);
+}
+
+#[cfg(test)]
+mod tests {
+    use chess_protocol::testing::Engine;
+
+    use crate::session::Session;
+
+    #[test]
+    fn negotiates_features_and_plays_moves() {
+        let mut engine = Engine::start(Session::new);
+        engine.send("xboard");
+        engine.send("protover 2");
+        let features = engine.read_until("feature").pop().unwrap();
+        for feature in ["setboard=1", "usermove=1", "ping=1", "done=1"] {
+            assert!(features.contains(feature), "{features}");
+        }
+
+        // After `new` the engine plays black, so it answers the move.
+        engine.send("new");
+        engine.send("sd 2");
+        engine.send("usermove e2e4");
+        let reply = engine.read_until("move ").pop().unwrap();
+        assert!(reply.len() > "move ".len(), "{reply}");
+
+        // In force mode it only keeps track of the moves.
+        engine.send("force");
+        engine.send("usermove d2d4");
+        engine.send("usermove e2e5");
+        engine.send("ping 1");
+        assert_eq!(
+            vec!["Illegal move: e2e5", "pong 1"],
+            engine.read_until("pong")
+        );
+        engine.quit();
+    }
+
+    #[test]
+    fn takes_back_moves_and_posts_thinking() {
+        let mut engine = Engine::start(Session::new);
+        engine.send("protover 2");
+        engine.read_until("feature");
+
+        // After 1. f3 e5 2. g4 black mates with Qh4.
+        engine.send("force");
+        engine.send("setboard rnbqkbnr/pppp1ppp/8/4p3/6P1/5P2/PPPPP2P/RNBQKBNR b KQkq - 0 2");
+        engine.send("post");
+        engine.send("sd 3");
+        engine.send("go");
+        let lines = engine.read_until("move ");
+        assert_eq!("move d8h4", lines.last().unwrap());
+        assert!(
+            lines
+                .iter()
+                .any(|line| line.starts_with("1 100001 ") && line.ends_with(" Qh4#")),
+            "{lines:?}"
+        );
+        assert_eq!(vec!["0-1 {Black mates}"], engine.read_until("0-1"));
+
+        engine.send("force");
+        engine.send("nopost");
+        engine.send("undo");
+        engine.send("go");
+        assert_eq!(vec!["move d8h4"], engine.read_until("move "));
+        engine.read_until("0-1");
+
+        // Only the one move has been played since the position was set.
+        engine.send("force");
+        engine.send("remove");
+        engine.send("setboard not a position");
+        engine.send("ping 2");
+        assert_eq!(
+            vec![
+                "Error (no moves to take back): remove",
+                "tellusererror Illegal position",
+                "pong 2"
+            ],
+            engine.read_until("pong")
+        );
+        engine.quit();
+    }
+}
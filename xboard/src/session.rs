@@ -0,0 +1,378 @@
+use std::{
+    io::Write,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, MutexGuard,
+    },
+    time::Duration,
+};
+
+use chess_ai::{Limits, Score, SearchInfo, SearchInfoKind, Searcher};
+use chess_common::Player;
+use chess_core::{Board, SelectedMove};
+use chess_protocol::{Output, Protocol, SearchThread};
+
+use crate::command::{TimeControl, XboardCommand};
+
+/// The time control until the GUI sends `level` or `st`, which is also
+/// xboard's own default.
+const DEFAULT_TIME_CONTROL: TimeControl = TimeControl {
+    moves_per_period: 40,
+    base: Duration::from_secs(300),
+    increment: Duration::ZERO,
+};
+
+/// Mates are reported as this plus the number of moves to mate, as the
+/// protocol asks.
+const MATE_SCORE: i32 = 100_000;
+
+/// The position of the game, along with how far into the game it is. The
+/// board only knows the moves played since the position was set, so the
+/// plies before it are counted separately.
+#[derive(Clone)]
+struct Game {
+    board: Board,
+    /// The number of plies played since the start of the game, including
+    /// those before a position set with `setboard`.
+    plies: u32,
+}
+
+impl Game {
+    fn new() -> Self {
+        Self {
+            board: Board::default(),
+            plies: 0,
+        }
+    }
+
+    /// Sets up the game from a FEN, taking the plies already played from
+    /// its fullmove number.
+    fn from_fen(fen: &str) -> Option<Self> {
+        let board = Board::from_str(fen).ok()?;
+        let full_moves = fen
+            .split_whitespace()
+            .nth(5)
+            .and_then(|field| field.parse::<u32>().ok())
+            .unwrap_or(1)
+            .max(1);
+        let plies = (full_moves - 1) * 2 + u32::from(board.player_to_move() == Player::Black);
+        Some(Self { board, plies })
+    }
+
+    fn play(&mut self, move_: SelectedMove) {
+        self.board
+            .make_move(move_)
+            .expect("a move from the list of possible moves to be legal");
+        self.plies += 1;
+    }
+
+    fn undo(&mut self) -> bool {
+        if self.board.undo().is_err() {
+            return false;
+        }
+        self.plies = self.plies.saturating_sub(1);
+        true
+    }
+}
+
+/// The state of a game against a GUI: the position, which side the engine
+/// plays, the time control, and the search in progress if there is one.
+pub(crate) struct Session<W: Write + Send + 'static> {
+    output: Output<W>,
+    /// Shared with the search thread, which plays the engine's move on it.
+    game: Arc<Mutex<Game>>,
+    search: SearchThread,
+    /// Set when the search for the engine's move is abandoned, for example
+    /// by `force` or `undo`, so that its move isn't played.
+    cancelled: Arc<AtomicBool>,
+    post: Arc<AtomicBool>,
+    /// The side the engine plays, or None in force mode, where it only
+    /// keeps track of the moves it's given.
+    engine_side: Option<Player>,
+    time_control: Option<TimeControl>,
+    /// The exact time to spend on each move, set with `st`.
+    move_time: Option<Duration>,
+    depth: Option<usize>,
+    engine_clock: Option<Duration>,
+    opponent_clock: Option<Duration>,
+}
+
+impl<W: Write + Send + 'static> Session<W> {
+    pub(crate) fn new(output: Output<W>) -> Self {
+        let mut searcher = Searcher::new();
+        let post = Arc::new(AtomicBool::new(false));
+        let info_output = output.clone();
+        let info_post = post.clone();
+        searcher.set_info_callback(move |info| {
+            if info_post.load(Ordering::Relaxed)
+                && info.kind == SearchInfoKind::Iteration
+                && info.multi_pv == 1
+            {
+                info_output.send(&thinking_line(info));
+            }
+        });
+
+        Self {
+            output,
+            game: Arc::new(Mutex::new(Game::new())),
+            search: SearchThread::new(searcher),
+            cancelled: Arc::new(AtomicBool::new(false)),
+            post,
+            engine_side: Some(Player::Black),
+            time_control: Some(DEFAULT_TIME_CONTROL),
+            move_time: None,
+            depth: None,
+            engine_clock: None,
+            opponent_clock: None,
+        }
+    }
+
+    /// Handles one command, returning false once the GUI has asked the
+    /// engine to quit.
+    pub(crate) fn handle(&mut self, command: XboardCommand) -> bool {
+        match command {
+            XboardCommand::Xboard | XboardCommand::Ignored => {}
+            XboardCommand::Protover(version) => {
+                if version >= 2 {
+                    self.output.send(&format!(
+                        "feature myname=\"chess_ai {}\" ping=1 setboard=1 usermove=1 time=1 \
+                         san=0 draw=0 sigint=0 sigterm=0 reuse=1 analyze=0 colors=0 done=1",
+                        env!("CARGO_PKG_VERSION")
+                    ));
+                }
+            }
+            XboardCommand::New => {
+                self.finish_search(true);
+                *self.game() = Game::new();
+                self.search.searcher().new_game();
+                self.engine_side = Some(Player::Black);
+                self.depth = None;
+                self.engine_clock = None;
+                self.opponent_clock = None;
+            }
+            XboardCommand::Quit => {
+                self.finish_search(true);
+                return false;
+            }
+            XboardCommand::Force | XboardCommand::Result => {
+                self.finish_search(true);
+                self.engine_side = None;
+            }
+            XboardCommand::Go => {
+                self.finish_search(true);
+                let side = self.game().board.player_to_move();
+                self.engine_side = Some(side);
+                self.think();
+            }
+            XboardCommand::MoveNow => self.finish_search(false),
+            XboardCommand::Ping(id) => self.output.send(&format!("pong {id}")),
+            XboardCommand::UserMove(notation) => self.user_move(&notation),
+            XboardCommand::Level(time_control) => {
+                self.time_control = Some(time_control);
+                self.move_time = None;
+            }
+            XboardCommand::SearchTime(move_time) => {
+                self.move_time = Some(move_time);
+                self.time_control = None;
+            }
+            XboardCommand::SearchDepth(depth) => self.depth = Some(depth),
+            XboardCommand::Time(time) => self.engine_clock = Some(time),
+            XboardCommand::OpponentTime(time) => self.opponent_clock = Some(time),
+            XboardCommand::Undo => self.undo("undo", 1),
+            XboardCommand::Remove => self.undo("remove", 2),
+            XboardCommand::SetBoard(fen) => {
+                self.finish_search(true);
+                match Game::from_fen(&fen) {
+                    Some(game) => *self.game() = game,
+                    None => self.output.send("tellusererror Illegal position"),
+                }
+            }
+            XboardCommand::Post => self.post.store(true, Ordering::Relaxed),
+            XboardCommand::NoPost => self.post.store(false, Ordering::Relaxed),
+            XboardCommand::Unknown(line) => {
+                self.output
+                    .send(&format!("Error (unknown command): {line}"));
+            }
+        }
+        true
+    }
+
+    fn game(&self) -> MutexGuard<'_, Game> {
+        self.game.lock().expect("the search thread not to panic")
+    }
+
+    /// Stops the search in progress, if there is one, and waits for it.
+    /// Unless the search is cancelled, it still plays the best move it has
+    /// found.
+    fn finish_search(&mut self, cancel: bool) {
+        if cancel {
+            self.cancelled.store(true, Ordering::Relaxed);
+        }
+        self.search.stop();
+    }
+
+    /// Plays the opponent's move, and starts thinking if it's now the
+    /// engine's turn.
+    fn user_move(&mut self, notation: &str) {
+        self.finish_search(true);
+        let played = {
+            let mut game = self.game();
+            let move_ = game
+                .board
+                .possible_moves()
+                .find(|move_| move_.to_uci() == notation);
+            move_.map(|move_| game.play(move_)).is_some()
+        };
+
+        if !played {
+            self.output.send(&format!("Illegal move: {notation}"));
+        } else if self.engine_side == Some(self.game().board.player_to_move()) {
+            self.think();
+        }
+    }
+
+    /// Takes back `plies` moves, leaving the position unchanged if there
+    /// aren't that many to take back.
+    fn undo(&mut self, command: &str, plies: usize) {
+        self.finish_search(true);
+        let mut game = self.game();
+        let before = game.clone();
+        if (0..plies).any(|_| !game.undo()) {
+            *game = before;
+            drop(game);
+            self.output
+                .send(&format!("Error (no moves to take back): {command}"));
+        }
+    }
+
+    /// Starts searching for the engine's move on another thread, which
+    /// plays the move when it's done. If the game is already over, the
+    /// result is reported instead.
+    fn think(&mut self) {
+        let game = self.game().clone();
+        if let Some(result) = game_result(&game.board) {
+            self.output.send(result);
+            return;
+        }
+
+        let limits = self.limits(&game);
+        let shared_game = self.game.clone();
+        let output = self.output.clone();
+        self.cancelled.store(false, Ordering::Relaxed);
+        let cancelled = self.cancelled.clone();
+
+        self.search.spawn(move |searcher| {
+            let mut position = game.board;
+            let result = searcher.search(&mut position, limits);
+            let mut game = shared_game.lock().expect("the session not to panic");
+            if cancelled.load(Ordering::Relaxed) {
+                return;
+            }
+            if let Some(best_move) = result.best_move {
+                output.send(&format!("move {}", best_move.to_uci()));
+                game.play(best_move);
+                if let Some(result) = game_result(&game.board) {
+                    output.send(result);
+                }
+            }
+        });
+    }
+
+    /// Works out the limits of the engine's next search from `st`, `sd`
+    /// and the clocks.
+    fn limits(&self, game: &Game) -> Limits {
+        let mut limits = Limits {
+            depth: self.depth,
+            move_time: self.move_time,
+            ..Default::default()
+        };
+        if let Some(time_control) = self.time_control {
+            let engine_clock = self.engine_clock.unwrap_or(time_control.base);
+            let opponent_clock = self.opponent_clock.unwrap_or(time_control.base);
+            let (white_time, black_time) = match game.board.player_to_move() {
+                Player::White => (engine_clock, opponent_clock),
+                Player::Black => (opponent_clock, engine_clock),
+            };
+            limits.white_time = Some(white_time);
+            limits.black_time = Some(black_time);
+            limits.white_increment = Some(time_control.increment);
+            limits.black_increment = Some(time_control.increment);
+
+            if time_control.moves_per_period > 0 {
+                let moves_played = game.plies / 2;
+                limits.moves_to_go = Some(
+                    time_control.moves_per_period - moves_played % time_control.moves_per_period,
+                );
+            }
+        }
+        limits
+    }
+}
+
+impl<W: Write + Send + 'static> Protocol for Session<W> {
+    fn handle_line(&mut self, line: &str) -> bool {
+        self.handle(XboardCommand::parse(line))
+    }
+
+    fn quit(&mut self) {
+        self.handle(XboardCommand::Quit);
+    }
+}
+
+/// The result to report if the game is over in the position.
+fn game_result(board: &Board) -> Option<&'static str> {
+    if board.possible_moves().next().is_some() {
+        return None;
+    }
+    Some(match (board.is_check(), board.player_to_move()) {
+        (true, Player::White) => "0-1 {Black mates}",
+        (true, Player::Black) => "1-0 {White mates}",
+        (false, _) => "1/2-1/2 {Stalemate}",
+    })
+}
+
+/// Formats a search report as thinking output: the depth, the score in
+/// centipawns, the time in centiseconds, the nodes and the line.
+fn thinking_line(info: &SearchInfo) -> String {
+    let score = match info.score {
+        Score::Centipawns(centipawns) => centipawns,
+        Score::Mate(moves) if moves > 0 => MATE_SCORE + moves,
+        Score::Mate(moves) => -MATE_SCORE + moves,
+    };
+    format!(
+        "{} {score} {} {} {}",
+        info.depth,
+        info.elapsed.as_millis() / 10,
+        info.nodes,
+        info.pv_san.join(" ")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use chess_protocol::Output;
+
+    use super::Session;
+    use crate::command::XboardCommand;
+
+    #[test]
+    fn counts_moves_to_go_from_the_fullmove_number() {
+        let mut session = Session::new(Output::new(Vec::new()));
+        session.handle(XboardCommand::parse("level 40 5 0"));
+        session.handle(XboardCommand::parse(
+            "setboard 4k3/8/8/8/8/8/8/4K2R w K - 0 30",
+        ));
+        let game = session.game().clone();
+        assert_eq!(Some(11), session.limits(&game).moves_to_go);
+
+        session.handle(XboardCommand::parse("force"));
+        session.handle(XboardCommand::parse("usermove e1f1"));
+        let game = session.game().clone();
+        assert_eq!(Some(11), session.limits(&game).moves_to_go);
+
+        session.handle(XboardCommand::parse("usermove e8d8"));
+        let game = session.game().clone();
+        assert_eq!(Some(10), session.limits(&game).moves_to_go);
+    }
+}